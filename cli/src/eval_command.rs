@@ -0,0 +1,289 @@
+use std::{
+  collections::{BTreeMap, HashMap},
+  fs,
+};
+
+use summon_compiler::{
+  compile, resolve_entry_path, run_concrete, CompileOk, CompileOptions, RunConcreteOk,
+};
+use summon_vm::{
+  circuit::{Circuit, CircuitInput, CircuitNumber, NumberOrBool},
+  field_element::{parse_field_modulus, FieldElement},
+  fixed_width::FixedWidthNumber,
+  numeric_model::parse_numeric_model,
+  summon_type::{path_with_key, MAX_BITS},
+  vs_value::Val,
+};
+
+use crate::{
+  handle_diagnostics_cli,
+  read_json_arg::{exit_with_error, read_json_arg},
+};
+
+const USAGE: &str = "Usage: summonc eval main.ts --inputs json|FILE.json [--public-inputs json|FILE.json] [--field-modulus P] [--numeric-model MODEL] [--width N] [--concrete]
+       summonc eval circuit.txt circuit_info.json --inputs json|FILE.json [--width N]";
+
+/**
+ * Evaluates a circuit with the given inputs and prints the outputs as JSON. The circuit is either
+ * compiled from a program or read from `circuit.txt` and `circuit_info.json`.
+ *
+ * Inputs can be given as the leaves that appear in the circuit (e.g. `"xs[0]": 1`) or as the
+ * original structure (e.g. `"xs": [1, 2]`). Circuits with a field modulus are evaluated mod p (see
+ * `FieldElement`), and their inputs can also be decimal strings.
+ *
+ * With `--width N`, numbers wrap at `2^N` and follow the signedness of the inputs, matching the
+ * boolean circuit from `--boolify-width N --native-boolify` (see `FixedWidthNumber`).
+ *
+ * With `--concrete`, the program is run directly with the input values instead (see
+ * `run_concrete`), which gives reference outputs for checking the circuit.
+ */
+pub fn eval_command(args: &[String]) {
+  let mut paths = Vec::<&str>::new();
+  let mut inputs_arg = None;
+  let mut public_inputs_arg = None;
+  let mut concrete = false;
+  let mut width = None;
+  let mut numeric_model = None;
+  let mut options = CompileOptions::default();
+
+  let mut i = 0;
+
+  while i < args.len() {
+    match args[i].as_str() {
+      "--inputs" => {
+        inputs_arg = Some(arg_value(args, i));
+        i += 2;
+      }
+      "--public-inputs" => {
+        public_inputs_arg = Some(arg_value(args, i));
+        i += 2;
+      }
+      "--field-modulus" => {
+        let modulus =
+          parse_field_modulus(arg_value(args, i)).unwrap_or_else(|e| exit_with_error(&e));
+        options.field_modulus = Some(modulus);
+        i += 2;
+      }
+      "--numeric-model" => {
+        numeric_model = Some(arg_value(args, i));
+        i += 2;
+      }
+      "--width" => {
+        width = match arg_value(args, i).parse::<usize>() {
+          Ok(width) if (1..=MAX_BITS).contains(&width) => Some(width),
+          _ => exit_with_error(&format!("--width must be from 1 to {}", MAX_BITS)),
+        };
+        i += 2;
+      }
+      "--concrete" => {
+        concrete = true;
+        i += 1;
+      }
+      path => {
+        paths.push(path);
+        i += 1;
+      }
+    }
+  }
+
+  if let Some(numeric_model) = numeric_model {
+    options.numeric_model = parse_numeric_model(numeric_model, options.field_modulus.as_ref())
+      .unwrap_or_else(|e| exit_with_error(&e));
+  }
+
+  let Some(inputs_arg) = inputs_arg else {
+    exit_with_usage();
+  };
+
+  let mut input_leaves = HashMap::<String, serde_json::Value>::new();
+
+  for (name, value) in read_json_arg(inputs_arg, "inputs") {
+    flatten_input(&name, value, &mut input_leaves);
+  }
+
+  let public_inputs = match public_inputs_arg {
+    Some(arg) => read_json_arg(arg, "public inputs"),
+    None => HashMap::new(),
+  };
+
+  let circuit = match (paths.as_slice(), concrete) {
+    ([main_path], true) => {
+      let outputs = run_concrete_outputs(main_path, &public_inputs, &input_leaves, &options);
+      println!("{}", serde_json::to_string_pretty(&outputs).unwrap());
+      return;
+    }
+    ([main_path], false) => compile_circuit(main_path, &public_inputs, &options),
+    ([circuit_path, info_path], false) => read_circuit(circuit_path, info_path),
+    _ => exit_with_usage(),
+  };
+
+  for name in circuit.inputs.keys() {
+    if !input_leaves.contains_key(name) {
+      exit_with_error(&format!("Missing input: {}", name));
+    }
+  }
+
+  for name in input_leaves.keys() {
+    if !circuit.inputs.contains_key(name) {
+      exit_with_error(&format!("Unknown input: {}", name));
+    }
+  }
+
+  let outputs = match (&circuit.field_modulus, width) {
+    (Some(_), Some(_)) => exit_with_error("--width can't be used with a field modulus"),
+    (Some(modulus), None) => eval_outputs(
+      &circuit,
+      input_leaves,
+      modulus,
+      |_, value| FieldElement::from_input_json(value, modulus),
+      FieldElement::to_json,
+    ),
+    (None, Some(width)) => eval_outputs(
+      &circuit,
+      input_leaves,
+      &width,
+      |input, value| FixedWidthNumber::from_input_json(value, input.signed, width),
+      |value| value.to_json(width),
+    ),
+    (None, None) => eval_outputs(
+      &circuit,
+      input_leaves,
+      &(),
+      |_, value| NumberOrBool::from_input_json(value),
+      NumberOrBool::to_json,
+    ),
+  };
+
+  println!("{}", serde_json::to_string_pretty(&outputs).unwrap());
+}
+
+fn eval_outputs<N: CircuitNumber>(
+  circuit: &Circuit,
+  input_leaves: HashMap<String, serde_json::Value>,
+  params: &N::Params,
+  from_json: impl Fn(&CircuitInput, &serde_json::Value) -> Option<N>,
+  to_json: impl Fn(&N) -> serde_json::Value,
+) -> serde_json::Map<String, serde_json::Value> {
+  let mut inputs = BTreeMap::<String, N>::new();
+
+  for (name, value) in input_leaves {
+    let Some(number) = from_json(&circuit.inputs[&name], &value) else {
+      exit_with_error(&format!("Invalid value for input {}: {}", name, value));
+    };
+
+    inputs.insert(name, number);
+  }
+
+  circuit
+    .eval_with(&inputs, params)
+    .iter()
+    .map(|(name, value)| (name.clone(), to_json(value)))
+    .collect()
+}
+
+fn compile_circuit(
+  main_path: &str,
+  public_inputs: &HashMap<String, serde_json::Value>,
+  options: &CompileOptions,
+) -> Circuit {
+  let compile_result = compile(
+    resolve_entry_path(main_path),
+    public_inputs,
+    options,
+    |path| fs::read_to_string(path).map_err(|e| e.to_string()),
+  );
+
+  let diagnostics = match &compile_result {
+    Ok(ok) => &ok.diagnostics,
+    Err(err) => &err.diagnostics,
+  };
+
+  handle_diagnostics_cli(diagnostics);
+
+  let CompileOk { circuit, .. } = compile_result.expect("Error should have caused earlier exit");
+
+  circuit
+}
+
+fn run_concrete_outputs(
+  main_path: &str,
+  public_inputs: &HashMap<String, serde_json::Value>,
+  inputs: &HashMap<String, serde_json::Value>,
+  options: &CompileOptions,
+) -> serde_json::Map<String, serde_json::Value> {
+  let run_result = run_concrete(
+    resolve_entry_path(main_path),
+    public_inputs,
+    inputs,
+    options,
+    |path| fs::read_to_string(path).map_err(|e| e.to_string()),
+  );
+
+  let diagnostics = match &run_result {
+    Ok(ok) => &ok.diagnostics,
+    Err(err) => &err.diagnostics,
+  };
+
+  handle_diagnostics_cli(diagnostics);
+
+  let RunConcreteOk { outputs, .. } = run_result.expect("Error should have caused earlier exit");
+
+  outputs
+    .into_iter()
+    .map(|(name, value)| (name, concrete_to_json(&value)))
+    .collect()
+}
+
+fn concrete_to_json(value: &Val) -> serde_json::Value {
+  match value {
+    // Print integers without a decimal point
+    Val::Number(x) if x.fract() == 0.0 && x.abs() < 2f64.powi(53) => (*x as i64).into(),
+    _ => value.to_json().unwrap_or(serde_json::Value::Null),
+  }
+}
+
+fn read_circuit(circuit_path: &str, info_path: &str) -> Circuit {
+  let bristol = fs::read_to_string(circuit_path)
+    .unwrap_or_else(|e| exit_with_error(&format!("Failed to read {}: {}", circuit_path, e)));
+
+  let info = fs::read_to_string(info_path)
+    .map_err(|e| e.to_string())
+    .and_then(|info| serde_json::from_str::<serde_json::Value>(&info).map_err(|e| e.to_string()))
+    .unwrap_or_else(|e| exit_with_error(&format!("Failed to read {}: {}", info_path, e)));
+
+  Circuit::from_bristol(&bristol, &info).unwrap_or_else(|e| exit_with_error(&e))
+}
+
+fn flatten_input(
+  path: &str,
+  value: serde_json::Value,
+  leaves: &mut HashMap<String, serde_json::Value>,
+) {
+  match value {
+    serde_json::Value::Array(elements) => {
+      for (i, element) in elements.into_iter().enumerate() {
+        flatten_input(&format!("{}[{}]", path, i), element, leaves);
+      }
+    }
+    serde_json::Value::Object(properties) => {
+      for (key, value) in properties {
+        flatten_input(&path_with_key(path, &key), value, leaves);
+      }
+    }
+    _ => {
+      leaves.insert(path.to_string(), value);
+    }
+  }
+}
+
+fn arg_value(args: &[String], i: usize) -> &str {
+  match args.get(i + 1) {
+    Some(value) => value,
+    None => exit_with_usage(),
+  }
+}
+
+fn exit_with_usage() -> ! {
+  eprintln!("{}", USAGE);
+  std::process::exit(1);
+}
@@ -1,3 +1,7 @@
+mod eval_command;
 mod handle_diagnostics_cli;
+mod read_json_arg;
 
+pub use eval_command::eval_command;
 pub use handle_diagnostics_cli::handle_diagnostics_cli;
+pub use read_json_arg::read_json_arg;
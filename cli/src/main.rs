@@ -7,59 +7,91 @@ use std::{
 
 use boolify::boolify;
 use serde_json::to_string_pretty;
-use summon_cli::handle_diagnostics_cli;
-use summon_compiler::{bristol_depth, compile, resolve_entry_path, CompileOk};
+use summon_cli::{eval_command, handle_diagnostics_cli, read_json_arg};
+use summon_compiler::{
+  bristol_depth, compile, resolve_entry_path, BooleanMetrics, CompileOk, CompileOptions,
+};
+use summon_vm::{
+  bit_width::infer_bit_widths, boolean_circuit::to_boolean_bristol,
+  field_element::parse_field_modulus, numeric_model::parse_numeric_model,
+};
 
 fn main() {
   let args: Vec<String> = std::env::args().collect();
 
   if args.len() < 2 {
-    eprintln!("Usage: summonc main.ts [--public-inputs json|FILE.json] [--boolify-width WIDTH]");
+    eprintln!(
+      "Usage: summonc main.ts [--public-inputs json|FILE.json] [--boolify-width WIDTH|auto] [--native-boolify] [--error-flag] [--max-steps N|none] [--max-branches N|none] [--max-signals N|none] [--profile] [--metrics] [--optimize] [--minimize-depth] [--field-modulus P] [--numeric-model f64|uintN|intN|field]\n       summonc eval main.ts --inputs json|FILE.json"
+    );
     std::process::exit(1);
   }
 
+  if args[1] == "eval" {
+    eval_command(&args[2..]);
+    return;
+  }
+
   let mut public_inputs_path = None;
   let mut boolify_width = None;
+  let mut native_boolify = false;
+  let mut numeric_model = None;
+  let mut options = CompileOptions::default();
 
   for i in 2..args.len() {
     if args[i] == "--public-inputs" {
       public_inputs_path = Some(args.get(i + 1).expect("missing arg").clone());
+    } else if args[i] == "--native-boolify" {
+      native_boolify = true;
+    } else if args[i] == "--profile" {
+      options.profile = true;
+    } else if args[i] == "--metrics" {
+      options.metrics = true;
+    } else if args[i] == "--optimize" {
+      options.optimize = true;
+    } else if args[i] == "--minimize-depth" {
+      options.minimize_depth = true;
+    } else if args[i] == "--error-flag" {
+      options.error_flag_output = true;
+    } else if args[i] == "--max-steps" {
+      options.limits.steps = parse_limit(args.get(i + 1).expect("missing arg"));
+    } else if args[i] == "--max-branches" {
+      options.limits.branches = parse_limit(args.get(i + 1).expect("missing arg"));
+    } else if args[i] == "--max-signals" {
+      options.limits.signals = parse_limit(args.get(i + 1).expect("missing arg"));
+    } else if args[i] == "--field-modulus" {
+      let modulus = args.get(i + 1).expect("missing arg");
+
+      options.field_modulus = Some(parse_field_modulus(modulus).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+      }));
+    } else if args[i] == "--numeric-model" {
+      numeric_model = Some(args.get(i + 1).expect("missing arg"));
     } else if args[i] == "--boolify-width" {
-      boolify_width = Some(
-        args
-          .get(i + 1)
-          .expect("missing arg")
-          .parse::<usize>()
-          .expect("invalid usize"),
-      );
+      boolify_width = Some(match args.get(i + 1).expect("missing arg").as_str() {
+        "auto" => None,
+        width => Some(width.parse::<usize>().expect("invalid usize")),
+      });
     }
   }
 
-  let entry_point = resolve_entry_path(&args[1]);
-
-  let public_inputs: HashMap<String, serde_json::Value> = if let Some(path) = public_inputs_path {
-    if path.get(0..1) == Some("{") {
-      // if the first character is '{', we assume it's a json string
-      serde_json::from_str::<HashMap<String, serde_json::Value>>(&path)
-        .expect("Failed to parse public inputs string")
-    } else {
-      let path = Path::new(&path);
-
-      if !path.exists() {
-        eprintln!("Public inputs file does not exist: {}", path.display());
+  if let Some(numeric_model) = numeric_model {
+    // Parsed last because `field` uses the field modulus
+    options.numeric_model = parse_numeric_model(numeric_model, options.field_modulus.as_ref())
+      .unwrap_or_else(|e| {
+        eprintln!("{}", e);
         std::process::exit(1);
-      }
+      });
+  }
 
-      let file = File::open(path).expect("Failed to open public inputs file");
+  let entry_point = resolve_entry_path(&args[1]);
 
-      serde_json::from_reader::<_, HashMap<String, serde_json::Value>>(file)
-        .expect("Failed to parse public inputs file")
-    }
-  } else {
-    HashMap::new()
+  let public_inputs = match public_inputs_path {
+    Some(arg) => read_json_arg(&arg, "public inputs"),
+    None => HashMap::new(),
   };
 
-  let compile_result = compile(entry_point, &public_inputs, |path| {
+  let compile_result = compile(entry_point, &public_inputs, &options, |path| {
     fs::read_to_string(path).map_err(|e| e.to_string())
   });
 
@@ -73,8 +105,16 @@ fn main() {
   let CompileOk {
     circuit,
     diagnostics: _,
+    profile,
+    optimization,
+    mut metrics,
+    return_value,
   } = compile_result.expect("Error should have caused earlier exit");
 
+  if let Some(return_value) = return_value {
+    println!("Program output: {}", return_value);
+  }
+
   let output_dir = Path::new("output");
 
   if output_dir.exists() {
@@ -86,7 +126,59 @@ fn main() {
   let mut bristol_circuit = circuit.to_bristol();
 
   if let Some(boolify_width) = boolify_width {
-    bristol_circuit = boolify(&bristol_circuit, boolify_width)
+    if circuit.field_modulus.is_some() {
+      eprintln!("--boolify-width can't be used with --field-modulus");
+      std::process::exit(1);
+    }
+
+    let max_input_bit_width = circuit.max_input_bit_width();
+
+    let boolify_width = match (boolify_width, max_input_bit_width) {
+      (Some(width), Some(max_width)) if width < max_width => {
+        eprintln!(
+          "--boolify-width {} is too small for inputs declared with {} bits",
+          width, max_width
+        );
+        std::process::exit(1);
+      }
+      (Some(width), _) => width,
+      (None, Some(max_width)) => max_width,
+      (None, None) => {
+        eprintln!("--boolify-width auto requires inputs with declared widths (eg summon.uint(8))");
+        std::process::exit(1);
+      }
+    };
+
+    if options.metrics {
+      let inferred_bits: usize = infer_bit_widths(&circuit, boolify_width).iter().sum();
+
+      println!(
+        "Inferred wire bits: {} (vs {} at uniform width {})",
+        inferred_bits,
+        circuit.size * boolify_width,
+        boolify_width,
+      );
+    }
+
+    bristol_circuit = if native_boolify {
+      to_boolean_bristol(&circuit, boolify_width).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+      })
+    } else {
+      // Boolify's arithmetic is unsigned, so its `AShiftR` is already a logical shift
+      for gate in &mut bristol_circuit.gates {
+        if gate.op == "AUShiftR" {
+          gate.op = "AShiftR".to_string();
+        }
+      }
+
+      boolify(&bristol_circuit, boolify_width)
+    };
+  }
+
+  if let Some(optimization) = &optimization {
+    print!("Optimization:\n{}\n", optimization);
   }
 
   println!(
@@ -96,6 +188,18 @@ fn main() {
     bristol_depth(&bristol_circuit),
   );
 
+  if let Some(profile) = &profile {
+    print!("\n{}\n", profile);
+  }
+
+  if let Some(metrics) = &mut metrics {
+    if boolify_width.is_some() {
+      metrics.boolean = Some(BooleanMetrics::new(&bristol_circuit));
+    }
+
+    print!("\n{}\n", metrics);
+  }
+
   bristol_circuit
     .write_bristol(&mut BufWriter::new(
       File::create("output/circuit.txt").unwrap(),
@@ -105,7 +209,7 @@ fn main() {
 
   fs::write(
     "output/circuit_info.json",
-    to_string_pretty(&bristol_circuit.info).unwrap(),
+    to_string_pretty(&circuit.info_json(&bristol_circuit.info)).unwrap(),
   )
   .unwrap();
   println!("output/circuit_info.json");
@@ -116,4 +220,21 @@ fn main() {
   )
   .unwrap();
   println!("output/mpc_settings.json");
+
+  if let Some(profile) = &profile {
+    fs::write("output/profile.json", to_string_pretty(profile).unwrap()).unwrap();
+    println!("output/profile.json");
+  }
+
+  if let Some(metrics) = &metrics {
+    fs::write("output/metrics.json", to_string_pretty(metrics).unwrap()).unwrap();
+    println!("output/metrics.json");
+  }
+}
+
+fn parse_limit(arg: &str) -> Option<usize> {
+  match arg {
+    "none" => None,
+    limit => Some(limit.parse::<usize>().expect("invalid usize")),
+  }
 }
@@ -0,0 +1,29 @@
+use std::{collections::HashMap, fs::File, path::Path};
+
+/**
+ * Reads a JSON object of named values from an argument that is either the JSON itself (if it
+ * starts with `{`) or the path of a JSON file. Exits with an error message on failure.
+ */
+pub fn read_json_arg(arg: &str, description: &str) -> HashMap<String, serde_json::Value> {
+  if arg.get(0..1) == Some("{") {
+    return serde_json::from_str::<HashMap<String, serde_json::Value>>(arg)
+      .unwrap_or_else(|e| exit_with_error(&format!("Failed to parse {}: {}", description, e)));
+  }
+
+  let path = Path::new(arg);
+
+  if !path.exists() {
+    exit_with_error(&format!("File does not exist: {}", path.display()));
+  }
+
+  let file = File::open(path)
+    .unwrap_or_else(|e| exit_with_error(&format!("Failed to open {} file: {}", description, e)));
+
+  serde_json::from_reader::<_, HashMap<String, serde_json::Value>>(file)
+    .unwrap_or_else(|e| exit_with_error(&format!("Failed to parse {} file: {}", description, e)))
+}
+
+pub(crate) fn exit_with_error(message: &str) -> ! {
+  eprintln!("{}", message);
+  std::process::exit(1);
+}
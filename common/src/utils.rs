@@ -60,5 +60,13 @@ pub struct InputDescriptor {
   pub from: String,
   pub name: String,
   pub type_json: serde_json::Value,
+  pub bit_width: Option<usize>, // None for `number`, which doesn't declare a width
+  pub signed: bool,
   pub id: usize,
 }
+
+#[derive(Clone)]
+pub struct OutputDescriptor {
+  pub to: Option<Vec<String>>, // None means the output is public
+  pub name: String,
+}
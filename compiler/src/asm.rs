@@ -2,6 +2,7 @@ use std::{
   collections::HashMap,
   fmt::Write,
   hash::{Hash as HashTrait, Hasher},
+  rc::Rc,
 };
 
 use num_bigint::BigInt;
@@ -195,6 +196,28 @@ impl Module {
   ) -> &'a DefinitionContent {
     &self.definitions[*ptr_to_index.get(ptr).unwrap()].content
   }
+
+  /**
+   * Fills in the path of the span markers (`FnLine::Span`). Modules are compiled without knowing
+   * their path, so this happens when they are gathered.
+   */
+  pub fn set_source_path(&mut self, path: &str) {
+    let path: Rc<str> = path.into();
+
+    for defn in &mut self.definitions {
+      let body = match &mut defn.content {
+        DefinitionContent::Function(fn_) => &mut fn_.body,
+        DefinitionContent::Lazy(lazy) => &mut lazy.body,
+        DefinitionContent::Meta(_) | DefinitionContent::Value(_) => continue,
+      };
+
+      for line in body {
+        if let FnLine::Span(source_span) = line {
+          source_span.path = Some(path.clone());
+        }
+      }
+    }
+  }
 }
 
 impl StructuredFormattable for Module {
@@ -510,6 +533,7 @@ pub enum FnLine {
   Empty,
   Comment(String),
   Release(Register),
+  Span(SourceSpan),
 }
 
 impl StructuredFormattable for FnLine {
@@ -520,10 +544,34 @@ impl StructuredFormattable for FnLine {
       FnLine::Empty => Ok(()),
       FnLine::Comment(message) => sf.write(&format!("// {}", message)),
       FnLine::Release(reg) => sf.write(&format!("(release {})", Structured(reg))),
+      FnLine::Span(source_span) => sf.write(source_span),
     }
   }
 }
 
+/**
+ * Marks the following instructions as coming from `span` of the file at `path`. These become the
+ * source map that's used to report runtime errors.
+ */
+#[derive(Debug, Clone)]
+pub struct SourceSpan {
+  pub path: Option<Rc<str>>,
+  pub span: swc_common::Span,
+}
+
+impl StructuredFormattable for SourceSpan {
+  fn structured_fmt(&self, sf: &mut StructuredFormatter<'_, '_>) -> std::fmt::Result {
+    sf.write("(span ")?;
+
+    if let Some(path) = &self.path {
+      sf.write(&serde_json::to_string(path.as_ref()).expect("Failed json serialization"))?;
+      sf.write(" ")?;
+    }
+
+    sf.write(&format!("{}..{})", self.span.lo.0, self.span.hi.0))
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct Label {
   pub name: String,
@@ -6,14 +6,23 @@ use std::{
 use num_bigint::{BigInt, Sign};
 
 use summon_common::BuiltinName;
+use summon_vm::source_map::{SourceLocation, SourceMap};
 
 use crate::asm::{
   Array, Builtin, Class, ContentHashable, Definition, DefinitionContent, FnLine, Function, Hash,
-  Instruction, Label, LabelRef, Lazy, Meta, Module, Number, Object, Pointer, Register, Structured,
-  StructuredFormattable, Value,
+  Instruction, Label, LabelRef, Lazy, Meta, Module, Number, Object, Pointer, Register, SourceSpan,
+  Structured, StructuredFormattable, Value,
 };
 
 pub fn assemble(module: &Module) -> Vec<u8> {
+  assemble_with_source_map(module).0
+}
+
+/**
+ * Like `assemble`, but also produces a map from bytecode positions to the source code, based on the
+ * span markers (`FnLine::Span`) in the module.
+ */
+pub fn assemble_with_source_map(module: &Module) -> (Vec<u8>, SourceMap) {
   let mut assembler = Assembler {
     output: Vec::new(),
     fn_data: Default::default(),
@@ -21,21 +30,33 @@ pub fn assemble(module: &Module) -> Vec<u8> {
       references: HashMap::new(),
       found_locations: HashMap::new(),
     },
+    source_map: SourceMap::default(),
+    meta_names: HashMap::new(),
   };
 
   assembler.module(module);
 
-  assembler.output
+  (assembler.output, assembler.source_map)
 }
 
 struct Assembler {
   output: Vec<u8>,
   fn_data: AssemblerFnData,
   definitions_map: LocationMap,
+  source_map: SourceMap,
+  meta_names: HashMap<Pointer, String>,
 }
 
 impl Assembler {
   fn module(&mut self, module: &Module) {
+    for definition in &module.definitions {
+      if let DefinitionContent::Meta(meta) = &definition.content {
+        self
+          .meta_names
+          .insert(definition.pointer.clone(), meta.name.clone());
+      }
+    }
+
     self.value(&module.export_default);
     self.output.push(ValueType::ExportStar as u8);
     self.varsize_uint(module.export_star.includes.len());
@@ -76,6 +97,13 @@ impl Assembler {
   }
 
   fn function(&mut self, function: &Function) {
+    let name = match &function.meta {
+      Some(p) => self.meta_names.get(p).map_or("", |name| name.as_str()),
+      None => "",
+    };
+
+    self.source_map.start_function(self.output.len(), name);
+
     self.output.push(match function.is_generator {
       false => ValueType::Function,
       true => ValueType::GeneratorFunction,
@@ -120,6 +148,9 @@ impl Assembler {
         FnLine::Label(label) => {
           self.label(label);
         }
+        FnLine::Span(source_span) => {
+          self.source_span(source_span);
+        }
         FnLine::Empty | FnLine::Comment(..) | FnLine::Release(..) => {}
       }
     }
@@ -133,6 +164,19 @@ impl Assembler {
     self.fn_data.labels_map.resolve(&mut self.output);
   }
 
+  fn source_span(&mut self, source_span: &SourceSpan) {
+    if let Some(path) = &source_span.path {
+      self.source_map.set_location(
+        self.output.len(),
+        SourceLocation {
+          path: path.clone(),
+          lo: source_span.span.lo.0,
+          hi: source_span.span.hi.0,
+        },
+      );
+    }
+  }
+
   fn meta(&mut self, meta: &Meta) {
     self.output.push(ValueType::Meta as u8);
 
@@ -168,6 +212,8 @@ impl Assembler {
   }
 
   fn lazy(&mut self, lazy: &Lazy) {
+    self.source_map.start_function(self.output.len(), "");
+
     self.output.push(ValueType::Lazy as u8);
 
     self.fn_data = Default::default();
@@ -182,6 +228,9 @@ impl Assembler {
         FnLine::Label(label) => {
           self.label(label);
         }
+        FnLine::Span(source_span) => {
+          self.source_span(source_span);
+        }
         FnLine::Empty | FnLine::Comment(..) | FnLine::Release(..) => {}
       }
     }
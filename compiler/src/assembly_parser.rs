@@ -3,11 +3,12 @@ use std::str::FromStr;
 
 use num_bigint::BigInt;
 use summon_common::{InstructionByte, BUILTIN_NAMES};
+use swc_common::{BytePos, Span};
 
 use crate::asm::{
   Array, Builtin, Class, ContentHashable, Definition, DefinitionContent, ExportStar, FnLine,
   Function, Hash, Instruction, Label, LabelRef, Meta, Module, Number, Object, Pointer, Register,
-  Value,
+  SourceSpan, Value,
 };
 
 pub struct AssemblyParser<'a> {
@@ -500,6 +501,13 @@ impl AssemblyParser<'_> {
         continue;
       }
 
+      if c == '(' && self.test_chars("(span") {
+        let source_span = self.assemble_source_span();
+        function.body.push(FnLine::Span(source_span));
+
+        continue;
+      }
+
       if c == '(' {
         self.parse_exact("(release");
         self.parse_whitespace();
@@ -524,6 +532,48 @@ impl AssemblyParser<'_> {
     function
   }
 
+  fn assemble_source_span(&mut self) -> SourceSpan {
+    self.parse_exact("(span");
+    self.parse_whitespace();
+
+    let path = match self.pos.peek() {
+      Some('"') => {
+        let path = self.parse_string_literal();
+        self.parse_whitespace();
+        Some(path.into())
+      }
+      _ => None,
+    };
+
+    let lo = self.parse_u32();
+    self.parse_exact("..");
+    let hi = self.parse_u32();
+    self.parse_exact(")\n");
+
+    SourceSpan {
+      path,
+      span: Span::new(BytePos(lo), BytePos(hi)),
+    }
+  }
+
+  fn parse_u32(&mut self) -> u32 {
+    let mut digits = String::new();
+
+    while let Some(&c) = self.pos.peek() {
+      if !c.is_ascii_digit() {
+        break;
+      }
+
+      digits.push(c);
+      self.pos.next();
+    }
+
+    match digits.parse::<u32>() {
+      Ok(value) => value,
+      Err(_) => panic!("{}", self.render_pos(0, "Expected u32")),
+    }
+  }
+
   fn assemble_fn_meta(&mut self) -> Meta {
     self.parse_exact("meta {");
     self.parse_optional_whitespace();
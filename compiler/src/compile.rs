@@ -3,23 +3,49 @@ use std::{cell::RefCell, collections::BTreeMap, collections::HashMap, rc::Rc};
 
 use crate::asm::Module;
 use crate::summon_io::SummonIO;
-use summon_common::InputDescriptor;
+use num_bigint::BigUint;
+use summon_common::{InputDescriptor, OutputDescriptor};
 use summon_vm::circuit::{CircuitInput, MpcSettings};
-use summon_vm::vs_value::{ToDynamicVal, Val};
+use summon_vm::source_map::StackTraceFrame;
+use summon_vm::summon_type::SummonType;
+use summon_vm::vs_value::{ToDynamicVal, ToVal, Val};
 use summon_vm::{
-  circuit::Circuit, circuit_builder::CircuitBuilder, circuit_vm::CircuitVM,
-  id_generator::IdGenerator, Bytecode, DecoderMaker,
+  circuit::Circuit,
+  circuit_builder::CircuitBuilder,
+  circuit_optimizer::{optimize_circuit, OptimizationReport, OptimizeOptions},
+  circuit_vm::{CircuitVM, CircuitVMLimits},
+  numeric_model::NumericModel,
+  signal_arena::SignalArena,
+  Bytecode, DecoderMaker,
 };
-use swc_common::DUMMY_SP;
+use swc_common::{BytePos, Span, DUMMY_SP};
 
 use crate::{
-  asm, assembler::assemble, diagnostic::DiagnosticLevel, gather_modules, link_module, Diagnostic,
-  ResolvedPath,
+  asm,
+  assembler::assemble_with_source_map,
+  diagnostic::DiagnosticLevel,
+  diagnostics_by_path::{display_path, pos_to_line_col},
+  gather_modules, link_module,
+  metrics::CircuitMetrics,
+  profile::CircuitProfile,
+  Diagnostic, ResolvedPath,
 };
 
 pub struct CompileOk {
   pub circuit: Circuit,
   pub diagnostics: HashMap<ResolvedPath, Vec<Diagnostic>>,
+
+  /** Present when `CompileOptions::profile` is set. */
+  pub profile: Option<CircuitProfile>,
+
+  /** Present when `CompileOptions::optimize` is set. */
+  pub optimization: Option<OptimizationReport>,
+
+  /** Present when `CompileOptions::metrics` is set. */
+  pub metrics: Option<CircuitMetrics>,
+
+  /** The value returned by the main function (pretty printed), which isn't part of the circuit. */
+  pub return_value: Option<String>,
 }
 
 #[derive(Debug)]
@@ -30,6 +56,83 @@ pub struct CompileErr {
 
 pub type CompileResult = Result<CompileOk, CompileErr>;
 
+#[derive(Clone, Debug)]
+pub struct CompileOptions {
+  /**
+   * Instead of failing when an exception is thrown under a signal-dependent condition, add a
+   * public `error` output which is true for the inputs that throw. When it is true, all other
+   * outputs are zero (or false).
+   */
+  pub error_flag_output: bool,
+
+  /**
+   * Compilation fails when one of these is exceeded, pointing at the conditional jump that keeps
+   * forking. Without them, a loop like `for (let i = 0; i < input; i++)` compiles forever.
+   */
+  pub limits: CircuitVMLimits,
+
+  /**
+   * Track which function and source line created each gate, and produce a `CircuitProfile`. This
+   * makes compilation slower.
+   */
+  pub profile: bool,
+
+  /**
+   * Produce `CircuitMetrics` (non-linear gate counts, multiplicative depth per output, the
+   * critical path, etc). Like `profile`, this tracks where signals come from, which makes
+   * compilation slower.
+   */
+  pub metrics: bool,
+
+  /**
+   * Compute over the integers mod this prime, as arithmetic MPC and ZK backends do. Constants are
+   * encoded as field elements (e.g. `-1` becomes `p - 1`) and the modulus is recorded in the
+   * circuit info. See `FieldElement`.
+   */
+  pub field_modulus: Option<BigUint>,
+
+  /**
+   * How operations on numbers known at compile time are computed (JavaScript's `f64` by default).
+   * Choosing the circuit's arithmetic means code gives the same results whether or not its values
+   * depend on the inputs. See `NumericModel`.
+   */
+  pub numeric_model: NumericModel,
+
+  /**
+   * Run `optimize_circuit` on the generated circuit (constant folding, common subexpression
+   * elimination, etc), and report the gate counts of each pass.
+   */
+  pub optimize: bool,
+
+  /**
+   * Also rebalance chains like `sum += x` into trees to reduce depth (see `OptimizeOptions`).
+   * Implies `optimize`.
+   */
+  pub minimize_depth: bool,
+}
+
+impl Default for CompileOptions {
+  fn default() -> Self {
+    CompileOptions {
+      error_flag_output: false,
+      limits: CircuitVMLimits {
+        steps: Some(100_000_000),
+        branches: Some(10_000),
+        // Each signal takes about 150 bytes in the `SignalArena` (its node and its interning
+        // entry), so this stops a runaway program at around 1.5GB. Circuits that legitimately
+        // need more can raise it with `--max-signals`.
+        signals: Some(10_000_000),
+      },
+      profile: false,
+      metrics: false,
+      field_modulus: None,
+      numeric_model: NumericModel::Float64,
+      optimize: false,
+      minimize_depth: false,
+    }
+  }
+}
+
 pub struct CompileLinkedModuleResult {
   pub module: Option<Module>,
   pub diagnostics: HashMap<ResolvedPath, Vec<Diagnostic>>,
@@ -38,42 +141,52 @@ pub struct CompileLinkedModuleResult {
 pub fn compile<ReadFile>(
   path: ResolvedPath,
   public_inputs: &HashMap<String, serde_json::Value>,
+  options: &CompileOptions,
   read_file: ReadFile,
 ) -> CompileResult
 where
   ReadFile: Fn(&str) -> Result<String, String>,
 {
   let CompileArtifacts {
-    main_asm,
     main,
     mut diagnostics,
-  } = get_compile_artifacts(path.clone(), read_file)?;
+  } = get_compile_artifacts(path.clone(), &options.numeric_model, &read_file)?;
 
-  if main_asm.parameters.len() != 1 {
-    diagnostics.entry(path).or_default().push(Diagnostic {
-      level: DiagnosticLevel::Error,
-      message: format!(
-        "number of main function arguments ({}) is not 1",
-        main_asm.parameters.len()
-      ),
-      span: DUMMY_SP,
-    });
+  let signal_arena = Rc::new(RefCell::new(SignalArena::new()));
+  let io = SummonIO::new(&json_to_vals(public_inputs), &signal_arena);
 
-    return Err(CompileErr {
-      circuit: None,
-      diagnostics,
-    });
-  }
+  let RunOk {
+    return_value,
+    error_flag,
+  } = match run(main, &io, &signal_arena, options) {
+    Ok(run_ok) => run_ok,
+    Err((message, stack_trace)) => {
+      let (error_path, diagnostic) = stack_trace_diagnostic(message, &stack_trace, &read_file);
 
-  let id_gen = Rc::new(RefCell::new(IdGenerator::new()));
+      diagnostics
+        .entry(error_path.unwrap_or(path))
+        .or_default()
+        .push(diagnostic);
 
-  let public_inputs = public_inputs
-    .iter()
-    .map(|(name, value)| (name.clone(), Val::from_json(value)))
-    .collect::<HashMap<_, _>>();
+      return Err(CompileErr {
+        circuit: None,
+        diagnostics,
+      });
+    }
+  };
 
-  let io = SummonIO::new(&public_inputs, &id_gen);
-  run(main, &io);
+  if let Some(error_flag) = error_flag {
+    if let Err(err) = io.data.borrow_mut().add_error_output(&error_flag) {
+      diagnostics
+        .entry(path.clone())
+        .or_default()
+        .push(Diagnostic {
+          level: DiagnosticLevel::Error,
+          message: format!("Failed to add error output: {}", err.pretty()),
+          span: DUMMY_SP,
+        });
+    }
+  }
 
   for unused_input in io.unused_public_inputs() {
     let unused_path = ResolvedPath {
@@ -90,17 +203,65 @@ where
       });
   }
 
-  let (parties, input_descriptors, outputs, builder) = build(io);
-  let circuit = generate_circuit(parties, input_descriptors, outputs, builder);
+  let mut built = match build(io, options.field_modulus.clone()) {
+    Ok(built) => built,
+    Err(err) => {
+      diagnostics.entry(path).or_default().push(Diagnostic {
+        level: DiagnosticLevel::Error,
+        message: format!("Failed to build circuit: {}", err.pretty()),
+        span: DUMMY_SP,
+      });
 
-  if diagnostics.iter().any(|(_, path_diagnostics)| {
-    path_diagnostics.iter().any(|diagnostic| {
-      matches!(
-        diagnostic.level,
-        DiagnosticLevel::Error | DiagnosticLevel::InternalError
-      )
-    })
-  }) {
+      return Err(CompileErr {
+        circuit: None,
+        diagnostics,
+      });
+    }
+  };
+
+  let mut gate_origins = take(&mut built.builder.gate_origins);
+
+  let mut circuit = match generate_circuit(built) {
+    Ok(circuit) => circuit,
+    Err(err) => {
+      diagnostics.entry(path).or_default().push(Diagnostic {
+        level: DiagnosticLevel::Error,
+        message: format!("Failed to build circuit: {}", err),
+        span: DUMMY_SP,
+      });
+
+      return Err(CompileErr {
+        circuit: None,
+        diagnostics,
+      });
+    }
+  };
+
+  let optimization = (options.optimize || options.minimize_depth).then(|| {
+    let optimize_options = OptimizeOptions {
+      minimize_depth: options.minimize_depth,
+    };
+
+    let report = optimize_circuit(&mut circuit, &optimize_options);
+
+    gate_origins = report
+      .gate_sources
+      .iter()
+      .map(|i| gate_origins[*i].clone())
+      .collect();
+
+    report
+  });
+
+  let profile = options
+    .profile
+    .then(|| CircuitProfile::new(&circuit, &gate_origins, &read_file));
+
+  let metrics = options
+    .metrics
+    .then(|| CircuitMetrics::new(&circuit, &gate_origins, &read_file));
+
+  if has_errors(&diagnostics) {
     return Err(CompileErr {
       circuit: Some(circuit),
       diagnostics,
@@ -110,6 +271,114 @@ where
   Ok(CompileOk {
     circuit,
     diagnostics,
+    profile,
+    optimization,
+    metrics,
+    return_value: return_value.map(|value| value.pretty()),
+  })
+}
+
+pub struct RunConcreteOk {
+  pub outputs: BTreeMap<String, Val>,
+  pub diagnostics: HashMap<ResolvedPath, Vec<Diagnostic>>,
+}
+
+/**
+ * Runs the program with concrete values for `io.input` instead of signals, giving the outputs that
+ * the circuit should produce for these inputs. This is useful as a reference for checking
+ * circuits, since it doesn't involve branch merging or circuit generation.
+ *
+ * `inputs` uses the same names as the circuit inputs (e.g. `xs[0]` for array inputs), and the
+ * outputs are flattened the same way as the circuit outputs.
+ *
+ * Note that the outputs can legitimately differ from the circuit where Summon departs from
+ * JavaScript, e.g. `summon.isSignal` and out-of-range signal indexes. Exceptions always fail the
+ * run, even with `CompileOptions::error_flag_output`.
+ */
+pub fn run_concrete<ReadFile>(
+  path: ResolvedPath,
+  public_inputs: &HashMap<String, serde_json::Value>,
+  inputs: &HashMap<String, serde_json::Value>,
+  options: &CompileOptions,
+  read_file: ReadFile,
+) -> Result<RunConcreteOk, CompileErr>
+where
+  ReadFile: Fn(&str) -> Result<String, String>,
+{
+  let CompileArtifacts {
+    main,
+    mut diagnostics,
+  } = get_compile_artifacts(path.clone(), &options.numeric_model, &read_file)?;
+
+  let signal_arena = Rc::new(RefCell::new(SignalArena::new()));
+  let io = SummonIO::new_concrete(
+    &json_to_vals(public_inputs),
+    &json_to_vals(inputs),
+    &signal_arena,
+  );
+
+  let options = CompileOptions {
+    error_flag_output: false,
+    profile: false,
+    metrics: false,
+    ..options.clone()
+  };
+
+  if let Err((message, stack_trace)) = run(main, &io, &signal_arena, &options) {
+    let (error_path, diagnostic) = stack_trace_diagnostic(message, &stack_trace, &read_file);
+
+    diagnostics
+      .entry(error_path.unwrap_or(path))
+      .or_default()
+      .push(diagnostic);
+
+    return Err(CompileErr {
+      circuit: None,
+      diagnostics,
+    });
+  }
+
+  for unused_input in io.unused_concrete_inputs() {
+    diagnostics
+      .entry(path.clone())
+      .or_default()
+      .push(Diagnostic {
+        level: DiagnosticLevel::Error,
+        message: format!("Unused input: {}", unused_input),
+        span: DUMMY_SP,
+      });
+  }
+
+  if has_errors(&diagnostics) {
+    return Err(CompileErr {
+      circuit: None,
+      diagnostics,
+    });
+  }
+
+  let outputs = io.data.borrow().outputs.iter().cloned().collect();
+
+  Ok(RunConcreteOk {
+    outputs,
+    diagnostics,
+  })
+}
+
+fn json_to_vals(values: &HashMap<String, serde_json::Value>) -> HashMap<String, Val> {
+  values
+    .iter()
+    .map(|(name, value)| (name.clone(), Val::from_json(value)))
+    .collect()
+}
+
+fn has_errors(diagnostics: &HashMap<ResolvedPath, Vec<Diagnostic>>) -> bool {
+  diagnostics.iter().any(|(_, path_diagnostics)| {
+    path_diagnostics.iter().any(|diagnostic| {
+      matches!(
+        diagnostic.level,
+        DiagnosticLevel::Error | DiagnosticLevel::InternalError
+      )
+    })
   })
 }
 
@@ -121,7 +390,7 @@ where
   ReadFile: Fn(&str) -> Result<String, String>,
 {
   let gm = gather_modules(entry_point.clone(), read_file);
-  let mut link_module_result = link_module(&gm.entry_point, &gm.modules);
+  let mut link_module_result = link_module(&gm.entry_point, &gm.modules, &NumericModel::Float64);
 
   let mut result = CompileLinkedModuleResult {
     module: link_module_result.module,
@@ -138,26 +407,26 @@ where
 }
 
 struct CompileArtifacts {
-  main_asm: asm::Function,
   main: Val,
   diagnostics: HashMap<ResolvedPath, Vec<Diagnostic>>,
 }
 
 fn get_compile_artifacts<ReadFile>(
   path: ResolvedPath,
+  numeric_model: &NumericModel,
   read_file: ReadFile,
 ) -> Result<CompileArtifacts, CompileErr>
 where
   ReadFile: Fn(&str) -> Result<String, String>,
 {
   let gm = gather_modules(path.clone(), read_file);
-  let mut link_module_result = link_module(&gm.entry_point, &gm.modules);
+  let mut link_module_result = link_module(&gm.entry_point, &gm.modules, numeric_model);
 
   let module = link_module_result.module;
   let mut diagnostics = gm.diagnostics;
 
   diagnostics
-    .entry(path)
+    .entry(path.clone())
     .or_default()
     .append(&mut link_module_result.diagnostics);
 
@@ -171,31 +440,56 @@ where
     }
   };
 
-  let asm_fn = get_asm_main(&module);
+  let Some(main_asm) = get_asm_main(&module) else {
+    diagnostics.entry(path).or_default().push(Diagnostic {
+      level: DiagnosticLevel::Error,
+      message: "Default export is not a function".to_string(),
+      span: DUMMY_SP,
+    });
 
-  let bytecode = Rc::new(Bytecode::new(assemble(&module)));
+    return Err(CompileErr {
+      circuit: None,
+      diagnostics,
+    });
+  };
+
+  if main_asm.parameters.len() != 1 {
+    diagnostics.entry(path).or_default().push(Diagnostic {
+      level: DiagnosticLevel::Error,
+      message: format!(
+        "number of main function arguments ({}) is not 1",
+        main_asm.parameters.len()
+      ),
+      span: DUMMY_SP,
+    });
+
+    return Err(CompileErr {
+      circuit: None,
+      diagnostics,
+    });
+  }
+
+  let (code, source_map) = assemble_with_source_map(&module);
+  let bytecode = Rc::new(Bytecode::with_source_map(code, source_map));
 
   let val = bytecode.decoder(0).decode_val(&mut vec![]);
 
   Ok(CompileArtifacts {
-    main_asm: asm_fn.clone(),
     main: val,
     diagnostics,
   })
 }
 
-fn get_asm_main(module: &asm::Module) -> &asm::Function {
+fn get_asm_main(module: &asm::Module) -> Option<&asm::Function> {
   let main_ptr = match &module.export_default {
     asm::Value::Pointer(ptr) => ptr,
-    _ => panic!("Expected pointer"),
-  };
-
-  let fn_ = match resolve_ptr(module, main_ptr).unwrap() {
-    asm::DefinitionContent::Function(fn_) => fn_,
-    _ => panic!("Expected function"),
+    _ => return None,
   };
 
-  fn_
+  match resolve_ptr(module, main_ptr)? {
+    asm::DefinitionContent::Function(fn_) => Some(fn_),
+    _ => None,
+  }
 }
 
 fn resolve_ptr<'a>(
@@ -211,8 +505,35 @@ fn resolve_ptr<'a>(
   None
 }
 
-fn run(main: Val, io: &SummonIO) {
-  let mut vm = CircuitVM::default();
+struct RunOk {
+  return_value: Option<Val>,
+
+  /** See `CompileOptions::error_flag_output`. */
+  error_flag: Option<Val>,
+}
+
+/**
+ * Runs the main function, returning its return value and the error flag, or an error message along
+ * with where it happened (an uncaught exception or an exceeded limit).
+ */
+fn run(
+  main: Val,
+  io: &SummonIO,
+  signal_arena: &Rc<RefCell<SignalArena>>,
+  options: &CompileOptions,
+) -> Result<RunOk, (String, Vec<StackTraceFrame>)> {
+  let mut vm = CircuitVM {
+    limits: options.limits.clone(),
+    signal_arena: Some(signal_arena.clone()),
+    numeric_model: options.numeric_model.clone(),
+    ..Default::default()
+  };
+
+  if options.error_flag_output {
+    vm.error_flag = Some(false.to_val());
+  }
+
+  vm.track_signal_origins = options.profile || options.metrics;
 
   let res = vm.run(
     None,
@@ -221,47 +542,161 @@ fn run(main: Val, io: &SummonIO) {
     vec![io.clone().to_dynamic_val()],
   );
 
-  match &res {
-    Ok(Val::Void | Val::Undefined) => {}
-    Ok(return_value) => {
-      println!("Program output: {}", return_value.pretty());
-    }
-    Err(err) => {
-      eprintln!("Uncaught exception: {}", err.pretty());
-      std::process::exit(1);
+  // Signals created after this (e.g. for the error output) aren't from the program's code
+  signal_arena.borrow_mut().origin = None;
+
+  let res = match res {
+    Ok(res) => res,
+    Err(exception) => {
+      return Err(match take(&mut vm.limit_exceeded) {
+        Some(limit_exceeded) => (limit_exceeded.message, limit_exceeded.stack_trace),
+        None => (
+          format!("Uncaught exception: {}", exception.pretty()),
+          take(&mut vm.exception_stack_trace),
+        ),
+      });
     }
   };
+
+  let return_value = match res {
+    Val::Void | Val::Undefined => None,
+    return_value => Some(return_value),
+  };
+
+  Ok(RunOk {
+    return_value,
+    error_flag: vm.error_flag,
+  })
+}
+
+/**
+ * Reports an error at the innermost statement with a known location, followed by the call stack,
+ * e.g.
+ *
+ * ```text
+ * Uncaught exception: Error{"message":"oops"}
+ *   at check (src/check.ts:3:5)
+ *   at main (src/main.ts:7:3)
+ * ```
+ */
+fn stack_trace_diagnostic<ReadFile>(
+  mut message: String,
+  stack_trace: &[StackTraceFrame],
+  read_file: &ReadFile,
+) -> (Option<ResolvedPath>, Diagnostic)
+where
+  ReadFile: Fn(&str) -> Result<String, String>,
+{
+  let mut texts = HashMap::<Rc<str>, Option<String>>::new();
+
+  for frame in stack_trace {
+    let function_name = match frame.function_name.as_ref() {
+      "" => "<anonymous>",
+      name => name,
+    };
+
+    let Some(location) = &frame.location else {
+      message.push_str(&format!("\n  at {}", function_name));
+      continue;
+    };
+
+    let text = texts
+      .entry(location.path.clone())
+      .or_insert_with(|| read_file(&location.path).ok());
+
+    let (line, col) = match text {
+      Some(text) => pos_to_line_col(text, location.lo),
+      None => (0, 0),
+    };
+
+    message.push_str(&format!(
+      "\n  at {} ({}:{}:{})",
+      function_name,
+      display_path(&location.path),
+      line,
+      col
+    ));
+  }
+
+  let location = stack_trace.iter().find_map(|frame| frame.location.as_ref());
+
+  let diagnostic = Diagnostic {
+    level: DiagnosticLevel::Error,
+    message,
+    span: match location {
+      Some(location) => Span::new(BytePos(location.lo), BytePos(location.hi)),
+      None => DUMMY_SP,
+    },
+  };
+
+  let path = location.map(|location| ResolvedPath {
+    path: location.path.to_string(),
+  });
+
+  (path, diagnostic)
 }
 
-fn build(
-  io: SummonIO,
-) -> (
-  Vec<String>,
-  Vec<InputDescriptor>,
-  BTreeMap<String, usize>,
-  CircuitBuilder,
-) {
-  let mut builder = CircuitBuilder::default();
+/** What `build` takes from `SummonIO`, along with the gates for the outputs. */
+struct Built {
+  parties: Vec<String>,
+  input_descriptors: Vec<InputDescriptor>,
+  input_shapes: BTreeMap<String, SummonType>,
+  outputs: Vec<(String, usize)>,
+  output_descriptors: Vec<OutputDescriptor>,
+  output_shapes: BTreeMap<String, SummonType>,
+  builder: CircuitBuilder,
+}
+
+fn build(io: SummonIO, field_modulus: Option<BigUint>) -> Result<Built, Val> {
+  let mut builder = CircuitBuilder {
+    field_modulus,
+    ..Default::default()
+  };
+
   builder.include_inputs(&io.input_ids());
 
   let mut io_data = io.data.borrow_mut();
   let parties = take(&mut io_data.parties);
   let input_descriptors = take(&mut io_data.inputs);
-  let outputs = builder.include_outputs(&io_data.public_outputs);
+  let input_shapes = take(&mut io_data.input_shapes);
+  let output_descriptors = take(&mut io_data.output_descriptors);
+  let output_shapes = take(&mut io_data.output_shapes);
+  let outputs = builder.include_outputs(&io_data.outputs)?;
 
   drop(io_data);
   drop(io);
-  builder.drop_signal_data();
 
-  (parties, input_descriptors, outputs, builder)
+  Ok(Built {
+    parties,
+    input_descriptors,
+    input_shapes,
+    outputs,
+    output_descriptors,
+    output_shapes,
+    builder,
+  })
 }
 
-fn generate_circuit(
-  parties: Vec<String>,
-  input_descriptors: Vec<InputDescriptor>,
-  outputs: BTreeMap<String, usize>,
-  builder: CircuitBuilder,
-) -> Circuit {
+/** The shapes of composite inputs/outputs (see `Circuit::info_json`), without scalars. */
+fn composite_shapes(shapes: BTreeMap<String, SummonType>) -> BTreeMap<String, serde_json::Value> {
+  shapes
+    .into_iter()
+    .filter(|(_, shape)| !shape.is_scalar())
+    .map(|(name, shape)| (name, shape.to_json()))
+    .collect()
+}
+
+fn generate_circuit(built: Built) -> Result<Circuit, String> {
+  let Built {
+    parties,
+    input_descriptors,
+    input_shapes,
+    outputs,
+    output_descriptors,
+    output_shapes,
+    builder,
+  } = built;
+
   let mut inputs = BTreeMap::<String, CircuitInput>::new();
   for (i, desc) in input_descriptors.iter().enumerate() {
     inputs.insert(
@@ -269,6 +704,8 @@ fn generate_circuit(
       CircuitInput {
         wire_id: i,
         type_json: desc.type_json.clone(),
+        bit_width: desc.bit_width,
+        signed: desc.signed,
       },
     );
   }
@@ -278,14 +715,15 @@ fn generate_circuit(
     constants.insert(*wire_id, value.clone());
   }
 
-  let outputs_vec = outputs.keys().cloned().collect::<Vec<_>>();
-
-  Circuit {
+  Ok(Circuit {
     size: builder.wire_count,
     inputs,
     constants,
+    input_shapes: composite_shapes(input_shapes),
     outputs,
-    mpc_settings: MpcSettings::from_io(&parties, &input_descriptors, outputs_vec),
+    output_shapes: composite_shapes(output_shapes),
+    mpc_settings: MpcSettings::from_io(&parties, &input_descriptors, &output_descriptors)?,
     gates: builder.gates,
-  }
+    field_modulus: builder.field_modulus,
+  })
 }
@@ -43,25 +43,7 @@ fn handle_file_diagnostics(
   file_path: &String,
   diagnostics: &Vec<Diagnostic>,
 ) -> std::fmt::Result {
-  let path = 'b: {
-    if file_path == "(str)" {
-      // TODO: Fix this hack
-      break 'b None;
-    }
-
-    let current_dir = std::env::current_dir().expect("Failed to get current directory");
-    let abs_path = PathBuf::from(file_path);
-
-    Some(match abs_path.strip_prefix(&current_dir) {
-      Ok(p) => p.into(),
-      Err(_) => abs_path,
-    })
-  };
-
-  let path_str = match path {
-    Some(path) => path.to_string_lossy().to_string(),
-    None => file_path.clone(),
-  };
+  let path_str = display_path(file_path);
 
   let mut level_counts = HashMap::<DiagnosticLevel, usize>::new();
 
@@ -102,7 +84,25 @@ fn handle_file_diagnostics(
   Ok(())
 }
 
-fn pos_to_line_col(text: &str, pos: u32) -> (u32, u32) {
+/**
+ * Shows paths relative to the current directory when possible.
+ */
+pub(crate) fn display_path(file_path: &str) -> String {
+  if file_path == "(str)" {
+    // TODO: Fix this hack
+    return file_path.to_string();
+  }
+
+  let current_dir = std::env::current_dir().expect("Failed to get current directory");
+  let abs_path = PathBuf::from(file_path);
+
+  match abs_path.strip_prefix(&current_dir) {
+    Ok(p) => p.to_string_lossy().to_string(),
+    Err(_) => abs_path.to_string_lossy().to_string(),
+  }
+}
+
+pub(crate) fn pos_to_line_col(text: &str, pos: u32) -> (u32, u32) {
   let mut line = 1u32;
   let mut col = 1u32;
 
@@ -6,7 +6,7 @@ use swc_common::Spanned;
 
 use crate::asm::{
   Builtin, ContentHashable, Definition, DefinitionContent, FnLine, Function, Instruction, Label,
-  Meta, Pointer, Register, Value,
+  Meta, Pointer, Register, SourceSpan, Value,
 };
 use crate::diagnostic::{Diagnostic, DiagnosticContainer, DiagnosticReporter};
 use crate::expression_compiler::CompiledExpression;
@@ -127,6 +127,17 @@ impl<'a> FunctionCompiler<'a> {
     self.fn_.body.push(FnLine::Comment(message));
   }
 
+  /**
+   * Marks the code that follows as coming from `span`, so that runtime errors can be reported
+   * there.
+   */
+  pub fn source_span(&mut self, span: swc_common::Span) {
+    self
+      .fn_
+      .body
+      .push(FnLine::Span(SourceSpan { path: None, span }));
+  }
+
   pub fn lookup(&self, ident: &Ident) -> Option<&Name> {
     let name = self.mc.scope_analysis.lookup(ident);
 
@@ -281,6 +292,8 @@ impl<'a> FunctionCompiler<'a> {
           self.handle_block_body(block);
         }
         swc_ecma_ast::BlockStmtOrExpr::Expr(expr) => {
+          self.source_span(expr.span());
+
           let mut expression_compiler = ExpressionCompiler { fnc: self };
 
           expression_compiler.compile_into(expr, Register::return_());
@@ -480,6 +493,10 @@ impl<'a> FunctionCompiler<'a> {
   fn statement(&mut self, statement: &swc_ecma_ast::Stmt, fn_last: bool) {
     use swc_ecma_ast::Stmt::*;
 
+    if !matches!(statement, Block(_) | Empty(_)) {
+      self.source_span(statement.span());
+    }
+
     match statement {
       Block(block) => self.block_statement(block),
       Empty(_) => {}
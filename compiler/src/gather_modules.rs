@@ -78,6 +78,9 @@ where
     };
 
     let mut compiler_output = compile_module(&file_contents);
+    compiler_output
+      .module
+      .set_source_path(&dependency.path.path);
     // println!("{}: {}", dependency.path, compiler_output.module);
 
     gm.diagnostics
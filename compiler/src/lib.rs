@@ -14,9 +14,11 @@ mod ident;
 mod import_pattern;
 mod instruction;
 mod link_module;
+mod metrics;
 mod module_compiler;
 mod name_allocator;
 mod optimization;
+mod profile;
 mod resolve_entry_path;
 mod resolve_path;
 mod scope;
@@ -29,21 +31,30 @@ mod tests;
 mod util;
 mod visit_pointers;
 
-pub use assembler::assemble;
+pub use assembler::{assemble, assemble_with_source_map};
 pub use assembly_parser::parse_module;
 pub use bristol_depth::bristol_depth;
 pub use compile::compile;
 pub use compile::compile_linked_module;
+pub use compile::run_concrete;
 pub use compile::CompileErr;
 pub use compile::CompileOk;
+pub use compile::CompileOptions;
 pub use compile::CompileResult;
+pub use compile::RunConcreteOk;
 pub use diagnostic::Diagnostic;
 pub use diagnostic::DiagnosticLevel;
 pub use diagnostics_by_path::DiagnosticsByPath;
 pub use gather_modules::gather_modules;
 pub use link_module::link_module;
+pub use metrics::BooleanMetrics;
+pub use metrics::CircuitMetrics;
+pub use metrics::PathGate;
 pub use module_compiler::compile_module;
 pub use module_compiler::CompilerOutput;
+pub use profile::CircuitProfile;
+pub use profile::FunctionProfile;
+pub use profile::LineProfile;
 pub use resolve_entry_path::resolve_entry_path;
 pub use resolve_path::resolve_path;
 pub use resolve_path::ResolvedPath;
@@ -1,6 +1,7 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::mem::swap;
 
+use summon_vm::numeric_model::NumericModel;
 use tiny_keccak::{Hasher, Keccak};
 
 use crate::asm::{
@@ -24,6 +25,7 @@ pub struct LinkModuleResult {
 pub fn link_module(
   entry_point: &ResolvedPath,
   modules: &HashMap<ResolvedPath, PathAndModule>,
+  numeric_model: &NumericModel,
 ) -> LinkModuleResult {
   let mut result = LinkModuleResult {
     module: None,
@@ -115,7 +117,11 @@ pub fn link_module(
   collapse_pointers_of_pointers(&mut path_and_module.module);
   calculate_content_hashes(&mut path_and_module.module, &mut result.diagnostics);
 
-  optimize(&mut path_and_module.module, &mut pointer_allocator);
+  optimize(
+    &mut path_and_module.module,
+    &mut pointer_allocator,
+    numeric_model,
+  );
 
   result.module = Some(path_and_module.module);
   result
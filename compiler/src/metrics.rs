@@ -0,0 +1,371 @@
+use std::{
+  cmp::max,
+  collections::{BTreeMap, HashMap},
+  fmt,
+  rc::Rc,
+};
+
+use bristol_circuit::BristolCircuit;
+use serde::Serialize;
+use summon_vm::{circuit::Circuit, source_map::StackTraceFrame};
+
+use crate::{
+  diagnostics_by_path::display_path,
+  profile::{function_name, SourceLines},
+};
+
+/**
+ * Measures of what a circuit costs in MPC (see `CompileOptions::metrics`).
+ *
+ * Linear gates (addition, multiplication by a constant, etc, see `Circuit::is_linear`) are
+ * typically free, so the cost is mostly the non-linear gates, and the number of rounds follows
+ * the multiplicative depth: the most non-linear gates on any path from the inputs to an output.
+ */
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitMetrics {
+  pub gates: usize,
+  pub non_linear_gates: usize,
+  pub mult_depth: usize,
+  pub output_mult_depths: BTreeMap<String, usize>,
+
+  /** Gate counts by bristol operation, e.g. `AMul`. */
+  pub ops: BTreeMap<String, usize>,
+
+  /**
+   * The gates leading to the output with the largest multiplicative depth (then the largest
+   * depth), starting from the inputs.
+   */
+  pub critical_path: Vec<PathGate>,
+
+  /** Present when the circuit was boolified (set by the caller). */
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub boolean: Option<BooleanMetrics>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathGate {
+  pub op: String,
+  pub linear: bool,
+
+  /** The multiplicative depth of the gate's output. */
+  pub mult_depth: usize,
+
+  /** Where the gate was created, when signal origins were tracked. */
+  pub function: Option<String>,
+  pub path: Option<String>,
+  pub line: Option<u32>,
+}
+
+/**
+ * Like `CircuitMetrics`, for a boolean circuit, where `AND` (and `OR`) are the non-linear gates.
+ */
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BooleanMetrics {
+  pub gates: usize,
+  pub and_gates: usize,
+  pub and_depth: usize,
+  pub ops: BTreeMap<String, usize>,
+}
+
+impl CircuitMetrics {
+  /**
+   * `gate_origins` has an entry for each gate of the circuit (see `CircuitBuilder::gate_origins`).
+   */
+  pub fn new<ReadFile>(
+    circuit: &Circuit,
+    gate_origins: &[Option<Rc<StackTraceFrame>>],
+    read_file: &ReadFile,
+  ) -> CircuitMetrics
+  where
+    ReadFile: Fn(&str) -> Result<String, String>,
+  {
+    assert_eq!(circuit.gates.len(), gate_origins.len());
+
+    let mult_depths = circuit.mult_depths();
+    let mut depths = vec![0usize; circuit.size];
+    let mut producers = HashMap::<usize, usize>::new();
+    let mut ops = BTreeMap::<String, usize>::new();
+
+    for (i, gate) in circuit.gates.iter().enumerate() {
+      let mut depth = 0;
+      gate.for_each_input(|input| depth = max(depth, depths[input]));
+      depths[gate.output()] = depth + 1;
+
+      producers.insert(gate.output(), i);
+      *ops.entry(gate.bristol_op()).or_default() += 1;
+    }
+
+    let deepest = |wires: &mut dyn Iterator<Item = usize>| {
+      wires.fold(None, |best: Option<usize>, wire| match best {
+        Some(best) if (mult_depths[best], depths[best]) >= (mult_depths[wire], depths[wire]) => {
+          Some(best)
+        }
+        _ => Some(wire),
+      })
+    };
+
+    let mut path_gates = vec![];
+    let mut wire = deepest(&mut circuit.outputs.iter().map(|(_, wire)| *wire));
+
+    while let Some(gate_index) = wire.and_then(|wire| producers.get(&wire)) {
+      let gate = &circuit.gates[*gate_index];
+      let mut inputs = vec![];
+      gate.for_each_input(|input| inputs.push(input));
+
+      path_gates.push(*gate_index);
+      wire = deepest(&mut inputs.into_iter());
+    }
+
+    path_gates.reverse();
+
+    let mut source_lines = SourceLines::new(read_file);
+
+    let critical_path = path_gates
+      .into_iter()
+      .map(|i| {
+        let gate = &circuit.gates[i];
+        let origin = gate_origins[i].as_deref();
+        let location = origin.and_then(|origin| origin.location.as_ref());
+
+        PathGate {
+          op: gate.bristol_op(),
+          linear: circuit.is_linear(gate),
+          mult_depth: mult_depths[gate.output()],
+          function: origin.map(function_name),
+          path: location.map(|location| display_path(&location.path)),
+          line: location.map(|location| source_lines.line(location)),
+        }
+      })
+      .collect();
+
+    CircuitMetrics {
+      gates: circuit.gates.len(),
+      non_linear_gates: circuit
+        .gates
+        .iter()
+        .filter(|gate| !circuit.is_linear(gate))
+        .count(),
+      mult_depth: mult_depths.iter().copied().fold(0, max),
+      output_mult_depths: circuit
+        .outputs
+        .iter()
+        .map(|(name, wire)| (name.clone(), mult_depths[*wire]))
+        .collect(),
+      ops,
+      critical_path,
+      boolean: None,
+    }
+  }
+}
+
+impl BooleanMetrics {
+  pub fn new(circuit: &BristolCircuit) -> BooleanMetrics {
+    let mut and_depths = vec![0usize; circuit.wire_count];
+    let mut and_gates = 0;
+    let mut ops = BTreeMap::<String, usize>::new();
+
+    for gate in &circuit.gates {
+      let non_linear = matches!(gate.op.as_str(), "AND" | "OR");
+
+      if non_linear {
+        and_gates += 1;
+      }
+
+      let depth = usize::from(non_linear)
+        + gate
+          .inputs
+          .iter()
+          .map(|input| and_depths[*input])
+          .fold(0, max);
+
+      for output in &gate.outputs {
+        and_depths[*output] = depth;
+      }
+
+      *ops.entry(gate.op.clone()).or_default() += 1;
+    }
+
+    BooleanMetrics {
+      gates: circuit.gates.len(),
+      and_gates,
+      and_depth: and_depths.iter().copied().fold(0, max),
+      ops,
+    }
+  }
+}
+
+impl fmt::Display for CircuitMetrics {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(
+      f,
+      "Gates: {} ({} non-linear), mult depth: {}",
+      self.gates, self.non_linear_gates, self.mult_depth
+    )?;
+
+    writeln!(f, "Mult depth by output:")?;
+
+    for (name, mult_depth) in &self.output_mult_depths {
+      writeln!(f, "  {}: {}", name, mult_depth)?;
+    }
+
+    writeln!(f, "Gates by op:")?;
+    write_ops(f, &self.ops)?;
+
+    let non_linear_path = self.critical_path.iter().filter(|gate| !gate.linear);
+
+    writeln!(
+      f,
+      "Critical path ({} gates, non-linear shown):",
+      self.critical_path.len()
+    )?;
+
+    for gate in non_linear_path {
+      let location = match (&gate.path, gate.line) {
+        (Some(path), Some(line)) => format!(" ({}:{})", path, line),
+        _ => "".to_string(),
+      };
+
+      writeln!(
+        f,
+        "  {:>4} {:<10} {}{}",
+        gate.mult_depth,
+        gate.op,
+        gate.function.as_deref().unwrap_or("(unattributed)"),
+        location,
+      )?;
+    }
+
+    if let Some(boolean) = &self.boolean {
+      writeln!(
+        f,
+        "Boolean gates: {} ({} AND), AND depth: {}",
+        boolean.gates, boolean.and_gates, boolean.and_depth
+      )?;
+
+      write_ops(f, &boolean.ops)?;
+    }
+
+    Ok(())
+  }
+}
+
+fn write_ops(f: &mut fmt::Formatter<'_>, ops: &BTreeMap<String, usize>) -> fmt::Result {
+  for (op, count) in ops {
+    writeln!(f, "  {:<10} {}", op, count)?;
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{collections::HashMap, fs};
+
+  use bristol_circuit::{CircuitInfo, Gate as BristolGate};
+
+  use super::*;
+  use crate::{compile, resolve_entry_path::resolve_entry_path, CompileOptions};
+
+  fn metrics(path: &str) -> CircuitMetrics {
+    let options = CompileOptions {
+      metrics: true,
+      ..Default::default()
+    };
+
+    compile(resolve_entry_path(path), &HashMap::new(), &options, |p| {
+      fs::read_to_string(p).map_err(|e| e.to_string())
+    })
+    .expect("Compile failed")
+    .metrics
+    .expect("Missing metrics")
+  }
+
+  fn critical_ops(metrics: &CircuitMetrics) -> Vec<(&str, bool, usize, Option<u32>)> {
+    metrics
+      .critical_path
+      .iter()
+      .map(|gate| (gate.op.as_str(), gate.linear, gate.mult_depth, gate.line))
+      .collect()
+  }
+
+  /** In `examples/multDepth.ts`, `a * b * c` (line 10) needs two rounds and `a + b + c` none. */
+  #[test]
+  fn product_of_three_has_mult_depth_two() {
+    let m = metrics("../examples/multDepth.ts");
+
+    assert_eq!((m.gates, m.non_linear_gates, m.mult_depth), (4, 2, 2));
+
+    assert_eq!(
+      m.output_mult_depths,
+      BTreeMap::from([("product".to_string(), 2), ("sum".to_string(), 0)]),
+    );
+
+    assert_eq!(
+      m.ops,
+      BTreeMap::from([("AAdd".to_string(), 2), ("AMul".to_string(), 2)]),
+    );
+
+    assert_eq!(
+      critical_ops(&m),
+      vec![("AMul", false, 1, Some(10)), ("AMul", false, 2, Some(10))]
+    );
+  }
+
+  /** In `examples/privateOutput.ts`, only `a < b` (line 12) isn't linear. */
+  #[test]
+  fn sum_is_linear() {
+    let m = metrics("../examples/privateOutput.ts");
+
+    assert_eq!((m.gates, m.non_linear_gates, m.mult_depth), (2, 1, 1));
+
+    assert_eq!(
+      m.output_mult_depths,
+      BTreeMap::from([("aLessThanB".to_string(), 1), ("sum".to_string(), 0)]),
+    );
+
+    assert_eq!(critical_ops(&m), vec![("ALt", false, 1, Some(12))]);
+  }
+
+  #[test]
+  fn boolean_metrics_count_and_depth() {
+    let gate = |op: &str, inputs: Vec<usize>, output: usize| BristolGate {
+      inputs,
+      outputs: vec![output],
+      op: op.to_string(),
+    };
+
+    // Wires 0-2 are inputs: ((x0 & x1) ^ x2) & !x0, and x0 | x2 separately
+    let circuit = BristolCircuit {
+      wire_count: 8,
+      info: CircuitInfo {
+        constants: vec![],
+        inputs: vec![],
+        outputs: vec![],
+      },
+      gates: vec![
+        gate("AND", vec![0, 1], 3),
+        gate("XOR", vec![3, 2], 4),
+        gate("INV", vec![0], 5),
+        gate("AND", vec![4, 5], 6),
+        gate("OR", vec![0, 2], 7),
+      ],
+    };
+
+    let m = BooleanMetrics::new(&circuit);
+
+    assert_eq!((m.gates, m.and_gates, m.and_depth), (5, 3, 2));
+
+    assert_eq!(
+      m.ops,
+      BTreeMap::from([
+        ("AND".to_string(), 2),
+        ("INV".to_string(), 1),
+        ("OR".to_string(), 1),
+        ("XOR".to_string(), 1),
+      ]),
+    );
+  }
+}
@@ -1,6 +1,7 @@
 use num_bigint::BigInt;
 use summon_common::unicode_at;
 use summon_vm::{
+  numeric_model::NumericModel,
   operations,
   vs_class::VsClass,
   vs_object::VsObject,
@@ -360,10 +361,20 @@ pub struct FnState {
   pub register_releases: HashMap<String, Vec<usize>>,
   pub invalidated_releases: Vec<usize>,
   pub new_instructions: Vec<Instruction>,
+
+  /**
+   * How the VM will compute with numbers. Folding only follows JavaScript (`operations`), so number
+   * operations are left to the VM with any other model.
+   */
+  pub numeric_model: NumericModel,
 }
 
 impl FnState {
-  pub fn new(fn_: &Function, pointer_kals: HashMap<Pointer, Kal>) -> Self {
+  pub fn new(
+    fn_: &Function,
+    pointer_kals: HashMap<Pointer, Kal>,
+    numeric_model: NumericModel,
+  ) -> Self {
     let mut reg_allocator = RegAllocator::default();
 
     for p in &fn_.parameters {
@@ -381,6 +392,7 @@ impl FnState {
         asm::FnLine::Label(_) => {}
         asm::FnLine::Empty => {}
         asm::FnLine::Comment(_) => {}
+        asm::FnLine::Span(_) => {}
         asm::FnLine::Release(reg) => reg_allocator.alloc.mark_used(&reg.name),
       }
     }
@@ -388,6 +400,7 @@ impl FnState {
     FnState {
       reg_allocator,
       pointer_kals,
+      numeric_model,
       ..Default::default()
     }
   }
@@ -403,9 +416,15 @@ impl FnState {
       register_releases: Default::default(),
       invalidated_releases: Default::default(),
       new_instructions: take(&mut self.new_instructions),
+      numeric_model: take(&mut self.numeric_model),
     }
   }
 
+  /** Whether number operations can be computed here, i.e. the VM uses JavaScript's arithmetic. */
+  fn folds_numbers(&self) -> bool {
+    self.numeric_model == NumericModel::Float64
+  }
+
   fn get_mut(&mut self, reg_name: String) -> &mut Kal {
     self.registers.entry(reg_name).or_default()
   }
@@ -472,7 +491,7 @@ impl FnState {
         // TODO: Use apply_binary_op?
 
         let new_value = match self.get(reg.name.clone()) {
-          Kal::Number(Number(x)) => Kal::Number(Number(x + 1.0)),
+          Kal::Number(Number(x)) if self.folds_numbers() => Kal::Number(Number(x + 1.0)),
           Kal::BigInt(x) => Kal::BigInt(x + BigInt::from(1)),
           _ => Kal::Unknown,
         };
@@ -483,7 +502,7 @@ impl FnState {
         // TODO: Use apply_binary_op?
 
         let new_value = match self.get(reg.name.clone()) {
-          Kal::Number(Number(x)) => Kal::Number(Number(x - 1.0)),
+          Kal::Number(Number(x)) if self.folds_numbers() => Kal::Number(Number(x - 1.0)),
           Kal::BigInt(x) => Kal::BigInt(x - BigInt::from(1)),
           _ => Kal::Unknown,
         };
@@ -866,6 +885,10 @@ impl FnState {
   ) -> Option<()> {
     let arg = self.eval_arg(arg).try_to_val()?;
 
+    if !self.folds_numbers() && matches!(arg, Val::Number(_)) {
+      return None;
+    }
+
     let kal = match op(&arg) {
       Ok(res) => res.try_to_kal()?,
       Err(_) => return None,
@@ -906,6 +929,11 @@ impl FnState {
     let left = left.try_to_val()?;
     let right = right.try_to_val()?;
 
+    if !self.folds_numbers() && (matches!(left, Val::Number(_)) || matches!(right, Val::Number(_)))
+    {
+      return None;
+    }
+
     let kal = op(&left, &right).ok()?.try_to_kal()?;
 
     self.set(dst.name.clone(), kal);
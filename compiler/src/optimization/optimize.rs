@@ -1,3 +1,5 @@
+use summon_vm::numeric_model::NumericModel;
+
 use crate::asm::Module;
 use crate::name_allocator::NameAllocator;
 
@@ -10,13 +12,21 @@ use super::shake_tree::shake_tree;
 use super::simplify::simplify;
 use super::simplify_jumps::simplify_jumps;
 
-pub fn optimize(module: &mut Module, pointer_allocator: &mut NameAllocator) {
+/**
+ * `numeric_model` is the one the module will run with, which constant folding needs to agree with
+ * (see `FnState::numeric_model`).
+ */
+pub fn optimize(
+  module: &mut Module,
+  pointer_allocator: &mut NameAllocator,
+  numeric_model: &NumericModel,
+) {
   shake_tree(module);
 
   let passes = 3;
 
   for i in 0..passes {
-    simplify(module, i == passes - 1);
+    simplify(module, i == passes - 1, numeric_model);
     reduce_instructions(module);
     remove_unused_labels(module);
     remove_unused_registers(module);
@@ -7,7 +7,9 @@ pub fn remove_meta_lines(module: &mut Module) {
     if let DefinitionContent::Function(fn_) = &mut defn.content {
       for line in take(&mut fn_.body) {
         match &line {
-          FnLine::Instruction(_) | FnLine::Label(_) | FnLine::Empty => fn_.body.push(line),
+          FnLine::Instruction(_) | FnLine::Label(_) | FnLine::Empty | FnLine::Span(_) => {
+            fn_.body.push(line)
+          }
           FnLine::Comment(_) | FnLine::Release(_) => continue,
         }
       }
@@ -26,7 +26,11 @@ fn remove_unused_labels_fn(fn_: &mut Function) {
           InstructionFieldMut::Value(_) | InstructionFieldMut::Register(_) => {}
         });
       }
-      FnLine::Label(_) | FnLine::Empty | FnLine::Comment(_) | FnLine::Release(_) => {}
+      FnLine::Label(_)
+      | FnLine::Empty
+      | FnLine::Comment(_)
+      | FnLine::Release(_)
+      | FnLine::Span(_) => {}
     }
   }
 
@@ -37,7 +41,11 @@ fn remove_unused_labels_fn(fn_: &mut Function) {
           continue;
         }
       }
-      FnLine::Instruction(_) | FnLine::Empty | FnLine::Comment(_) | FnLine::Release(_) => {}
+      FnLine::Instruction(_)
+      | FnLine::Empty
+      | FnLine::Comment(_)
+      | FnLine::Release(_)
+      | FnLine::Span(_) => {}
     }
 
     fn_.body.push(line);
@@ -98,7 +98,9 @@ fn reg_is_always_returned(body: &mut Vec<FnLine>, reg: &String) -> bool {
         false
       }
       FnLine::Instruction(_) | FnLine::Label(_) => false,
-      FnLine::Empty | FnLine::Comment(_) | FnLine::Release(_) => last_was_return_reg,
+      FnLine::Empty | FnLine::Comment(_) | FnLine::Release(_) | FnLine::Span(_) => {
+        last_was_return_reg
+      }
     };
 
     if let FnLine::Instruction(Instruction::End) = line {
@@ -1,12 +1,14 @@
 use std::{collections::HashMap, mem::take};
 
+use summon_vm::numeric_model::NumericModel;
+
 use crate::asm::{
   DefinitionContent, FnLine, Function, Instruction, Module, Pointer, Register, Structured,
 };
 
 use super::kal::{FnState, Kal};
 
-pub fn simplify(module: &mut Module, take_registers: bool) {
+pub fn simplify(module: &mut Module, take_registers: bool, numeric_model: &NumericModel) {
   let mut pointer_kals = HashMap::<Pointer, Kal>::new();
 
   for defn in &mut module.definitions {
@@ -28,7 +30,8 @@ pub fn simplify(module: &mut Module, take_registers: bool) {
   for defn in &mut module.definitions {
     match &mut defn.content {
       DefinitionContent::Function(fn_) => {
-        simplify_fn(FnState::new(fn_, pointer_kals.clone()), fn_, take_registers)
+        let state = FnState::new(fn_, pointer_kals.clone(), numeric_model.clone());
+        simplify_fn(state, fn_, take_registers)
       }
       DefinitionContent::Meta(_) => {}
       DefinitionContent::Value(_) => {}
@@ -61,7 +64,11 @@ fn handle_mutation_releases(body: &mut [FnLine], i: usize, take_registers: bool)
         }
       });
     }
-    FnLine::Release(_) | FnLine::Label(_) | FnLine::Empty | FnLine::Comment(_) => {}
+    FnLine::Release(_)
+    | FnLine::Label(_)
+    | FnLine::Empty
+    | FnLine::Comment(_)
+    | FnLine::Span(_) => {}
   };
 
   for (released_reg, skips) in calls {
@@ -166,7 +173,7 @@ fn simplify_fn(mut state: FnState, fn_: &mut Function, take_registers: bool) {
         }
       }
       FnLine::Label(_) => state.clear_local(),
-      FnLine::Empty | FnLine::Comment(_) => {}
+      FnLine::Empty | FnLine::Comment(_) | FnLine::Span(_) => {}
       FnLine::Release(reg) => {
         pending_releases.push(reg.clone());
 
@@ -196,7 +203,7 @@ fn is_jmp_or_label(line: &FnLine) -> bool {
   match line {
     FnLine::Instruction(instr) => is_jmp_instr(instr),
     FnLine::Label(_) => true,
-    FnLine::Empty | FnLine::Comment(_) | FnLine::Release(_) => false,
+    FnLine::Empty | FnLine::Comment(_) | FnLine::Release(_) | FnLine::Span(_) => false,
   }
 }
 
@@ -69,7 +69,11 @@ fn simplify_jumps_fn(fn_: &mut Function) {
             substitutions.insert(i, FnLine::Instruction(Instruction::Jmp(label_ref.clone())));
           }
           FnLine::Instruction(_) => {}
-          FnLine::Label(_) | FnLine::Empty | FnLine::Comment(_) | FnLine::Release(_) => {
+          FnLine::Label(_)
+          | FnLine::Empty
+          | FnLine::Comment(_)
+          | FnLine::Release(_)
+          | FnLine::Span(_) => {
             panic!("Jump to non-instruction")
           }
         },
@@ -94,7 +98,11 @@ fn next_instruction_index(body: &[FnLine], mut i: usize) -> Option<usize> {
   while i < body.len() {
     match &body[i] {
       FnLine::Instruction(_) => return Some(i),
-      FnLine::Label(_) | FnLine::Empty | FnLine::Comment(_) | FnLine::Release(_) => {}
+      FnLine::Label(_)
+      | FnLine::Empty
+      | FnLine::Comment(_)
+      | FnLine::Release(_)
+      | FnLine::Span(_) => {}
     }
 
     i += 1;
@@ -0,0 +1,347 @@
+use std::{
+  cmp::{max, Reverse},
+  collections::HashMap,
+  fmt,
+  rc::Rc,
+};
+
+use serde::Serialize;
+use summon_vm::{
+  circuit::Circuit,
+  source_map::{SourceLocation, StackTraceFrame},
+};
+
+use crate::diagnostics_by_path::{display_path, pos_to_line_col};
+
+const BAR_WIDTH: usize = 30;
+const LINES_PER_FUNCTION: usize = 5;
+
+/**
+ * Breaks down the gates of a circuit by the function and source line of the instruction that
+ * created them (see `CompileOptions::profile`).
+ *
+ * Gates are attributed to the innermost function only, so a helper called from several places gets
+ * all of its gates, and its callers don't include them.
+ */
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitProfile {
+  pub gates: usize,
+  pub mult_depth: usize,
+
+  /** Sorted by gate count, largest first. */
+  pub functions: Vec<FunctionProfile>,
+
+  /** Sorted by gate count, largest first. */
+  pub lines: Vec<LineProfile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionProfile {
+  pub name: String,
+  pub path: Option<String>,
+  pub gates: usize,
+
+  /** The largest multiplicative depth of the wires produced by this function's gates. */
+  pub mult_depth: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineProfile {
+  pub function: String,
+  pub path: String,
+  pub line: u32,
+  pub gates: usize,
+  pub mult_depth: usize,
+}
+
+#[derive(Default)]
+struct Tally {
+  gates: usize,
+  mult_depth: usize,
+}
+
+impl Tally {
+  fn add(&mut self, mult_depth: usize) {
+    self.gates += 1;
+    self.mult_depth = max(self.mult_depth, mult_depth);
+  }
+}
+
+impl CircuitProfile {
+  /**
+   * `gate_origins` has an entry for each gate of the circuit (see `CircuitBuilder::gate_origins`).
+   */
+  pub fn new<ReadFile>(
+    circuit: &Circuit,
+    gate_origins: &[Option<Rc<StackTraceFrame>>],
+    read_file: &ReadFile,
+  ) -> CircuitProfile
+  where
+    ReadFile: Fn(&str) -> Result<String, String>,
+  {
+    assert_eq!(circuit.gates.len(), gate_origins.len());
+
+    let wire_mult_depths = circuit.mult_depths();
+    let mut source_lines = SourceLines::new(read_file);
+
+    let mut functions = HashMap::<(String, Option<String>), Tally>::new();
+    let mut lines = HashMap::<(String, String, u32), Tally>::new();
+
+    for (gate, origin) in circuit.gates.iter().zip(gate_origins) {
+      let mult_depth = wire_mult_depths[gate.output()];
+
+      let Some(origin) = origin else {
+        functions
+          .entry(("(unattributed)".to_string(), None))
+          .or_default()
+          .add(mult_depth);
+
+        continue;
+      };
+
+      let function_name = function_name(origin);
+
+      let path = origin
+        .location
+        .as_ref()
+        .map(|location| display_path(&location.path));
+
+      functions
+        .entry((function_name.clone(), path.clone()))
+        .or_default()
+        .add(mult_depth);
+
+      let (Some(location), Some(path)) = (&origin.location, path) else {
+        continue;
+      };
+
+      let line = source_lines.line(location);
+
+      lines
+        .entry((function_name, path, line))
+        .or_default()
+        .add(mult_depth);
+    }
+
+    let mut functions = functions
+      .into_iter()
+      .map(|((name, path), tally)| FunctionProfile {
+        name,
+        path,
+        gates: tally.gates,
+        mult_depth: tally.mult_depth,
+      })
+      .collect::<Vec<_>>();
+
+    functions.sort_by(|a, b| {
+      (Reverse(a.gates), &a.name, &a.path).cmp(&(Reverse(b.gates), &b.name, &b.path))
+    });
+
+    let mut lines = lines
+      .into_iter()
+      .map(|((function, path, line), tally)| LineProfile {
+        function,
+        path,
+        line,
+        gates: tally.gates,
+        mult_depth: tally.mult_depth,
+      })
+      .collect::<Vec<_>>();
+
+    lines.sort_by(|a, b| {
+      (Reverse(a.gates), &a.path, a.line).cmp(&(Reverse(b.gates), &b.path, b.line))
+    });
+
+    CircuitProfile {
+      gates: circuit.gates.len(),
+      mult_depth: wire_mult_depths.iter().copied().fold(0, max),
+      functions,
+      lines,
+    }
+  }
+
+  fn write_row(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+    label: &str,
+    gates: usize,
+    mult_depth: usize,
+  ) -> fmt::Result {
+    let share = if self.gates == 0 {
+      0.0
+    } else {
+      gates as f64 / self.gates as f64
+    };
+
+    let bar_len = (share * BAR_WIDTH as f64).round() as usize;
+
+    writeln!(
+      f,
+      "  {:<width$} {:>5.1}% {:>8} gates, mult depth {:<4} {}",
+      "#".repeat(bar_len),
+      share * 100.0,
+      gates,
+      mult_depth,
+      label,
+      width = BAR_WIDTH,
+    )
+  }
+}
+
+/**
+ * The name of the function an origin is in, with a placeholder for anonymous functions.
+ */
+pub(crate) fn function_name(origin: &StackTraceFrame) -> String {
+  match origin.function_name.as_ref() {
+    "" => "<anonymous>".to_string(),
+    name => name.to_string(),
+  }
+}
+
+/**
+ * Finds the line of source locations, reading each file once. The line is 0 when the file can't
+ * be read.
+ */
+pub(crate) struct SourceLines<'a, ReadFile> {
+  read_file: &'a ReadFile,
+  texts: HashMap<Rc<str>, Option<String>>,
+  lines_by_pos: HashMap<(Rc<str>, u32), u32>,
+}
+
+impl<'a, ReadFile> SourceLines<'a, ReadFile>
+where
+  ReadFile: Fn(&str) -> Result<String, String>,
+{
+  pub(crate) fn new(read_file: &'a ReadFile) -> Self {
+    SourceLines {
+      read_file,
+      texts: HashMap::new(),
+      lines_by_pos: HashMap::new(),
+    }
+  }
+
+  pub(crate) fn line(&mut self, location: &SourceLocation) -> u32 {
+    *self
+      .lines_by_pos
+      .entry((location.path.clone(), location.lo))
+      .or_insert_with(|| {
+        let text = self
+          .texts
+          .entry(location.path.clone())
+          .or_insert_with(|| (self.read_file)(&location.path).ok());
+
+        match text {
+          Some(text) => pos_to_line_col(text, location.lo).0,
+          None => 0,
+        }
+      })
+  }
+}
+
+/**
+ * A text summary in the style of a flame graph, with a bar for each function's share of the gates
+ * and its busiest lines nested underneath.
+ */
+impl fmt::Display for CircuitProfile {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "Gates by function:")?;
+
+    for function in &self.functions {
+      let label = match &function.path {
+        Some(path) => format!("{} ({})", function.name, path),
+        None => function.name.clone(),
+      };
+
+      self.write_row(f, &label, function.gates, function.mult_depth)?;
+
+      let function_lines = self
+        .lines
+        .iter()
+        .filter(|line| line.function == function.name && Some(&line.path) == function.path.as_ref())
+        .take(LINES_PER_FUNCTION);
+
+      for line in function_lines {
+        let label = format!("  {}:{}", line.path, line.line);
+        self.write_row(f, &label, line.gates, line.mult_depth)?;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{collections::HashMap, fs};
+
+  use super::*;
+  use crate::{compile, resolve_entry_path::resolve_entry_path, CompileOptions};
+
+  fn profile(path: &str, options: CompileOptions) -> CircuitProfile {
+    let options = CompileOptions {
+      profile: true,
+      ..options
+    };
+
+    compile(resolve_entry_path(path), &HashMap::new(), &options, |p| {
+      fs::read_to_string(p).map_err(|e| e.to_string())
+    })
+    .expect("Compile failed")
+    .profile
+    .expect("Missing profile")
+  }
+
+  /**
+   * In `examples/errorFlag.ts`, `withdraw` compares (line 20) and subtracts (line 24). The gates
+   * that zero the outputs when it throws are added after the program has run, so they don't belong
+   * to any function.
+   */
+  #[test]
+  fn attributes_gates_to_functions_and_lines() {
+    let profile = profile(
+      "../examples/errorFlag.ts",
+      CompileOptions {
+        error_flag_output: true,
+        ..Default::default()
+      },
+    );
+
+    let functions = profile
+      .functions
+      .iter()
+      .map(|function| (function.name.as_str(), function.gates))
+      .collect::<HashMap<_, _>>();
+
+    // `!error` reuses the negated comparison from the `if`, so only the two multiplications by it
+    // are unattributed
+    assert_eq!(
+      functions,
+      HashMap::from([("withdraw", 3), ("(unattributed)", 2)]),
+    );
+
+    let withdraw = profile
+      .functions
+      .iter()
+      .find(|function| function.name == "withdraw")
+      .unwrap();
+
+    assert!(withdraw
+      .path
+      .as_ref()
+      .is_some_and(|path| path.ends_with("errorFlag.ts")));
+
+    let mut lines = profile
+      .lines
+      .iter()
+      .map(|line| (line.function.as_str(), line.line, line.gates))
+      .collect::<Vec<_>>();
+
+    lines.sort();
+
+    assert_eq!(lines, vec![("withdraw", 20, 2), ("withdraw", 24, 1)]);
+    assert_eq!(profile.gates, 5);
+  }
+}
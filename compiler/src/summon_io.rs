@@ -1,19 +1,18 @@
 use std::{
   cell::RefCell,
-  collections::{HashMap, HashSet},
+  collections::{BTreeMap, HashMap, HashSet},
   rc::Rc,
 };
 
 use num_bigint::BigInt;
-use serde_json::json;
-use summon_common::InputDescriptor;
+use summon_common::{InputDescriptor, OutputDescriptor};
 use summon_vm::{
-  circuit_signal::{CircuitSignal, CircuitSignalData},
+  circuit_signal::CircuitSignal,
   error_builtin::ToError,
-  id_generator::IdGenerator,
-  internal_error_builtin::ToInternalError,
   native_function::{native_fn, NativeFunction},
-  operations::op_triple_eq_impl,
+  operations::{op_and, op_mul, op_not},
+  signal_arena::SignalArena,
+  summon_type::SummonType,
   type_error_builtin::ToTypeError,
   val_dynamic_downcast::val_dynamic_downcast,
   vs_array::VsArray,
@@ -28,15 +27,42 @@ pub struct SummonIO {
 }
 
 impl SummonIO {
-  pub fn new(public_inputs: &HashMap<String, Val>, id_gen: &Rc<RefCell<IdGenerator>>) -> Self {
+  pub fn new(
+    public_inputs: &HashMap<String, Val>,
+    signal_arena: &Rc<RefCell<SignalArena>>,
+  ) -> Self {
+    Self::new_impl(public_inputs, None, signal_arena)
+  }
+
+  /**
+   * Like `new`, but `io.input` gives the concrete values in `inputs` (keyed by the input names used
+   * in the circuit, e.g. `xs[0]` for array inputs) instead of signals.
+   */
+  pub fn new_concrete(
+    public_inputs: &HashMap<String, Val>,
+    inputs: &HashMap<String, Val>,
+    signal_arena: &Rc<RefCell<SignalArena>>,
+  ) -> Self {
+    Self::new_impl(public_inputs, Some(inputs.clone()), signal_arena)
+  }
+
+  fn new_impl(
+    public_inputs: &HashMap<String, Val>,
+    concrete_inputs: Option<HashMap<String, Val>>,
+    signal_arena: &Rc<RefCell<SignalArena>>,
+  ) -> Self {
     Self {
       data: Rc::new(RefCell::new(SummonIOData {
-        id_gen: id_gen.clone(),
+        signal_arena: signal_arena.clone(),
         inputs: Vec::new(),
         inputs_used: HashSet::new(),
+        input_shapes: BTreeMap::new(),
+        concrete_inputs,
         public_inputs: public_inputs.clone(),
         public_inputs_used: HashSet::new(),
-        public_outputs: Vec::new(),
+        outputs: Vec::new(),
+        output_descriptors: Vec::new(),
+        output_shapes: BTreeMap::new(),
         parties: Vec::new(),
       })),
     }
@@ -52,6 +78,20 @@ impl SummonIO {
       .collect()
   }
 
+  pub fn unused_concrete_inputs(&self) -> Vec<String> {
+    let io_data = self.data.borrow();
+
+    let Some(concrete_inputs) = &io_data.concrete_inputs else {
+      return vec![];
+    };
+
+    concrete_inputs
+      .keys()
+      .filter(|key| !io_data.inputs_used.contains(*key))
+      .cloned()
+      .collect()
+  }
+
   pub fn input_ids(&self) -> Vec<usize> {
     let io_data = self.data.borrow();
     io_data.inputs.iter().map(|input| input.id).collect()
@@ -59,12 +99,16 @@ impl SummonIO {
 }
 
 pub struct SummonIOData {
-  pub id_gen: Rc<RefCell<IdGenerator>>,
+  pub signal_arena: Rc<RefCell<SignalArena>>,
   pub inputs: Vec<InputDescriptor>,
   pub inputs_used: HashSet<String>,
+  pub input_shapes: BTreeMap<String, SummonType>,
+  pub concrete_inputs: Option<HashMap<String, Val>>,
   pub public_inputs: HashMap<String, Val>,
   pub public_inputs_used: HashSet<String>,
-  pub public_outputs: Vec<(String, Val)>,
+  pub outputs: Vec<(String, Val)>,
+  pub output_descriptors: Vec<OutputDescriptor>,
+  pub output_shapes: BTreeMap<String, SummonType>,
   pub parties: Vec<String>,
 }
 
@@ -76,6 +120,140 @@ impl SummonIOData {
 
     self.parties.push(party.clone());
   }
+
+  pub fn add_input(&mut self, from: &str, name: &str, type_: &SummonType) -> Result<Val, Val> {
+    let leaves = type_.leaves(name);
+
+    let mut names_used = vec![name];
+
+    if !type_.is_scalar() {
+      names_used.extend(leaves.iter().map(|(path, _)| path.as_str()));
+    }
+
+    for name in &names_used {
+      if self.inputs_used.contains(*name) {
+        return Err(format!("Can't use existing input name: \"{}\"", name).to_error());
+      }
+    }
+
+    for name in names_used {
+      self.inputs_used.insert(name.to_string());
+    }
+
+    self.input_shapes.insert(name.to_string(), type_.clone());
+
+    if let Some(concrete_inputs) = &self.concrete_inputs {
+      let mut values = Vec::<Val>::new();
+
+      for (path, leaf_type) in leaves {
+        let Some(value) = concrete_inputs.get(&path) else {
+          return Err(format!("Missing input: \"{}\"", path).to_error());
+        };
+
+        if !leaf_type.check(value) {
+          return Err(
+            format!(
+              "Input \"{}\":{} does not match type {}",
+              path,
+              value.codify(),
+              leaf_type.to_json(),
+            )
+            .to_type_error(),
+          );
+        }
+
+        values.push(value.clone());
+      }
+
+      return Ok(type_.assemble(&mut values.into_iter()));
+    }
+
+    let mut signals = Vec::<Val>::new();
+
+    for (path, leaf_type) in leaves {
+      let signal = CircuitSignal::input(
+        &self.signal_arena,
+        match leaf_type {
+          SummonType::Bool => VsType::Bool,
+          _ => VsType::Number,
+        },
+      );
+
+      self.inputs.push(InputDescriptor {
+        from: from.to_string(),
+        name: path,
+        type_json: leaf_type.to_json(),
+        bit_width: leaf_type.bit_width(),
+        signed: leaf_type.is_signed(),
+        id: signal.id,
+      });
+
+      signals.push(signal.to_dynamic_val());
+    }
+
+    Ok(type_.assemble(&mut signals.into_iter()))
+  }
+
+  pub fn add_output(
+    &mut self,
+    to: Option<Vec<String>>,
+    name: String,
+    value: &Val,
+  ) -> Result<(), Val> {
+    let mut leaves = Vec::<(String, Val)>::new();
+
+    let Some(type_) = SummonType::flatten(value, &name, &mut leaves) else {
+      return Err(
+        "Only numbers, bools, and arrays/objects of these are supported as outputs".to_type_error(),
+      );
+    };
+
+    if self.output_shapes.contains_key(&name) {
+      return Err(format!("Output name already used: \"{}\"", name).to_error());
+    }
+
+    for (leaf_name, _) in &leaves {
+      if self.outputs.iter().any(|(n, _)| n == leaf_name) {
+        return Err(format!("Output name already used: \"{}\"", leaf_name).to_error());
+      }
+    }
+
+    if let Some(to) = &to {
+      for party in to {
+        self.add_party(party.clone());
+      }
+    }
+
+    for (leaf_name, leaf) in leaves {
+      self.output_descriptors.push(OutputDescriptor {
+        to: to.clone(),
+        name: leaf_name.clone(),
+      });
+
+      self.outputs.push((leaf_name, leaf));
+    }
+
+    self.output_shapes.insert(name, type_);
+
+    Ok(())
+  }
+
+  /**
+   * Adds the public `error` output (see `CompileOptions::error_flag_output`) and zeroes the other
+   * outputs when it is set.
+   */
+  pub fn add_error_output(&mut self, error_flag: &Val) -> Result<(), Val> {
+    let ok = op_not(error_flag)?;
+
+    for (_, value) in &mut self.outputs {
+      *value = match value.typeof_() {
+        VsType::Bool => op_and(&ok, value)?,
+        _ => op_mul(&ok, value)?,
+      };
+    }
+
+    self.add_output(None, "error".to_string(), error_flag)
+  }
 }
 
 impl ValTrait for SummonIO {
@@ -95,8 +273,8 @@ impl ValTrait for SummonIO {
     false
   }
 
-  fn is_truthy(&self) -> bool {
-    true
+  fn is_truthy(&self) -> Result<bool, Val> {
+    Ok(true)
   }
 
   fn is_nullish(&self) -> bool {
@@ -186,63 +364,15 @@ static INPUT: NativeFunction = native_fn(|this, params| {
     return Err("Expected `name` to be a string".to_type_error());
   };
 
-  let (type_json, signal) = 's: {
-    let number_type = Val::make_object(&[
-      ("about", "summon runtime type".to_val()),
-      ("json", "number".to_val()),
-    ]);
-
-    if let Ok(true) = op_triple_eq_impl(type_, &number_type) {
-      break 's (
-        json!("number"),
-        CircuitSignal::new(
-          &io_data.id_gen,
-          Some(VsType::Number),
-          CircuitSignalData::Input,
-        ),
-      );
-    }
-
-    let bool_type = Val::make_object(&[
-      ("about", "summon runtime type".to_val()),
-      ("json", "bool".to_val()),
-    ]);
-
-    if let Ok(true) = op_triple_eq_impl(type_, &bool_type) {
-      break 's (
-        json!("bool"),
-        CircuitSignal::new(
-          &io_data.id_gen,
-          Some(VsType::Bool),
-          CircuitSignalData::Input,
-        ),
-      );
-    }
-
-    return Err(
-      "Not implemented yet: type passed to io.input was something other than summon.number(), summon.bool()"
-        .to_internal_error(),
-    );
+  let Some(type_) = SummonType::from_val(type_) else {
+    return Err(format!("Unexpected/unsupported type: {}", type_.codify()).to_type_error());
   };
 
-  let newly_inserted = io_data.inputs_used.insert(name.to_string());
-
-  if !newly_inserted {
-    return Err(format!("Can't use existing input name: \"{}\"", name).to_error());
-  }
-
   let from = from.to_string();
-
-  io_data.inputs.push(InputDescriptor {
-    from: from.clone(),
-    name: name.to_string(),
-    type_json,
-    id: signal.id,
-  });
-
+  let value = io_data.add_input(&from, name, &type_)?;
   io_data.add_party(from);
 
-  Ok(signal.to_dynamic_val())
+  Ok(value)
 });
 
 static INPUT_PUBLIC: NativeFunction = native_fn(|this, params| {
@@ -266,24 +396,22 @@ static INPUT_PUBLIC: NativeFunction = native_fn(|this, params| {
     return Err(format!("Missing public input: \"{}\"", name).to_error());
   };
 
-  match check_runtime_type(type_, value) {
-    TypeCheckResult::Ok => {}
-    TypeCheckResult::BadValue { type_json } => {
-      return Err(
-        format!(
-          "Public input \"{}\":{} does not match type {}",
-          name,
-          value.codify(),
-          type_json.codify()
-        )
-        .to_type_error(),
-      );
-    }
-    TypeCheckResult::BadType => {
-      return Err(format!("Unexpected/unsupported type: {}", type_.codify()).to_type_error());
-    }
+  let Some(type_) = SummonType::from_val(type_) else {
+    return Err(format!("Unexpected/unsupported type: {}", type_.codify()).to_type_error());
   };
 
+  if !type_.check(value) {
+    return Err(
+      format!(
+        "Public input \"{}\":{} does not match type {}",
+        name,
+        value.codify(),
+        type_.to_json(),
+      )
+      .to_type_error(),
+    );
+  }
+
   let value = value.clone();
   io_data.public_inputs_used.insert(name.to_string());
 
@@ -297,33 +425,43 @@ static OUTPUT: NativeFunction = native_fn(|this, params| {
     return Err("Expected this to be Summon.IO".to_type_error());
   };
 
-  let mut _io_data = io.data.borrow_mut();
+  let mut io_data = io.data.borrow_mut();
 
   let (Some(to), Some(name), Some(value)) = (params.first(), params.get(1), params.get(2)) else {
     return Err("Params (to, name, value) not provided".to_type_error());
   };
 
-  let Val::String(_to) = to else {
-    return Err(
-      "Expected `to` to be a string (not implemented yet: array of strings)".to_type_error(),
-    );
-  };
+  let to = match to {
+    Val::String(to) => vec![to.to_string()],
+    Val::Array(to) => {
+      let mut recipients = Vec::<String>::new();
 
-  let Val::String(_id) = name else {
-    return Err("Expected `name` to be a string".to_type_error());
+      for party in &to.elements {
+        let Val::String(party) = party else {
+          return Err("Expected `to` to be a string or array of strings".to_type_error());
+        };
+
+        if !recipients.iter().any(|r| r.as_str() == party.as_ref()) {
+          recipients.push(party.to_string());
+        }
+      }
+
+      recipients
+    }
+    _ => return Err("Expected `to` to be a string or array of strings".to_type_error()),
   };
 
-  if value.typeof_() != VsType::Number && value.typeof_() != VsType::Bool {
-    return Err("Only number and bool outputs are currently supported".to_type_error());
+  if to.is_empty() {
+    return Err("Expected `to` to include at least one party".to_type_error());
   }
 
-  // _io_data.add_party(to.to_string());
+  let Val::String(name) = name else {
+    return Err("Expected `name` to be a string".to_type_error());
+  };
+
+  io_data.add_output(Some(to), name.to_string(), value)?;
 
-  // Note: This is actually very simple to implement within Summon. All we have to do is track this
-  // and change mpcSettings to only list this output for that party instead of everyone. The tricky
-  // part is having a backend that supports it. Implementing this without a backend implementation
-  // would just be confusing.
-  Err("Not implemented yet: io.output".to_internal_error())
+  Ok(Val::Undefined)
 });
 
 static OUTPUT_PUBLIC: NativeFunction = native_fn(|this, params| {
@@ -343,21 +481,7 @@ static OUTPUT_PUBLIC: NativeFunction = native_fn(|this, params| {
     return Err("Expected name to be a string".to_type_error());
   };
 
-  if value.typeof_() != VsType::Number && value.typeof_() != VsType::Bool {
-    return Err("Only number and bool outputs are currently supported".to_type_error());
-  }
-
-  if io_data
-    .public_outputs
-    .iter()
-    .any(|(n, _)| n == &name.to_string())
-  {
-    return Err(format!("Output name already used: \"{}\"", name).to_error());
-  }
-
-  io_data
-    .public_outputs
-    .push((name.to_string(), value.clone()));
+  io_data.add_output(None, name.to_string(), value)?;
 
   Ok(Val::Undefined)
 });
@@ -383,47 +507,3 @@ static ADD_PARTY: NativeFunction = native_fn(|this, params| {
 
   Ok(Val::Undefined)
 });
-
-enum TypeCheckResult {
-  Ok,
-  BadValue { type_json: Val },
-  BadType,
-}
-
-fn check_runtime_type(type_: &Val, value: &Val) -> TypeCheckResult {
-  let number_type = Val::make_object(&[
-    ("about", "summon runtime type".to_val()),
-    ("json", "number".to_val()),
-  ]);
-
-  if op_triple_eq_impl(type_, &number_type).unwrap_or(false) {
-    let res = if !matches!(value, Val::Number(_)) {
-      TypeCheckResult::BadValue {
-        type_json: "number".to_val(),
-      }
-    } else {
-      TypeCheckResult::Ok
-    };
-
-    return res;
-  }
-
-  let bool_type = Val::make_object(&[
-    ("about", "summon runtime type".to_val()),
-    ("json", "bool".to_val()),
-  ]);
-
-  if op_triple_eq_impl(type_, &bool_type).unwrap_or(false) {
-    let res = if !matches!(value, Val::Bool(_)) {
-      TypeCheckResult::BadValue {
-        type_json: "bool".to_val(),
-      }
-    } else {
-      TypeCheckResult::Ok
-    };
-
-    return res;
-  }
-
-  TypeCheckResult::BadType
-}
@@ -1,113 +1,1272 @@
 #[cfg(test)]
 mod tests_ {
   use std::{
-    collections::{BTreeMap, HashMap},
-    fs,
+    collections::{BTreeMap, HashMap, HashSet},
+    env, fs,
+    panic::{self, AssertUnwindSafe},
     path::PathBuf,
     process::Command,
+    sync::{Once, OnceLock},
   };
 
-  use summon_vm::circuit::{CircuitInput, CircuitNumber, NumberOrBool};
+  use bristol_circuit::BristolCircuit;
+  use num_bigint::BigUint;
+  use summon_vm::boolean_circuit::{eval_boolean_bristol, to_boolean_bristol};
+  use summon_vm::circuit::{Circuit, CircuitInput, CircuitNumber, NumberOrBool};
+  use summon_vm::circuit_optimizer::{optimize_circuit, OptimizeOptions};
+  use summon_vm::field_element::{parse_field_modulus, FieldElement};
+  use summon_vm::fixed_width::FixedWidthNumber;
+  use summon_vm::numeric_model::parse_numeric_model;
 
-  use crate::{compile, resolve_entry_path::resolve_entry_path, DiagnosticsByPath};
-
-  #[test]
-  fn test_annotations() {
-    let fetch_status = Command::new("../examples/fetch_deps.sh")
-      .status()
-      .expect("Failed to run fetch_deps.sh");
+  use crate::{
+    compile, resolve_entry_path::resolve_entry_path, run_concrete, CompileOptions,
+    DiagnosticsByPath, ResolvedPath,
+  };
 
-    if !fetch_status.success() {
-      panic!("fetch_deps not successful");
-    }
+  const DEFAULT_FUZZ_SEED: u64 = 0x5eed;
+  const DEFAULT_FUZZ_CASES: u64 = 20;
 
-    let test_cases = find_test_cases("../examples");
+  /**
+   * The largest value used for inputs when fuzzing, unless the file sets `fuzz-max`. Products of
+   * two values this size are still exact in JavaScript, so by default this doesn't look for
+   * differences beyond 2^53 (see `SUMMON_FUZZ_MAX` in the README).
+   */
+  const DEFAULT_FUZZ_MAX: u64 = 0xffff;
 
-    for test_case in &test_cases {
-      let TestCase {
-        path,
-        descriptor,
-        public_inputs,
-        input,
-        expected_output,
-      } = test_case;
+  /** Optimizer passes checked against the unoptimized circuit, which is all of them. */
+  const OPTIMIZE_ALL: OptimizeOptions = OptimizeOptions {
+    minimize_depth: true,
+  };
 
-      println!("Test {}: {}", path, descriptor);
+  /** The widths at which `FixedWidthNumber` is checked against the boolean circuit. */
+  const FIXED_WIDTHS: [usize; 4] = [8, 16, 32, 64];
 
-      let path = resolve_entry_path(path);
+  fn fetch_deps() {
+    static FETCH_DEPS: Once = Once::new();
 
-      let compile_result = compile(path.clone(), public_inputs, |p| {
-        fs::read_to_string(p).map_err(|e| e.to_string())
-      });
+    FETCH_DEPS.call_once(|| {
+      let fetch_status = Command::new("../examples/fetch_deps.sh")
+        .status()
+        .expect("Failed to run fetch_deps.sh");
 
-      let diagnostics = match &compile_result {
-        Ok(compile_ok) => &compile_ok.diagnostics,
-        Err(compile_err) => &compile_err.diagnostics,
-      };
+      if !fetch_status.success() {
+        panic!("fetch_deps not successful");
+      }
+    });
+  }
 
-      println!("{}", DiagnosticsByPath(diagnostics.clone()));
+  /** A `//! test` line with the circuit compiled for it. */
+  struct CompiledTestCase {
+    test_case: TestCase,
+    path: ResolvedPath,
+    circuit: Circuit,
+  }
 
-      let circuit = compile_result.expect("Compile failed").circuit;
+  impl CompiledTestCase {
+    fn description(&self) -> String {
+      format!("{}: {}", self.path.path, self.test_case.descriptor)
+    }
 
-      let inputs = circuit
+    fn inputs(&self) -> BTreeMap<String, NumberOrBool> {
+      self
+        .circuit
         .inputs
         .iter()
-        .map(
-          |(
-            name,
-            CircuitInput {
-              wire_id,
-              type_json: _,
-            },
-          )| { (name.clone(), NumberOrBool::from_json(&input[*wire_id])) },
-        )
-        .collect::<BTreeMap<_, _>>();
+        .map(|(name, CircuitInput { wire_id, .. })| {
+          (
+            name.clone(),
+            NumberOrBool::from_json(&self.test_case.input[*wire_id], &()),
+          )
+        })
+        .collect()
+    }
+  }
+
+  /**
+   * Compiles every `//! test` line in the examples. This happens once, and the tests below check
+   * the circuits against each backend.
+   */
+  fn compiled_test_cases() -> &'static [CompiledTestCase] {
+    static COMPILED: OnceLock<Vec<CompiledTestCase>> = OnceLock::new();
+
+    COMPILED.get_or_init(|| {
+      fetch_deps();
+
+      find_test_cases("../examples")
+        .into_iter()
+        .map(|test_case| {
+          let path = resolve_entry_path(&test_case.path);
+
+          let compile_result = compile(
+            path.clone(),
+            &test_case.public_inputs,
+            &test_case.options,
+            |p| fs::read_to_string(p).map_err(|e| e.to_string()),
+          );
+
+          let circuit = match compile_result {
+            Ok(compile_ok) => compile_ok.circuit,
+            Err(compile_err) => panic!(
+              "Test: {}: {}: Compile failed:\n{}",
+              path.path,
+              test_case.descriptor,
+              DiagnosticsByPath(compile_err.diagnostics)
+            ),
+          };
+
+          CompiledTestCase {
+            test_case,
+            path,
+            circuit,
+          }
+        })
+        .collect()
+    })
+  }
+
+  /** The test cases that compute with wrapping integers (not `field-modulus`). */
+  fn integer_test_cases() -> impl Iterator<Item = &'static CompiledTestCase> {
+    compiled_test_cases()
+      .iter()
+      .filter(|compiled| compiled.circuit.field_modulus.is_none())
+  }
 
-      let outputs = circuit.eval(&inputs);
+  /** The integer test cases that are evaluated with `NumberOrBool` (no `width`). */
+  fn number_or_bool_test_cases() -> impl Iterator<Item = &'static CompiledTestCase> {
+    integer_test_cases().filter(|compiled| compiled.test_case.width.is_none())
+  }
+
+  /** Checks the outputs of the circuit against the `//! test` line. */
+  #[test]
+  fn test_expected_outputs() {
+    for compiled in integer_test_cases() {
+      let circuit = &compiled.circuit;
+      let expected_output = &compiled.test_case.expected_output;
 
       let mut output_names = circuit.outputs.iter().collect::<Vec<_>>();
       output_names.sort_by(|(_, id_a), (_, id_b)| id_a.cmp(id_b));
 
+      if let Some(width) = compiled.test_case.width {
+        let outputs = circuit.eval_with(
+          &fixed_width_inputs(circuit, &compiled.test_case.input, width),
+          &width,
+        );
+
+        for (i, (name, _)) in output_names.iter().enumerate() {
+          let expected = FixedWidthNumber::from_input_json(&expected_output[i], false, width)
+            .expect("Invalid expected output");
+
+          assert!(
+            same_bits(&outputs[name], &expected),
+            "Test: {}: Output mismatch for {} at width {}: expected {}, got {}",
+            compiled.description(),
+            name,
+            width,
+            expected_output[i],
+            outputs[name].to_json(width),
+          );
+        }
+
+        continue;
+      }
+
+      let outputs = circuit.eval(&compiled.inputs());
+
       let ordered_outputs = output_names
         .iter()
-        .map(|(name, _)| outputs.get(*name).unwrap())
+        .map(|(name, _)| outputs.get(name).unwrap())
         .collect::<Vec<_>>();
 
-      let output_name_to_index = output_names
+      for (i, (name, _)) in output_names.iter().enumerate() {
+        assert_eq!(
+          outputs[name],
+          NumberOrBool::from_json(&expected_output[i], &()),
+          "Test: {}: Output mismatch for {}: expected {}, got {} ({:?} vs {:?})",
+          compiled.description(),
+          name,
+          expected_output[i],
+          outputs[name],
+          expected_output,
+          ordered_outputs
+        );
+      }
+    }
+  }
+
+  /**
+   * Checks the gate counts of files with `gates N`, so that changes which add gates (e.g. to how
+   * repeated computations are reused) show up in review.
+   */
+  #[test]
+  fn test_gate_counts() {
+    for compiled in compiled_test_cases() {
+      if let Some(gates) = compiled.test_case.gates {
+        assert_eq!(
+          compiled.circuit.gates.len(),
+          gates,
+          "Test: {}: Gate count mismatch",
+          compiled.description(),
+        );
+      }
+    }
+  }
+
+  /** Checks the 64-bit boolean circuit against evaluating the circuit with `NumberOrBool`. */
+  #[test]
+  fn test_boolean_circuit() {
+    for compiled in number_or_bool_test_cases() {
+      let inputs = compiled.inputs();
+      let outputs = compiled.circuit.eval(&inputs);
+
+      let boolean_circuit = to_boolean_bristol(&compiled.circuit, 64).unwrap();
+
+      let boolean_inputs = inputs
         .iter()
-        .enumerate()
-        .map(|(i, (name, _))| ((*name).clone(), i))
+        .map(|(name, value)| (name.clone(), number_or_bool_to_usize(value)))
         .collect::<BTreeMap<_, _>>();
 
+      let boolean_outputs = eval_boolean_bristol(&boolean_circuit, &boolean_inputs);
+
       for (name, value) in &outputs {
-        let wire_id = output_name_to_index[name];
+        assert_eq!(
+          boolean_outputs[name],
+          number_or_bool_to_usize(value),
+          "Test: {}: Boolean circuit output mismatch for {}",
+          compiled.description(),
+          name,
+        );
+      }
+
+      for output in &boolean_circuit.info.outputs {
+        let expected_type = match outputs[&output.name] {
+          NumberOrBool::Bool(_) => "bool",
+          NumberOrBool::Number(_) => "number",
+        };
+
+        assert_eq!(
+          output.type_,
+          expected_type,
+          "Test: {}: Boolean circuit output type mismatch for {}",
+          compiled.description(),
+          output.name,
+        );
+      }
+    }
+  }
+
+  /**
+   * Checks evaluating with `FixedWidthNumber` against the boolean circuit at each width (and the
+   * file's `width`).
+   */
+  #[test]
+  fn test_fixed_widths() {
+    for compiled in integer_test_cases() {
+      if compiled.test_case.width.is_none() {
+        // Panics (before the boolean circuit disagrees) if the test divides by zero
+        compiled.circuit.eval(&compiled.inputs());
+      }
+
+      for width in FIXED_WIDTHS.into_iter().chain(compiled.test_case.width) {
+        let boolean_circuit = to_boolean_bristol(&compiled.circuit, width).unwrap();
+
+        if let Some(mismatch) = fixed_width_mismatch(
+          &compiled.circuit,
+          &boolean_circuit,
+          width,
+          &compiled.test_case.input,
+        ) {
+          panic!("Test: {}: {}", compiled.description(), mismatch);
+        }
+      }
+    }
+  }
+
+  /** Checks that optimizing the circuit doesn't change its outputs. */
+  #[test]
+  fn test_optimized() {
+    for compiled in integer_test_cases() {
+      if let Some(width) = compiled.test_case.width {
+        let inputs = fixed_width_inputs(&compiled.circuit, &compiled.test_case.input, width);
+
+        let mut optimized = compiled.circuit.clone();
+        optimize_circuit(&mut optimized, &OPTIMIZE_ALL);
 
         assert_eq!(
-          *value,
-          NumberOrBool::from_json(&expected_output[wire_id]),
-          "Test: {}: {}: Output mismatch for {}: expected {}, got {} ({:?} vs {:?})",
-          path.path,
-          descriptor,
+          optimized.eval_with(&inputs, &width),
+          compiled.circuit.eval_with(&inputs, &width),
+          "Test: {}: Optimized circuit output mismatch at width {}",
+          compiled.description(),
+          width,
+        );
+
+        continue;
+      }
+
+      let inputs = compiled.inputs();
+      let outputs = compiled.circuit.eval(&inputs);
+
+      if let Some(mismatch) = optimized_mismatch(&compiled.circuit, &inputs, &outputs) {
+        panic!("Test: {}: {}", compiled.description(), mismatch);
+      }
+    }
+  }
+
+  /**
+   * Checks the circuit against running the program with the test's inputs, unless the file has
+   * `no-concrete`.
+   */
+  #[test]
+  fn test_concrete() {
+    for compiled in integer_test_cases() {
+      let TestCase {
+        public_inputs,
+        options,
+        check_concrete,
+        input,
+        ..
+      } = &compiled.test_case;
+
+      if !check_concrete {
+        continue;
+      }
+
+      let circuit = &compiled.circuit;
+
+      let concrete_inputs = circuit
+        .inputs
+        .iter()
+        .map(|(name, CircuitInput { wire_id, .. })| (name.clone(), input[*wire_id].clone()))
+        .collect::<HashMap<_, _>>();
+
+      let concrete_result = run_concrete(
+        compiled.path.clone(),
+        public_inputs,
+        &concrete_inputs,
+        options,
+        |p| fs::read_to_string(p).map_err(|e| e.to_string()),
+      );
+
+      let concrete_outputs = match concrete_result {
+        Ok(ok) => ok.outputs,
+        Err(err) => panic!(
+          "Test: {}: Concrete run failed:\n{}",
+          compiled.description(),
+          DiagnosticsByPath(err.diagnostics)
+        ),
+      };
+
+      let mut output_names = circuit
+        .outputs
+        .iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+      output_names.sort();
+
+      assert_eq!(
+        concrete_outputs.keys().collect::<Vec<_>>(),
+        output_names,
+        "Test: {}: Concrete run produced different outputs",
+        compiled.description(),
+      );
+
+      if let Some(width) = compiled.test_case.width {
+        let outputs = circuit.eval_with(&fixed_width_inputs(circuit, input, width), &width);
+
+        for (name, value) in &outputs {
+          let concrete_value = &concrete_outputs[name];
+
+          // Compared as bits, since e.g. -1 is all ones
+          let concrete_bits = NumberOrBool::from_val(concrete_value)
+            .and_then(|x| FixedWidthNumber::from_input_json(&x.to_json(), false, width));
+
+          assert!(
+            concrete_bits.is_some_and(|x| same_bits(value, &x)),
+            "Test: {}: Concrete output mismatch for {} at width {}: circuit gave {}, concrete run \
+             gave {}",
+            compiled.description(),
+            name,
+            width,
+            value.to_json(width),
+            concrete_value.codify(),
+          );
+        }
+
+        continue;
+      }
+
+      let outputs = circuit.eval(&compiled.inputs());
+
+      for (name, value) in &outputs {
+        let concrete_value = &concrete_outputs[name];
+
+        assert_eq!(
+          NumberOrBool::from_val(concrete_value).as_ref(),
+          Some(value),
+          "Test: {}: Concrete output mismatch for {}: circuit gave {}, concrete run gave {}",
+          compiled.description(),
           name,
-          expected_output[wire_id],
           value,
-          expected_output,
-          ordered_outputs
+          concrete_value.codify(),
+        );
+      }
+    }
+  }
+
+  /** Checks the circuits compiled with `field-modulus` (see `check_field_eval`). */
+  #[test]
+  fn test_field() {
+    for compiled in compiled_test_cases() {
+      let Some(field_modulus) = &compiled.circuit.field_modulus else {
+        continue;
+      };
+
+      check_field_eval(
+        &compiled.circuit,
+        field_modulus,
+        &compiled.test_case.input,
+        &compiled.test_case.expected_output,
+        &compiled.description(),
+      );
+    }
+  }
+
+  /**
+   * Files with `//! error PATTERN` lines are expected to fail to compile, with each pattern matching
+   * a line of the diagnostics. In patterns, `*` matches anything, and paths are relative to
+   * `examples`.
+   */
+  #[test]
+  fn test_errors() {
+    let examples_dir = format!("{}/", resolve_entry_path("../examples").path);
+
+    for path in read_dir_recursive("../examples") {
+      let content = fs::read_to_string(&path).expect("Unable to read file");
+
+      let patterns = content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("//! error "))
+        .collect::<Vec<_>>();
+
+      if patterns.is_empty() {
+        continue;
+      }
+
+      let path = path.to_str().unwrap();
+      println!("Test {}: errors", path);
+
+      let options = parse_options(&content);
+
+      let compile_result = compile(
+        resolve_entry_path(path),
+        &HashMap::new(),
+        &options.compile,
+        |p| fs::read_to_string(p).map_err(|e| e.to_string()),
+      );
+
+      let Err(compile_err) = compile_result else {
+        panic!("Test: {}: Expected compile to fail", path);
+      };
+
+      let diagnostics = strip_ansi(&DiagnosticsByPath(compile_err.diagnostics).to_string())
+        .replace(&examples_dir, "");
+
+      for pattern in patterns {
+        assert!(
+          diagnostics
+            .lines()
+            .any(|line| matches_pattern(line, pattern.trim())),
+          "Test: {}: No diagnostic matches `{}`:\n{}",
+          path,
+          pattern,
+          diagnostics,
+        );
+      }
+    }
+  }
+
+  fn matches_pattern(line: &str, pattern: &str) -> bool {
+    let mut rest = line;
+
+    for part in pattern.split('*') {
+      match rest.find(part) {
+        Some(i) => rest = &rest[i + part.len()..],
+        None => return false,
+      }
+    }
+
+    true
+  }
+
+  /** Removes terminal color codes (e.g. `\x1b[32m`), which `Val::pretty` uses for strings. */
+  fn strip_ansi(text: &str) -> String {
+    let mut res = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+      if c == '\x1b' {
+        for c in chars.by_ref() {
+          if c.is_ascii_alphabetic() {
+            break;
+          }
+        }
+
+        continue;
+      }
+
+      res.push(c);
+    }
+
+    res
+  }
+
+  /**
+   * Outputs stay in the order they were output, through the circuit info and the MPC settings of
+   * both the arithmetic and boolean circuits. This is checked for every example too, since the
+   * order of their wires can differ (e.g. `error` in `examples/errorFlag.ts`).
+   */
+  #[test]
+  fn test_output_order() {
+    let path = resolve_entry_path("../examples/longOutputArray.ts");
+
+    let circuit = compile(path, &HashMap::new(), &CompileOptions::default(), |p| {
+      fs::read_to_string(p).map_err(|e| e.to_string())
+    })
+    .expect("Compile failed")
+    .circuit;
+
+    let expected = (0..12).map(|i| format!("r[{}]", i)).collect::<Vec<_>>();
+
+    assert_eq!(output_names(&circuit), expected);
+    assert_eq!(bristol_output_names(&circuit.to_bristol()), expected);
+
+    assert_eq!(
+      bristol_output_names(&to_boolean_bristol(&circuit, 64).unwrap()),
+      expected,
+    );
+
+    let mpc_settings = serde_json::to_value(&circuit.mpc_settings).unwrap();
+    assert_eq!(mpc_settings[0]["outputs"], serde_json::json!(expected));
+
+    let mut out_of_wire_order = false;
+
+    for compiled in number_or_bool_test_cases() {
+      let circuit = &compiled.circuit;
+      let expected = output_names(circuit);
+
+      assert_eq!(
+        bristol_output_names(&circuit.to_bristol()),
+        expected,
+        "Test: {}: Arithmetic circuit output order",
+        compiled.description(),
+      );
+
+      assert_eq!(
+        bristol_output_names(&to_boolean_bristol(circuit, 64).unwrap()),
+        expected,
+        "Test: {}: Boolean circuit output order",
+        compiled.description(),
+      );
+
+      out_of_wire_order |= circuit.outputs.windows(2).any(|pair| pair[0].1 > pair[1].1);
+    }
+
+    assert!(out_of_wire_order, "Expected outputs out of wire order");
+  }
+
+  fn output_names(circuit: &Circuit) -> Vec<String> {
+    circuit
+      .outputs
+      .iter()
+      .map(|(name, _)| name.clone())
+      .collect()
+  }
+
+  fn bristol_output_names(bristol: &BristolCircuit) -> Vec<String> {
+    bristol
+      .info
+      .outputs
+      .iter()
+      .map(|io| io.name.clone())
+      .collect()
+  }
+
+  /** Composite inputs record their shape, so that front-ends can flatten values into the leaves. */
+  #[test]
+  fn test_input_shapes() {
+    let path = resolve_entry_path("../examples/compositeInputs.ts");
+
+    let circuit = compile(path, &HashMap::new(), &CompileOptions::default(), |p| {
+      fs::read_to_string(p).map_err(|e| e.to_string())
+    })
+    .expect("Compile failed")
+    .circuit;
+
+    let info = circuit.info_json(&circuit.to_bristol().info);
+
+    assert_eq!(
+      info["inputShapes"],
+      serde_json::json!({
+        "xs": { "type": "array", "element": "number", "length": 3 },
+        "pair": { "type": "tuple", "elements": ["number", "bool"] },
+        "point": {
+          "type": "object",
+          "properties": { "x": "number", "y": "number" },
+        },
+      }),
+    );
+
+    let input_names = circuit.inputs.keys().cloned().collect::<Vec<_>>();
+
+    assert_eq!(
+      input_names,
+      ["pair[0]", "pair[1]", "point.x", "point.y", "xs[0]", "xs[1]", "xs[2]"],
+    );
+  }
+
+  /** Each party's `mpc_settings` only lists the outputs sent to it with `io.output`. */
+  #[test]
+  fn test_private_outputs() {
+    let path = resolve_entry_path("../examples/privateOutput.ts");
+
+    let circuit = compile(path, &HashMap::new(), &CompileOptions::default(), |p| {
+      fs::read_to_string(p).map_err(|e| e.to_string())
+    })
+    .expect("Compile failed")
+    .circuit;
+
+    assert_eq!(
+      serde_json::to_value(&circuit.mpc_settings).unwrap(),
+      serde_json::json!([
+        { "name": "alice", "inputs": ["a"], "outputs": ["sum", "aLessThanB"] },
+        { "name": "bob", "inputs": ["b"], "outputs": ["aLessThanB"] },
+      ]),
+    );
+  }
+
+  /**
+   * Writes each circuit as `circuit.txt` and `circuit_info.json` would be, reads it back with
+   * `Circuit::from_bristol` (as `summonc eval` does), and checks that it evaluates the same.
+   */
+  #[test]
+  fn test_from_bristol() {
+    let mut declared_widths = false;
+    let mut output_shapes = false;
+
+    for compiled in number_or_bool_test_cases() {
+      let circuit = &compiled.circuit;
+      let bristol = circuit.to_bristol();
+
+      let mut text = Vec::<u8>::new();
+      bristol.write_bristol(&mut text).unwrap();
+
+      let parsed = Circuit::from_bristol(
+        &String::from_utf8(text).unwrap(),
+        &circuit.info_json(&bristol.info),
+      )
+      .unwrap_or_else(|e| panic!("Test: {}: {}", compiled.description(), e));
+
+      for (name, input) in &circuit.inputs {
+        let parsed_input = &parsed.inputs[name];
+
+        assert_eq!(
+          (
+            parsed_input.wire_id,
+            parsed_input.bit_width,
+            parsed_input.signed
+          ),
+          (input.wire_id, input.bit_width, input.signed),
+          "Test: {}: Input mismatch for {}",
+          compiled.description(),
+          name,
+        );
+      }
+
+      assert_eq!(parsed.input_shapes, circuit.input_shapes);
+      assert_eq!(parsed.outputs, circuit.outputs);
+      assert_eq!(parsed.output_shapes, circuit.output_shapes);
+
+      assert_eq!(
+        parsed.eval(&compiled.inputs()),
+        circuit.eval(&compiled.inputs()),
+        "Test: {}: Output mismatch after reading the bristol file",
+        compiled.description(),
+      );
+
+      declared_widths |= circuit
+        .inputs
+        .values()
+        .any(|input| input.bit_width.is_some());
+      output_shapes |= !circuit.output_shapes.is_empty();
+    }
+
+    assert!(
+      declared_widths,
+      "Expected inputs from summon.uint/summon.int"
+    );
+    assert!(output_shapes, "Expected outputs with outputShapes");
+  }
+
+  /**
+   * `examples/loopAdd.ts` with more iterations. Its additions form a chain as deep as the loop, and
+   * `minimize_depth` should rebalance that into a tree of logarithmic depth.
+   */
+  #[test]
+  fn test_minimize_depth() {
+    let path = resolve_entry_path("../examples/loopAdd.ts");
+
+    for iterations in [16, 64, 256] {
+      let depth = |minimize_depth| {
+        let options = CompileOptions {
+          optimize: true,
+          minimize_depth,
+          ..Default::default()
+        };
+
+        let read_file = |p: &str| {
+          fs::read_to_string(p)
+            .map(|source| {
+              source.replace(
+                "const iterations = 3;",
+                &format!("const iterations = {};", iterations),
+              )
+            })
+            .map_err(|e| e.to_string())
+        };
+
+        compile(path.clone(), &HashMap::new(), &options, read_file)
+          .expect("Compile failed")
+          .circuit
+          .depth()
+      };
+
+      let linear = depth(false);
+      let balanced = depth(true);
+
+      assert!(
+        linear >= iterations - 1,
+        "{} iterations: expected a chain, got depth {}",
+        iterations,
+        linear,
+      );
+
+      // One more than log2 allows for a leftover operand (e.g. the initial `0`)
+      assert!(
+        balanced <= iterations.ilog2() as usize + 1,
+        "{} iterations: expected logarithmic depth, got {} (from {})",
+        iterations,
+        balanced,
+        linear,
+      );
+    }
+  }
+
+  /**
+   * Checks circuits compiled with `field-modulus`. These compute mod p, so they aren't comparable
+   * to the boolean circuit or a concrete run.
+   */
+  fn check_field_eval(
+    circuit: &Circuit,
+    field_modulus: &BigUint,
+    input: &[serde_json::Value],
+    expected_output: &[serde_json::Value],
+    description: &str,
+  ) {
+    let inputs = circuit
+      .inputs
+      .iter()
+      .map(|(name, CircuitInput { wire_id, .. })| {
+        (
+          name.clone(),
+          FieldElement::from_json(&input[*wire_id], field_modulus),
+        )
+      })
+      .collect::<BTreeMap<_, _>>();
+
+    let outputs = circuit.eval_with(&inputs, field_modulus);
+
+    let mut output_names = circuit.outputs.iter().collect::<Vec<_>>();
+    output_names.sort_by(|(_, id_a), (_, id_b)| id_a.cmp(id_b));
+
+    for (i, (name, _)) in output_names.iter().enumerate() {
+      assert_eq!(
+        outputs[name],
+        FieldElement::from_json(&expected_output[i], field_modulus),
+        "Test: {}: Output mismatch for {}",
+        description,
+        name,
+      );
+    }
+
+    let mut optimized = circuit.clone();
+    optimize_circuit(&mut optimized, &OPTIMIZE_ALL);
+
+    assert_eq!(
+      optimized.eval_with(&inputs, field_modulus),
+      outputs,
+      "Test: {}: Optimized circuit output mismatch",
+      description,
+    );
+  }
+
+  /**
+   * Checks that `optimize_circuit` (with every pass) doesn't change the outputs, including in the
+   * 64-bit boolean circuit.
+   */
+  fn optimized_mismatch(
+    circuit: &Circuit,
+    inputs: &BTreeMap<String, NumberOrBool>,
+    outputs: &BTreeMap<String, NumberOrBool>,
+  ) -> Option<String> {
+    let mut optimized = circuit.clone();
+    optimize_circuit(&mut optimized, &OPTIMIZE_ALL);
+
+    let optimized_outputs = optimized.eval(inputs);
+
+    let boolean_inputs = inputs
+      .iter()
+      .map(|(name, value)| (name.clone(), number_or_bool_to_usize(value)))
+      .collect::<BTreeMap<_, _>>();
+
+    let boolean_outputs = eval_boolean_bristol(
+      &to_boolean_bristol(&optimized, 64).unwrap(),
+      &boolean_inputs,
+    );
+
+    for (name, value) in outputs {
+      if optimized_outputs[name] != *value {
+        return Some(format!(
+          "Output {}: circuit gave {}, optimized circuit gave {}",
+          name, value, optimized_outputs[name]
+        ));
+      }
+
+      if boolean_outputs[name] != number_or_bool_to_usize(value) {
+        return Some(format!(
+          "Output {}: circuit gave {}, optimized boolean circuit gave {}",
+          name, value, boolean_outputs[name]
+        ));
+      }
+    }
+
+    None
+  }
+
+  /**
+   * Evaluates each example on random inputs, comparing the circuit against its 64-bit boolean
+   * circuit, its optimized circuit and a concrete run of the program (unless the file has
+   * `no-concrete`). A mismatch is shrunk to a minimal input, which is reported in the same form as
+   * a `//! test` line.
+   *
+   * Examples are compiled once for each distinct set of public inputs in their `//! test` lines.
+   *
+   * `SUMMON_FUZZ_SEED`, `SUMMON_FUZZ_CASES` and `SUMMON_FUZZ_MAX` override the seed, the number of
+   * inputs tried per example and the largest input value. Numbers above 2^53 aren't exact in
+   * JavaScript, so a large `SUMMON_FUZZ_MAX` finds places where the circuit's wrapping arithmetic
+   * departs from the program.
+   */
+  #[test]
+  fn test_fuzz() {
+    fetch_deps();
+
+    let seed = env_u64("SUMMON_FUZZ_SEED").unwrap_or(DEFAULT_FUZZ_SEED);
+    let cases = env_u64("SUMMON_FUZZ_CASES").unwrap_or(DEFAULT_FUZZ_CASES);
+    let max_override = env_u64("SUMMON_FUZZ_MAX");
+
+    let mut rng = Rng(seed);
+    let mut compiled = HashSet::<(String, String)>::new();
+
+    for test_case in find_test_cases("../examples") {
+      if !test_case.fuzz {
+        continue;
+      }
+
+      let public_inputs =
+        serde_json::to_string(&test_case.public_inputs.iter().collect::<BTreeMap<_, _>>()).unwrap();
+
+      if !compiled.insert((test_case.path.clone(), public_inputs.clone())) {
+        continue;
+      }
+
+      println!("Fuzz {}: {}", test_case.path, public_inputs);
+
+      let path = resolve_entry_path(&test_case.path);
+
+      let compile_result = compile(
+        path.clone(),
+        &test_case.public_inputs,
+        &test_case.options,
+        |p| fs::read_to_string(p).map_err(|e| e.to_string()),
+      );
+
+      let circuit = match compile_result {
+        Ok(compile_ok) => compile_ok.circuit,
+        Err(compile_err) => panic!(
+          "Fuzz {}: Compile failed:\n{}",
+          test_case.path,
+          DiagnosticsByPath(compile_err.diagnostics)
+        ),
+      };
+
+      let boolean_circuit = to_boolean_bristol(&circuit, 64).unwrap();
+      let max = max_override.unwrap_or(test_case.fuzz_max);
+
+      let check = |input: &[serde_json::Value]| {
+        fuzz_mismatch(&test_case, &path, &circuit, &boolean_circuit, input)
+      };
+
+      for _ in 0..cases {
+        let mut input = vec![serde_json::Value::Null; circuit.inputs.len()];
+
+        for circuit_input in circuit.inputs.values() {
+          input[circuit_input.wire_id] = random_input(&mut rng, circuit_input, max);
+        }
+
+        let Some(mismatch) = check(&input) else {
+          continue;
+        };
+
+        let (input, mismatch) = shrink(input, mismatch, &check);
+
+        panic!(
+          "Fuzz {} (seed {}): {}: {}\n  Minimal input: {}",
+          test_case.path,
+          seed,
+          public_inputs,
+          mismatch,
+          serde_json::Value::Array(input),
         );
       }
     }
   }
 
+  /**
+   * Describes the first way the circuit disagrees with the boolean circuit or the concrete run for
+   * this input, if any.
+   */
+  fn fuzz_mismatch(
+    test_case: &TestCase,
+    path: &ResolvedPath,
+    circuit: &Circuit,
+    boolean_circuit: &BristolCircuit,
+    input: &[serde_json::Value],
+  ) -> Option<String> {
+    let inputs = circuit
+      .inputs
+      .iter()
+      .map(|(name, CircuitInput { wire_id, .. })| {
+        let value = NumberOrBool::from_input_json(&input[*wire_id]).expect("Invalid fuzz input");
+        (name.clone(), value)
+      })
+      .collect::<BTreeMap<_, _>>();
+
+    let Ok(outputs) = panic::catch_unwind(AssertUnwindSafe(|| circuit.eval(&inputs))) else {
+      return Some("Circuit evaluation panicked".to_string());
+    };
+
+    let boolean_inputs = inputs
+      .iter()
+      .map(|(name, value)| (name.clone(), number_or_bool_to_usize(value)))
+      .collect::<BTreeMap<_, _>>();
+
+    let Ok(boolean_outputs) = panic::catch_unwind(AssertUnwindSafe(|| {
+      eval_boolean_bristol(boolean_circuit, &boolean_inputs)
+    })) else {
+      return Some("Boolean circuit evaluation panicked".to_string());
+    };
+
+    for (name, value) in &outputs {
+      if boolean_outputs[name] != number_or_bool_to_usize(value) {
+        return Some(format!(
+          "Output {}: circuit gave {}, boolean circuit gave {}",
+          name, value, boolean_outputs[name]
+        ));
+      }
+    }
+
+    if let Some(mismatch) = fixed_width_mismatch(circuit, boolean_circuit, 64, input) {
+      return Some(mismatch);
+    }
+
+    if let Some(mismatch) = optimized_mismatch(circuit, &inputs, &outputs) {
+      return Some(mismatch);
+    }
+
+    if !test_case.check_concrete {
+      return None;
+    }
+
+    let concrete_inputs = circuit
+      .inputs
+      .iter()
+      .map(|(name, CircuitInput { wire_id, .. })| (name.clone(), input[*wire_id].clone()))
+      .collect::<HashMap<_, _>>();
+
+    let concrete_result = run_concrete(
+      path.clone(),
+      &test_case.public_inputs,
+      &concrete_inputs,
+      &test_case.options,
+      |p| fs::read_to_string(p).map_err(|e| e.to_string()),
+    );
+
+    let concrete_outputs = match concrete_result {
+      Ok(ok) => ok.outputs,
+      Err(err) => {
+        return Some(format!(
+          "Concrete run failed:\n{}",
+          DiagnosticsByPath(err.diagnostics)
+        ))
+      }
+    };
+
+    if !concrete_outputs.keys().eq(outputs.keys()) {
+      return Some("Concrete run produced different outputs".to_string());
+    }
+
+    for (name, value) in &outputs {
+      let concrete_value = &concrete_outputs[name];
+
+      if NumberOrBool::from_val(concrete_value).as_ref() != Some(value) {
+        return Some(format!(
+          "Output {}: circuit gave {}, concrete run gave {}",
+          name,
+          value,
+          concrete_value.codify()
+        ));
+      }
+    }
+
+    None
+  }
+
+  /**
+   * Describes the first output where evaluating with `FixedWidthNumber` disagrees with the boolean
+   * circuit at `width`, if any.
+   *
+   * The circuit should already have been evaluated with `NumberOrBool`, which panics when dividing
+   * by zero, because the boolean circuit gives a narrower quotient than `FixedWidthNumber` in that
+   * case.
+   */
+  fn fixed_width_mismatch(
+    circuit: &Circuit,
+    boolean_circuit: &BristolCircuit,
+    width: usize,
+    input: &[serde_json::Value],
+  ) -> Option<String> {
+    let inputs = fixed_width_inputs(circuit, input, width);
+    let outputs = circuit.eval_with(&inputs, &width);
+
+    let boolean_inputs = inputs
+      .iter()
+      .map(|(name, value)| (name.clone(), value.bits() as usize))
+      .collect::<BTreeMap<_, _>>();
+
+    let boolean_outputs = eval_boolean_bristol(boolean_circuit, &boolean_inputs);
+
+    for (name, value) in &outputs {
+      if boolean_outputs[name] != value.bits() as usize {
+        return Some(format!(
+          "Output {} at width {}: fixed width evaluation gave {}, boolean circuit gave {}",
+          name, width, value, boolean_outputs[name]
+        ));
+      }
+    }
+
+    None
+  }
+
+  fn fixed_width_inputs(
+    circuit: &Circuit,
+    input: &[serde_json::Value],
+    width: usize,
+  ) -> BTreeMap<String, FixedWidthNumber> {
+    circuit
+      .inputs
+      .iter()
+      .map(|(name, circuit_input)| {
+        let value = FixedWidthNumber::from_input_json(
+          &input[circuit_input.wire_id],
+          circuit_input.signed,
+          width,
+        )
+        .expect("Invalid fixed width input");
+        (name.clone(), value)
+      })
+      .collect()
+  }
+
+  /**
+   * Whether the values are the same kind with the same bits, regardless of signedness (so -1
+   * matches 255 at width 8).
+   */
+  fn same_bits(a: &FixedWidthNumber, b: &FixedWidthNumber) -> bool {
+    matches!(a, FixedWidthNumber::Bool(_)) == matches!(b, FixedWidthNumber::Bool(_))
+      && a.bits() == b.bits()
+  }
+
+  /**
+   * Repeatedly replaces input values with simpler ones (see `shrink_candidates`) while the mismatch
+   * persists.
+   */
+  fn shrink(
+    mut input: Vec<serde_json::Value>,
+    mut mismatch: String,
+    check: impl Fn(&[serde_json::Value]) -> Option<String>,
+  ) -> (Vec<serde_json::Value>, String) {
+    loop {
+      let mut progress = false;
+
+      for i in 0..input.len() {
+        for candidate in shrink_candidates(&input[i]) {
+          let mut smaller = input.clone();
+          smaller[i] = candidate;
+
+          if let Some(smaller_mismatch) = check(&smaller) {
+            input = smaller;
+            mismatch = smaller_mismatch;
+            progress = true;
+            break;
+          }
+        }
+      }
+
+      if !progress {
+        return (input, mismatch);
+      }
+    }
+  }
+
+  /**
+   * Values to try in place of `value`, simplest first. Numbers move towards zero, so they stay
+   * within the input's declared range.
+   */
+  fn shrink_candidates(value: &serde_json::Value) -> Vec<serde_json::Value> {
+    if let Some(b) = value.as_bool() {
+      return if b { vec![false.into()] } else { vec![] };
+    }
+
+    let x = json_to_i128(value);
+    let mut candidates = Vec::<i128>::new();
+
+    for candidate in [0, x / 2, x - x.signum()] {
+      if candidate != x && !candidates.contains(&candidate) {
+        candidates.push(candidate);
+      }
+    }
+
+    candidates.into_iter().map(i128_to_json).collect()
+  }
+
+  /**
+   * A random value for the input, within its declared type and `[-max, max]`. Values near zero and
+   * at the ends of the range are favored, since that's where bugs tend to be.
+   */
+  fn random_input(rng: &mut Rng, input: &CircuitInput, max: u64) -> serde_json::Value {
+    if input.type_json == "bool" {
+      return (rng.next_u64() % 2 == 1).into();
+    }
+
+    let (min, limit) = match (input.bit_width, input.signed) {
+      (Some(bits), true) => (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1),
+      (Some(bits), false) => (0, (1i128 << bits) - 1),
+      (None, _) => (0, u64::MAX as i128),
+    };
+
+    let min = min.max(-(max as i128));
+    let limit = limit.min(max as i128);
+
+    let x = if rng.next_u64() % 4 == 0 {
+      let edges = [min, limit, 0, 1, limit - 1, min + 1];
+      edges[(rng.next_u64() % edges.len() as u64) as usize]
+    } else {
+      // Log-uniform magnitude
+      let range_bits = 128 - limit.max(-min).leading_zeros() as u64;
+      let bits = rng.next_u64() % (range_bits + 1);
+      let magnitude = (rng.next_u64() as i128) & ((1i128 << bits) - 1);
+
+      if min < 0 && rng.next_u64() % 2 == 0 {
+        -magnitude
+      } else {
+        magnitude
+      }
+    };
+
+    i128_to_json(x.clamp(min, limit))
+  }
+
+  fn json_to_i128(value: &serde_json::Value) -> i128 {
+    match (value.as_i64(), value.as_u64()) {
+      (Some(x), _) => x as i128,
+      (_, Some(x)) => x as i128,
+      _ => panic!("Unexpected fuzz input {}", value),
+    }
+  }
+
+  fn i128_to_json(x: i128) -> serde_json::Value {
+    match i64::try_from(x) {
+      Ok(x) => x.into(),
+      Err(_) => (x as u64).into(),
+    }
+  }
+
+  fn env_u64(name: &str) -> Option<u64> {
+    let value = env::var(name).ok()?;
+
+    Some(
+      value
+        .parse()
+        .unwrap_or_else(|_| panic!("{} must be a number, got `{}`", name, value)),
+    )
+  }
+
+  /**
+   * splitmix64, which is plenty for picking test inputs and keeps runs reproducible from the seed.
+   */
+  struct Rng(u64);
+
+  impl Rng {
+    fn next_u64(&mut self) -> u64 {
+      self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+
+      let mut z = self.0;
+      z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+      z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+      z ^ (z >> 31)
+    }
+  }
+
+  fn number_or_bool_to_usize(value: &NumberOrBool) -> usize {
+    match value {
+      NumberOrBool::Number(x) => *x,
+      NumberOrBool::Bool(b) => *b as usize,
+    }
+  }
+
   #[derive(Debug)]
   struct TestCase {
     path: String,
     descriptor: String,
     public_inputs: HashMap<String, serde_json::Value>,
+    options: CompileOptions,
+
+    /** Whether to also check the circuit against running the program with concrete inputs. */
+    check_concrete: bool,
+
+    /** Whether `test_fuzz` includes this file, and the largest input value it uses. */
+    fuzz: bool,
+    fuzz_max: u64,
+
+    /** Evaluate with `FixedWidthNumber` at this width instead of `NumberOrBool`. */
+    width: Option<usize>,
+
+    /** The number of gates the (unoptimized) circuit should have. */
+    gates: Option<usize>,
+
     input: Vec<serde_json::Value>,
     expected_output: Vec<serde_json::Value>,
   }
 
-  fn parse_test_case(path: &str, line: &str) -> Option<TestCase> {
+  fn parse_test_case(path: &str, options: &FileOptions, line: &str) -> Option<TestCase> {
     let line = line.trim();
 
     if !line.starts_with("//! test ") {
@@ -116,6 +1275,23 @@ mod tests_ {
 
     // strip prefix
     let mut rest = line["//! test ".len()..].trim_start();
+
+    // optional flags that opt this test out of checks its file otherwise gets
+    let mut check_concrete = options.check_concrete;
+    let mut fuzz = options.fuzz;
+
+    loop {
+      if let Some(after) = rest.strip_prefix("no-concrete ") {
+        check_concrete = false;
+        rest = after.trim_start();
+      } else if let Some(after) = rest.strip_prefix("no-fuzz ") {
+        fuzz = false;
+        rest = after.trim_start();
+      } else {
+        break;
+      }
+    }
+
     let descriptor = rest.to_string();
 
     // parse optional public_inputs
@@ -186,19 +1362,127 @@ mod tests_ {
       path: path.to_string(),
       descriptor,
       public_inputs,
+      options: options.compile.clone(),
+      check_concrete,
+      fuzz,
+      fuzz_max: options.fuzz_max,
+      width: options.width,
+      gates: options.gates,
       input,
       expected_output,
     })
   }
 
+  struct FileOptions {
+    compile: CompileOptions,
+    check_concrete: bool,
+    fuzz: bool,
+    fuzz_max: u64,
+    width: Option<usize>,
+    gates: Option<usize>,
+  }
+
+  /**
+   * Files can set options for all of their tests with lines like `//! option error-flag`.
+   *
+   * `no-concrete` skips comparing against a concrete run, for programs that rely on Summon's
+   * departures from JavaScript (like reading out-of-range signal indexes). Error flag programs are
+   * also skipped, since a concrete run stops at the exception.
+   *
+   * `no-fuzz` leaves the file out of `test_fuzz`, and `fuzz-max N` limits its random inputs to N.
+   *
+   * A single test can also opt out with `no-concrete` or `no-fuzz` after `//! test` (e.g.
+   * `//! test no-fuzz { N: 2 } [4, 32] => [18]`).
+   *
+   * `field-modulus P` compiles with `CompileOptions::field_modulus` and checks the outputs mod P
+   * (see `check_field_eval`).
+   *
+   * `numeric-model MODEL` sets `CompileOptions::numeric_model` (after `field-modulus`, for `field`).
+   *
+   * `max-steps N`, `max-branches N` and `max-signals N` set `CompileOptions::limits`, like the
+   * `summonc` flags of the same names.
+   *
+   * `width N` evaluates the circuit with `FixedWidthNumber` at width N instead of `NumberOrBool`,
+   * for programs with signed inputs (`summon.int`), whose comparisons and division differ.
+   * Outputs are compared as bits, so an expected -1 matches all ones. These files aren't fuzzed,
+   * since fuzzing compares against `NumberOrBool`.
+   *
+   * `gates N` checks that the circuit has exactly N gates (see `test_gate_counts`).
+   */
+  fn parse_options(content: &str) -> FileOptions {
+    let mut options = FileOptions {
+      compile: CompileOptions::default(),
+      check_concrete: true,
+      fuzz: true,
+      fuzz_max: DEFAULT_FUZZ_MAX,
+      width: None,
+      gates: None,
+    };
+
+    for line in content.lines() {
+      let Some(option) = line.trim().strip_prefix("//! option ") else {
+        continue;
+      };
+
+      match option.trim() {
+        "error-flag" => {
+          options.compile.error_flag_output = true;
+          options.check_concrete = false;
+        }
+        "no-concrete" => options.check_concrete = false,
+        "no-fuzz" => options.fuzz = false,
+        option => {
+          if let Some(max) = option.strip_prefix("fuzz-max ") {
+            options.fuzz_max = max
+              .trim()
+              .parse()
+              .unwrap_or_else(|_| panic!("invalid fuzz-max `{}`", max));
+          } else if let Some(modulus) = option.strip_prefix("field-modulus ") {
+            options.compile.field_modulus =
+              Some(parse_field_modulus(modulus.trim()).unwrap_or_else(|e| panic!("{}", e)));
+            options.check_concrete = false;
+            options.fuzz = false;
+          } else if let Some(steps) = option.strip_prefix("max-steps ") {
+            options.compile.limits.steps = Some(parse_usize_option("max-steps", steps));
+          } else if let Some(branches) = option.strip_prefix("max-branches ") {
+            options.compile.limits.branches = Some(parse_usize_option("max-branches", branches));
+          } else if let Some(signals) = option.strip_prefix("max-signals ") {
+            options.compile.limits.signals = Some(parse_usize_option("max-signals", signals));
+          } else if let Some(width) = option.strip_prefix("width ") {
+            options.width = Some(parse_usize_option("width", width));
+            options.fuzz = false;
+          } else if let Some(gates) = option.strip_prefix("gates ") {
+            options.gates = Some(parse_usize_option("gates", gates));
+          } else if let Some(model) = option.strip_prefix("numeric-model ") {
+            options.compile.numeric_model =
+              parse_numeric_model(model.trim(), options.compile.field_modulus.as_ref())
+                .unwrap_or_else(|e| panic!("{}", e));
+          } else {
+            panic!("unknown test option `{}`", option);
+          }
+        }
+      }
+    }
+
+    options
+  }
+
+  fn parse_usize_option(name: &str, value: &str) -> usize {
+    value
+      .trim()
+      .parse()
+      .unwrap_or_else(|_| panic!("invalid {} `{}`", name, value))
+  }
+
   fn find_test_cases(dir: &str) -> Vec<TestCase> {
     let mut test_cases = Vec::new();
 
     for path in read_dir_recursive(dir) {
       let content = fs::read_to_string(&path).expect("Unable to read file");
+      let options = parse_options(&content);
 
       for line in content.lines() {
-        if let Some(test_case) = parse_test_case(path.to_str().unwrap(), line) {
+        if let Some(test_case) = parse_test_case(path.to_str().unwrap(), &options, line) {
           test_cases.push(test_case);
         }
       }
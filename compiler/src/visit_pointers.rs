@@ -219,7 +219,11 @@ where
         FnLine::Instruction(instruction) => {
           self.instruction(owner, instruction);
         }
-        FnLine::Label(..) | FnLine::Empty | FnLine::Comment(..) | FnLine::Release(..) => {}
+        FnLine::Label(..)
+        | FnLine::Empty
+        | FnLine::Comment(..)
+        | FnLine::Release(..)
+        | FnLine::Span(..) => {}
       }
     }
   }
@@ -0,0 +1,23 @@
+//! test [1, 2, 3, 4, true, 10, 20] => [6, 4, 30]
+//! test [1, 2, 3, 4, false, 10, 20] => [6, 0, 30]
+
+export default (io: Summon.IO) => {
+  const xs = io.input('alice', 'xs', summon.array(summon.number(), 3));
+  const pair = io.input('alice', 'pair', summon.tuple(summon.number(), summon.bool()));
+
+  const point = io.input(
+    'bob',
+    'point',
+    summon.object({ x: summon.number(), y: summon.number() }),
+  );
+
+  let sum = 0;
+
+  for (const x of xs) {
+    sum += x;
+  }
+
+  io.outputPublic('sum', sum);
+  io.outputPublic('pairValue', pair[1] ? pair[0] : 0);
+  io.outputPublic('pointSum', point.x + point.y);
+};
@@ -0,0 +1,25 @@
+//! option error-flag
+//! test [10, 3] => [false, 7, 3]
+//! test [10, 10] => [false, 0, 10]
+//! test [10, 12] => [true, 0, 0]
+
+// Compile with `--error-flag` to allow exceptions that depend on the inputs. The
+// circuit gets an extra public output called `error`, which is true when the
+// exception would have been thrown. When that happens, the other outputs are
+// zero.
+
+export default (io: Summon.IO) => {
+  const balance = io.input('alice', 'balance', summon.number());
+  const amount = io.input('bob', 'amount', summon.number());
+
+  io.outputPublic('remaining', withdraw(balance, amount));
+  io.outputPublic('withdrawn', amount);
+};
+
+function withdraw(balance: number, amount: number) {
+  if (amount > balance) {
+    throw new Error('Insufficient funds');
+  }
+
+  return balance - amount;
+}
@@ -0,0 +1,11 @@
+//! error errors/mixedSignalIndex.ts:10:*: Error: Uncaught exception: TypeError { message: "Can't index [1,true] with a signal because it contains a number and a bool" }
+
+// Indexing with a signal merges every element, so they need to be numbers or bools of the same type
+// (or arrays/objects of these with the same shape).
+
+export default (io: Summon.IO) => {
+  const i = io.input('alice', 'i', summon.number());
+  const mixed = [1, true];
+
+  io.outputPublic('value', mixed[i]);
+};
@@ -0,0 +1,14 @@
+//! error errors/mixedSignalIndexWrite.ts:11:*: Error: Uncaught exception: TypeError { message: "Can't assign true to an element of [1,2,3] with a signal index because it would merge a number and a bool" }
+
+// Writing with a signal index merges the value into every element, so it needs the same type (or
+// shape) as the elements.
+
+export default (io: Summon.IO) => {
+  const i = io.input('alice', 'i', summon.number());
+
+  const values = [1, 2, 3];
+
+  values[i] = true;
+
+  io.outputPublic('values', values);
+};
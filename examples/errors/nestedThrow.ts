@@ -0,0 +1,32 @@
+//! error errors/nestedThrow.ts:12:*: Error: Uncaught exception: *Amount must be positive, got -3*
+//! error   at checkAmount (errors/nestedThrow.ts:12:*)
+//! error   at total (errors/nestedThrow.ts:22:*)
+//! error   at * (errors/nestedThrow.ts:31:*)
+
+// An exception reports where it was thrown, followed by the calls that led there, like a
+// JavaScript stack trace.
+
+function checkAmount(amount: number) {
+  if (amount <= 0) {
+    // Thrown at compile time, since `amount` is a constant
+    throw new Error('Amount must be positive, got ' + amount);
+  }
+
+  return amount;
+}
+
+function total(amounts: number[]) {
+  let sum = 0;
+
+  for (const amount of amounts) {
+    sum += checkAmount(amount);
+  }
+
+  return sum;
+}
+
+export default (io: Summon.IO) => {
+  const x = io.input('alice', 'x', summon.number());
+
+  io.outputPublic('total', x + total([1, 2, -3]));
+};
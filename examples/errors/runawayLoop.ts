@@ -0,0 +1,27 @@
+//! option max-branches 20
+//! error errors/runawayLoop.ts:15:*: Error: Branch limit (20) reached. This conditional jump forked * times, which usually means a loop condition depends on a signal, so the loop never ends during compilation.
+//! error   at countDown (errors/runawayLoop.ts:15:*)
+//! error   at * (errors/runawayLoop.ts:26:*)
+
+// The compiler can't know when a loop on a signal should stop, so it keeps forking until it hits
+// a limit (`--max-branches` etc). The error points at the loop condition that keeps forking.
+//
+// To loop over a signal, loop up to a fixed bound and use the signal in the body instead, e.g.
+// `for (let i = 0; i < MAX; i++) { if (i < x) { ... } }`.
+
+function countDown(x: number) {
+  let steps = 0;
+
+  while (x > 0) {
+    x--;
+    steps++;
+  }
+
+  return steps;
+}
+
+export default (io: Summon.IO) => {
+  const x = io.input('alice', 'x', summon.number());
+
+  io.outputPublic('steps', countDown(x));
+};
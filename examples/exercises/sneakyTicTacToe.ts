@@ -64,6 +64,8 @@ export default function main(io: Summon.IO) {
     j: io.inputPublic('moveJ', summon.number()),
   };
 
+  // Tip: arrays can be read and written using signals as indexes, e.g. `grid[i][j] = 1`.
+
   // check the commitments are correct
   // check the grid for win conditions
   throw new Error('Implement me');
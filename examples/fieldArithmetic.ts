@@ -0,0 +1,16 @@
+//! option field-modulus 101
+//! test [3, 5] => [8, 99, 15, 41, 98]
+//! test [100, 1] => [0, 99, 100, 100, 1]
+//! test [3, 0] => [3, 3, 0, 0, 98]
+
+export default (io: Summon.IO) => {
+  const a = io.input('alice', 'a', summon.number());
+  const b = io.input('bob', 'b', summon.number());
+
+  // With `--field-modulus 101`, these are computed mod 101
+  io.outputPublic('sum', a + b);
+  io.outputPublic('difference', a - b); // -2 becomes 99
+  io.outputPublic('product', a * b);
+  io.outputPublic('quotient', a / b); // multiplies by the inverse of b (0 has none, giving 0)
+  io.outputPublic('negated', a * -1); // the constant -1 is encoded as 100
+};
@@ -0,0 +1,10 @@
+//! test [true, 100, 7] => [107]
+//! test [false, 100, 7] => [100]
+
+export default (io: Summon.IO) => {
+  const vote = io.input('alice', 'vote', summon.bool());
+  const balance = io.input('bob', 'balance', summon.uint(64));
+  const bonus = io.input('bob', 'bonus', summon.uint(8));
+
+  io.outputPublic('total', vote ? balance + bonus : balance);
+};
@@ -1,3 +1,4 @@
+//! option no-concrete
 //! test [1] => [2]
 
 export default (io: Summon.IO) => {
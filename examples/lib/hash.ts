@@ -7,7 +7,7 @@ export default function hash(salt: number, msg: number): number {
     [a, b] = [b, a];
   }
 
-  // Max safe integer in f64. This is a workaround due to encoding issues.
+  // Max safe integer in f64, since public inputs can't go beyond it (see Limitations in README)
   const mask = 0b11111111111111111111111111111111111111111111111111111;
 
   return (a + b) & mask;
@@ -1,5 +1,7 @@
 // Note: This could be implemented using `treeReduce` but we hardcode this version because it's
 // common and easier to understand without the extra abstraction.
+//
+// Compiling with `--minimize-depth` rebalances plain `sum += x` loops the same way.
 
 export default function treeSum<T>(items: T[], mapper: (x: T) => number): number {
   if (items.length <= 2) {
@@ -0,0 +1,15 @@
+//! test [3] => [4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]
+
+// Outputs are kept in declared order, so r[10] and r[11] come after r[9] rather than after r[1].
+
+export default (io: Summon.IO) => {
+  const x = io.input('alice', 'x', summon.number());
+
+  const r: number[] = [];
+
+  for (let i = 1; i <= 12; i++) {
+    r.push(x + i);
+  }
+
+  io.outputPublic('r', r);
+};
@@ -0,0 +1,15 @@
+//! test [3, 8, 5] => [3, 8, 3, 5, 1]
+//! test [9, 2, 5] => [2, 9, 2, 5, 1]
+//! test [0, 4, 4] => [0, 4, 0, 4, 0]
+
+export default (io: Summon.IO) => {
+  const a = io.input('alice', 'a', summon.number());
+  const b = io.input('bob', 'b', summon.number());
+  const c = io.input('bob', 'c', summon.number());
+
+  io.outputPublic('min', Math.min(a, b, c));
+  io.outputPublic('max', Math.max(a, b, c));
+  io.outputPublic('minAB', Math.min(a, b));
+  io.outputPublic('clamped', Math.clamp(c, 4, 6));
+  io.outputPublic('sign', Math.sign(a));
+};
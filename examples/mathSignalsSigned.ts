@@ -0,0 +1,19 @@
+//! option width 8
+//! test [-5, 3] => [5, -1, -5, 3, -2]
+//! test [7, -2] => [7, 1, -2, 7, 2]
+//! test [0, -128] => [0, 0, -128, 0, 0]
+//! test [-1, -1] => [1, -1, -1, -1, -1]
+
+// Comparisons of signed inputs only use two's complement in boolean circuits (and
+// `summonc eval --width`), so these are checked at 8 bits.
+
+export default (io: Summon.IO) => {
+  const a = io.input('alice', 'a', summon.int(8));
+  const b = io.input('bob', 'b', summon.int(8));
+
+  io.outputPublic('abs', Math.abs(a));
+  io.outputPublic('sign', Math.sign(a));
+  io.outputPublic('min', Math.min(a, b));
+  io.outputPublic('max', Math.max(a, b));
+  io.outputPublic('clamped', Math.clamp(a, -2, 2));
+};
@@ -1,6 +1,10 @@
+// For an even length, the median is the mean of the middle two values, which can be fractional,
+// but circuit division is integer division. So random inputs for N = 2 aren't compared with
+// JavaScript, only this test's inputs, whose mean is a whole number.
 //! test { N: 1 } [4] => [4]
-//! test { N: 2 } [4, 32] => [18]
+//! test no-fuzz { N: 2 } [4, 32] => [18]
 //! test { N: 3 } [4, 32, 8] => [8]
+//! test { N: 5 } [7, 3, 9, 1, 5] => [5]
 //! test { N: 11 } [4, 32, 8, 10, 50, 5, 20, 25, 38, 58, 93] => [25]
 
 import median from "./lib/median.ts";
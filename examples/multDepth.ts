@@ -0,0 +1,14 @@
+//! test [2, 3, 4] => [24, 9]
+//! test [5, 0, 7] => [0, 12]
+
+export default (io: Summon.IO) => {
+  const a = io.input('alice', 'a', summon.number());
+  const b = io.input('bob', 'b', summon.number());
+  const c = io.input('bob', 'c', summon.number());
+
+  // Two rounds of multiplication, since the second needs the result of the first
+  io.outputPublic('product', a * b * c);
+
+  // Additions are linear, so they don't need any
+  io.outputPublic('sum', a + b + c);
+};
@@ -0,0 +1,16 @@
+//! option numeric-model uint64
+//! option fuzz-max 255
+//! test [1] => [1099511627776, 1099511627776, 4, true]
+//! test [5] => [5497558138880, 5497558138880, 8, true]
+
+export default (io: Summon.IO) => {
+  const x = io.input('alice', 'x', summon.number());
+
+  // With `--numeric-model uint64`, numbers known at compile time use the same arithmetic as the
+  // circuit, so these agree (in JavaScript, `1 << 40` is 256)
+  io.outputPublic('shifted', x << 40);
+  io.outputPublic('scaled', x * (1 << 40));
+
+  io.outputPublic('plusHalfOf7', x + 7 / 2); // integer division
+  io.outputPublic('belowMinusOne', x < -1); // -1 is 2^64 - 1
+};
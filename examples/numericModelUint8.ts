@@ -0,0 +1,18 @@
+//! option numeric-model uint8
+//! option fuzz-max 100
+//! test [1] => [1, 2]
+//! test [100] => [100, 101]
+
+export default (io: Summon.IO) => {
+  const x = io.input('alice', 'x', summon.number());
+
+  // `++` and `+= 1` wrap the same way in `uint8`, so both counters are back to 0 and 1
+  let counter = 255;
+  counter++;
+
+  let plusEquals = 255;
+  plusEquals += 2;
+
+  io.outputPublic('incremented', x + counter);
+  io.outputPublic('added', x + plusEquals);
+};
@@ -0,0 +1,13 @@
+//! test [3, 5] => [8, true]
+//! test [5, 3] => [8, false]
+
+export default (io: Summon.IO) => {
+  const a = io.input('alice', 'a', summon.number());
+  const b = io.input('bob', 'b', summon.number());
+
+  // Only alice learns the sum
+  io.output('alice', 'sum', a + b);
+
+  // Both parties learn the comparison
+  io.output(['alice', 'bob'], 'aLessThanB', a < b);
+};
@@ -0,0 +1,24 @@
+//! option gates 11
+//! test [3, 4] => [24]
+//! test [4, 3] => [26]
+//! test [5, 5] => [50]
+
+// Computing the same thing again reuses the existing signal, so `b * a` and the second `a > b`
+// don't add any gates. Without that, this would be 13 gates rather than 11.
+
+export default (io: Summon.IO) => {
+  const a = io.input('alice', 'a', summon.number());
+  const b = io.input('bob', 'b', summon.number());
+
+  let total = a * b + b * a;
+
+  if (a > b) {
+    total += 1;
+  }
+
+  if (a > b) {
+    total += 1;
+  }
+
+  io.outputPublic('result', total);
+};
@@ -0,0 +1,21 @@
+//! option no-concrete
+//! test [0] => [10, 1, 2, true]
+//! test [1] => [20, 3, 4, false]
+//! test [3] => [40, 0, 0, false]
+//! test [7] => [0, 0, 0, false]
+
+export default (io: Summon.IO) => {
+  const i = io.input('alice', 'i', summon.number());
+
+  const table = [10, 20, 30, 40];
+  const points = [{ x: 1, y: 2 }, { x: 3, y: 4 }];
+  const flags = [true, false, true];
+
+  // Out-of-range indexes give zero values (0/false)
+  const point = points[i];
+
+  io.outputPublic('value', table[i]);
+  io.outputPublic('x', point.x);
+  io.outputPublic('y', point.y);
+  io.outputPublic('flag', flags[i]);
+};
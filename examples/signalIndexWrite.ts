@@ -0,0 +1,21 @@
+//! option no-concrete
+//! test [0, 0] => [7, 2, 3, 4]
+//! test [1, 0] => [1, 2, 7, 4]
+//! test [1, 1] => [1, 2, 3, 7]
+//! test [2, 1] => [1, 2, 3, 4]
+//! test [0, 2] => [1, 2, 3, 4]
+
+export default (io: Summon.IO) => {
+  const i = io.input('alice', 'i', summon.number());
+  const j = io.input('alice', 'j', summon.number());
+
+  const grid = [
+    [1, 2],
+    [3, 4],
+  ];
+
+  // Out-of-range writes leave the array unchanged
+  grid[i][j] = 7;
+
+  io.outputPublic('grid', grid);
+};
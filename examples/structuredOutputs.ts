@@ -0,0 +1,13 @@
+//! test [3, 5] => [true, false, 15, 8]
+//! test [4, 4] => [false, true, 16, 8]
+
+export default (io: Summon.IO) => {
+  const a = io.input('alice', 'a', summon.number());
+  const b = io.input('bob', 'b', summon.number());
+
+  // Flattened into result.flags[0], result.flags[1], result.stats.product, result.stats.sum
+  io.outputPublic('result', {
+    stats: { sum: a + b, product: a * b },
+    flags: [a < b, a === b],
+  });
+};
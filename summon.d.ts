@@ -19,6 +19,47 @@ declare const summon: {
 
   /** Produces a runtime value that models the type `boolean`. */
   bool(): Summon.Type<boolean>;
+
+  /**
+   * Produces a runtime value that models an unsigned integer of the given bit width (1 to 64).
+   *
+   * The width is recorded in the circuit info and used when producing boolean circuits.
+   */
+  uint(bits: number): Summon.Type<number>;
+
+  /**
+   * Produces a runtime value that models a signed (two's complement) integer of the given bit
+   * width (1 to 64).
+   */
+  int(bits: number): Summon.Type<number>;
+
+  /**
+   * Produces a runtime value that models a fixed-length array.
+   *
+   *     const xs = io.input('alice', 'xs', summon.array(summon.number(), 3));
+   *     // inputs: xs[0], xs[1], xs[2]
+   */
+  array<T>(type: Summon.Type<T>, length: number): Summon.Type<T[]>;
+
+  /**
+   * Produces a runtime value that models a tuple.
+   *
+   *     const [x, flag] = io.input('alice', 'pair', summon.tuple(summon.number(), summon.bool()));
+   *     // inputs: pair[0], pair[1]
+   */
+  tuple<T extends unknown[]>(
+    ...types: { [K in keyof T]: Summon.Type<T[K]> }
+  ): Summon.Type<T>;
+
+  /**
+   * Produces a runtime value that models an object with a fixed set of properties.
+   *
+   *     const p = io.input('alice', 'p', summon.object({ x: summon.number(), y: summon.number() }));
+   *     // inputs: p.x, p.y
+   */
+  object<T extends Record<string, unknown>>(
+    properties: { [K in keyof T]: Summon.Type<T[K]> },
+  ): Summon.Type<T>;
 };
 
 declare namespace Summon {
@@ -32,7 +73,12 @@ declare namespace Summon {
     /** Provide an output visible only to a specific party or parties. */
     output<T>(to: string | string[], name: string, value: T): void;
 
-    /** Provide an output visible to all parties. */
+    /**
+     * Provide an output visible to all parties.
+     *
+     * Arrays and objects are flattened into one circuit output per number/bool, named like
+     * `name[0]` and `name.x`. Their shapes are recorded in `outputShapes` in the circuit info.
+     */
     outputPublic<T>(name: string, value: T): void;
 
     /**
@@ -56,3 +102,12 @@ declare namespace Summon {
 
   export type TypeOf<T> = T extends Type<infer U> ? U : never;
 }
+
+interface Math {
+  /**
+   * Non-standard: restricts `x` to the range `[min, max]`, i.e.
+   * `Math.min(Math.max(x, min), max)`. Like `Math.min` and `Math.max`, this also works with
+   * signals.
+   */
+  clamp(x: number, min: number, max: number): number;
+}
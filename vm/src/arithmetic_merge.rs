@@ -1,7 +1,8 @@
 use std::collections::BTreeMap;
 
 use crate::{
-  circuit_signal::{CircuitSignal, CircuitSignalData},
+  builtins::internal_error_builtin::ToInternalError,
+  circuit_signal::{CircuitSignal, CircuitSignalData, SignalOperand},
   operations::{op_bit_xor, op_mul, op_triple_eq_impl},
   type_error_builtin::ToTypeError,
   unary_op::UnaryOp,
@@ -39,21 +40,26 @@ use crate::{
  * require that the structure is the same and we can merge element by element.
  * If the structure is different, we abort with a compilation error.
  */
-pub fn arithmetic_merge(left_flag: &Val, left: &Val, right_flag: &Val, right: &Val) -> Val {
-  assert!(left_flag.typeof_() == VsType::Bool);
-  assert!(right_flag.typeof_() == VsType::Bool);
+pub fn arithmetic_merge(
+  left_flag: &Val,
+  left: &Val,
+  right_flag: &Val,
+  right: &Val,
+) -> Result<Val, Val> {
+  if left_flag.typeof_() != VsType::Bool || right_flag.typeof_() != VsType::Bool {
+    return Err("Branch flags should be bools".to_internal_error());
+  }
 
   if quick_val_eq(left, right) {
-    return left.clone();
+    return Ok(left.clone());
   }
 
   arithmetic_merge_impl(&gen_direct_merge(left_flag, right_flag), left, right)
 }
 
-fn gen_direct_merge<'a>(
-  left_flag: &'a Val,
-  right_flag: &'a Val,
-) -> Box<dyn Fn(&'a Val, &'a Val) -> Val + 'a> {
+type DirectMerge<'a> = Box<dyn Fn(&'a Val, &'a Val) -> Result<Val, Val> + 'a>;
+
+fn gen_direct_merge<'a>(left_flag: &'a Val, right_flag: &'a Val) -> DirectMerge<'a> {
   if let Some(direct_merge) = optimized_direct_merge(false, left_flag, right_flag) {
     return direct_merge;
   }
@@ -62,29 +68,28 @@ fn gen_direct_merge<'a>(
     return direct_merge;
   }
 
-  Box::new(|left, right| {
-    op_bit_xor(
-      &op_mul(left_flag, left).unwrap(),
-      &op_mul(right_flag, right).unwrap(),
-    )
-    .unwrap()
-  })
+  Box::new(|left, right| op_bit_xor(&op_mul(left_flag, left)?, &op_mul(right_flag, right)?))
 }
 
 fn optimized_direct_merge<'a>(
   swap: bool,
   left_flag: &'a Val,
   right_flag: &'a Val,
-) -> Option<Box<dyn Fn(&'a Val, &'a Val) -> Val + 'a>> {
+) -> Option<DirectMerge<'a>> {
   let (left_flag, right_flag) = if swap {
     (right_flag, left_flag)
   } else {
     (left_flag, right_flag)
   };
 
-  if let Some(left_flag) = val_dynamic_downcast::<CircuitSignal>(left_flag) {
-    if let CircuitSignalData::UnaryOp(UnaryOp::Not, input) = &left_flag.data {
-      if quick_val_eq(input, right_flag) {
+  if let (Some(left_flag), Some(right_flag_signal)) = (
+    val_dynamic_downcast::<CircuitSignal>(left_flag),
+    val_dynamic_downcast::<CircuitSignal>(right_flag),
+  ) {
+    if let CircuitSignalData::UnaryOp(UnaryOp::Not, SignalOperand::Signal(input_id, _)) =
+      left_flag.data()
+    {
+      if input_id == right_flag_signal.id {
         // left_flag = 1 - right_flag
         // out = left_flag * left + right_flag * right
         //     = (1 - right_flag) * left + right_flag * right
@@ -94,11 +99,7 @@ fn optimized_direct_merge<'a>(
         return Some(Box::new(move |left, right| {
           let (left, right) = if swap { (right, left) } else { (left, right) };
 
-          op_bit_xor(
-            left,
-            &op_mul(right_flag, &op_bit_xor(right, left).unwrap()).unwrap(),
-          )
-          .unwrap()
+          op_bit_xor(left, &op_mul(right_flag, &op_bit_xor(right, left)?)?)
         }));
       }
     }
@@ -107,94 +108,88 @@ fn optimized_direct_merge<'a>(
   None
 }
 
-fn set_type(val: &Val, type_: VsType) -> Val {
+/**
+ * Gives the result of `direct_merge` the type of the values that were merged. (Merging bools
+ * computes `flag * left ^ flag * right`, which is a number.)
+ */
+fn set_type(val: &Val, type_: VsType) -> Result<Val, Val> {
   if val.typeof_() == type_ {
-    return val.clone();
+    return Ok(val.clone());
   }
 
-  match val {
-    Val::Bool(b) => match type_ {
-      VsType::Number => match b {
-        true => Val::Number(1.0),
-        false => Val::Number(0.0),
-      },
-      _ => panic!("Unexpected type_ {}", type_),
-    },
-    Val::Number(n) => match type_ {
-      VsType::Bool => {
-        if *n == 1.0 {
-          Val::Bool(true)
-        } else if *n == 0.0 {
-          Val::Bool(false)
-        } else {
-          panic!("Cannot convert to bool: {}", n)
-        }
-      }
-      _ => panic!("Unexpected type_ {}", type_),
-    },
-    Val::Dynamic(dynamic) => match dynamic.as_any().downcast_ref::<CircuitSignal>() {
+  match (val, type_) {
+    (Val::Bool(b), VsType::Number) => Ok(Val::Number(if *b { 1.0 } else { 0.0 })),
+    (Val::Number(n), VsType::Bool) if *n == 0.0 || *n == 1.0 => Ok(Val::Bool(*n == 1.0)),
+    _ => match val_dynamic_downcast::<CircuitSignal>(val) {
       Some(signal) => {
         let mut signal = signal.clone();
         signal.type_ = type_;
-        signal.to_dynamic_val()
+        Ok(signal.to_dynamic_val())
       }
-      _ => panic!("Unexpected non-signal dynamic"),
+      None => Err(format!("Can't merge {} as a {}", val.codify(), type_).to_internal_error()),
     },
-    _ => panic!("Unexpected val case"),
   }
 }
 
 fn arithmetic_merge_impl<'a>(
-  direct_merge: &impl Fn(&'a Val, &'a Val) -> Val,
+  direct_merge: &impl Fn(&'a Val, &'a Val) -> Result<Val, Val>,
   left: &'a Val,
   right: &'a Val,
-) -> Val {
+) -> Result<Val, Val> {
   if quick_val_eq(left, right) {
-    return left.clone();
+    return Ok(left.clone());
   }
 
   if is_circuit_ish(left) && is_circuit_ish(right) {
     let type_ = left.typeof_();
 
-    assert!(
-      right.typeof_() == type_,
-      "Cannot merge {} with {}",
-      type_,
-      right.typeof_()
-    );
-
-    assert!(type_ == VsType::Bool || type_ == VsType::Number);
+    if right.typeof_() != type_ {
+      return Err(
+        format!(
+          "Can't merge a {} with a {} after branching on a signal",
+          type_,
+          right.typeof_()
+        )
+        .to_type_error(),
+      );
+    }
 
-    return set_type(&direct_merge(left, right), type_);
+    return set_type(&direct_merge(left, right)?, type_);
   }
 
   match (left, right) {
     (Val::Array(left_arr), Val::Array(right_arr)) => {
       if left_arr.elements.len() != right_arr.elements.len() {
-        return CouldNotMerge(left.clone(), right.clone()).to_dynamic_val();
+        return Ok(CouldNotMerge(left.clone(), right.clone()).to_dynamic_val());
       }
 
-      return (0..left_arr.elements.len())
-        .map(|i| arithmetic_merge_impl(direct_merge, &left_arr.elements[i], &right_arr.elements[i]))
-        .collect::<Vec<_>>()
-        .to_val();
+      return Ok(
+        (0..left_arr.elements.len())
+          .map(|i| {
+            arithmetic_merge_impl(direct_merge, &left_arr.elements[i], &right_arr.elements[i])
+          })
+          .collect::<Result<Vec<_>, _>>()?
+          .to_val(),
+      );
     }
     (Val::Object(left), Val::Object(right)) => {
-      return VsObject {
-        string_map: arithmetic_merge_map(direct_merge, &left.string_map, &right.string_map),
-        symbol_map: arithmetic_merge_map(direct_merge, &left.symbol_map, &right.symbol_map),
-        prototype: arithmetic_merge_impl(direct_merge, &left.prototype, &right.prototype),
-      }
-      .to_val()
+      return Ok(
+        VsObject {
+          string_map: arithmetic_merge_map(direct_merge, &left.string_map, &right.string_map)?,
+          symbol_map: arithmetic_merge_map(direct_merge, &left.symbol_map, &right.symbol_map)?,
+          prototype: arithmetic_merge_impl(direct_merge, &left.prototype, &right.prototype)?,
+        }
+        .to_val(),
+      )
     }
     _ => {}
   };
 
   if let Ok(true) = op_triple_eq_impl(left, right) {
-    return left.clone();
+    return Ok(left.clone());
   }
 
-  CouldNotMerge(left.clone(), right.clone()).to_dynamic_val()
+  Ok(CouldNotMerge(left.clone(), right.clone()).to_dynamic_val())
 }
 
 fn quick_val_eq(left: &Val, right: &Val) -> bool {
@@ -228,27 +223,26 @@ fn is_circuit_ish(val: &Val) -> bool {
 }
 
 fn arithmetic_merge_map<'a, K: std::cmp::Ord + Clone>(
-  direct_merge: &impl Fn(&'a Val, &'a Val) -> Val,
+  direct_merge: &impl Fn(&'a Val, &'a Val) -> Result<Val, Val>,
   left: &'a BTreeMap<K, Val>,
   right: &'a BTreeMap<K, Val>,
-) -> BTreeMap<K, Val> {
-  if left.len() != right.len() {
-    panic!("Could not merge");
+) -> Result<BTreeMap<K, Val>, Val> {
+  if left.len() != right.len() || left.keys().any(|k| !right.contains_key(k)) {
+    return Err(
+      "Can't merge objects with different keys after branching on a signal".to_type_error(),
+    );
   }
 
   let mut res = BTreeMap::<K, Val>::new();
 
   for (k, left_value) in left {
-    match right.get(k) {
-      Some(right_value) => res.insert(
-        k.clone(),
-        arithmetic_merge_impl(direct_merge, left_value, right_value),
-      ),
-      None => panic!("Could not merge"),
-    };
+    res.insert(
+      k.clone(),
+      arithmetic_merge_impl(direct_merge, left_value, &right[k])?,
+    );
   }
 
-  res
+  Ok(res)
 }
 
 #[derive(Clone)]
@@ -271,8 +265,8 @@ impl ValTrait for CouldNotMerge {
     false
   }
 
-  fn is_truthy(&self) -> bool {
-    true
+  fn is_truthy(&self) -> Result<bool, Val> {
+    Ok(true)
   }
 
   fn is_nullish(&self) -> bool {
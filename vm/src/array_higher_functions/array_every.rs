@@ -1,5 +1,6 @@
+use super::super::circuit_signal::try_is_truthy;
 use super::super::native_frame_function::NativeFrameFunction;
-use super::super::vs_value::{Val, ValTrait};
+use super::super::vs_value::Val;
 use super::array_mapping_frame::{ArrayMappingFrame, ArrayMappingState};
 
 pub static EVERY: NativeFrameFunction = NativeFrameFunction {
@@ -11,11 +12,13 @@ pub static EVERY: NativeFrameFunction = NativeFrameFunction {
 struct EveryState {}
 
 impl ArrayMappingState for EveryState {
-  fn process(&mut self, _i: usize, _element: &Val, mapped: Val) -> Option<Val> {
-    match mapped.is_truthy() {
-      true => None,
-      false => Some(Val::Bool(false)),
-    }
+  fn process(&mut self, _i: usize, _element: &Val, mapped: Val) -> Result<Option<Val>, Val> {
+    Ok(
+      match try_is_truthy(&mapped, "The result of the every callback")? {
+        true => None,
+        false => Some(Val::Bool(false)),
+      },
+    )
   }
 
   fn finish(&mut self) -> Val {
@@ -1,7 +1,8 @@
 use crate::vs_value::ToVal;
 
+use super::super::circuit_signal::try_is_truthy;
 use super::super::native_frame_function::NativeFrameFunction;
-use super::super::vs_value::{Val, ValTrait};
+use super::super::vs_value::Val;
 use super::array_mapping_frame::{ArrayMappingFrame, ArrayMappingState};
 
 pub static FILTER: NativeFrameFunction = NativeFrameFunction {
@@ -15,12 +16,12 @@ struct FilterState {
 }
 
 impl ArrayMappingState for FilterState {
-  fn process(&mut self, _i: usize, element: &Val, mapped: Val) -> Option<Val> {
-    if mapped.is_truthy() {
+  fn process(&mut self, _i: usize, element: &Val, mapped: Val) -> Result<Option<Val>, Val> {
+    if try_is_truthy(&mapped, "The result of the filter callback")? {
       self.filter_results.push(element.clone());
     }
 
-    None
+    Ok(None)
   }
 
   fn finish(&mut self) -> Val {
@@ -1,5 +1,6 @@
+use super::super::circuit_signal::try_is_truthy;
 use super::super::native_frame_function::NativeFrameFunction;
-use super::super::vs_value::{Val, ValTrait};
+use super::super::vs_value::Val;
 use super::array_mapping_frame::{ArrayMappingFrame, ArrayMappingState};
 
 pub static FIND: NativeFrameFunction = NativeFrameFunction {
@@ -11,11 +12,13 @@ pub static FIND: NativeFrameFunction = NativeFrameFunction {
 struct FindState {}
 
 impl ArrayMappingState for FindState {
-  fn process(&mut self, _i: usize, element: &Val, mapped: Val) -> Option<Val> {
-    match mapped.is_truthy() {
-      true => Some(element.clone()),
-      false => None,
-    }
+  fn process(&mut self, _i: usize, element: &Val, mapped: Val) -> Result<Option<Val>, Val> {
+    Ok(
+      match try_is_truthy(&mapped, "The result of the find callback")? {
+        true => Some(element.clone()),
+        false => None,
+      },
+    )
   }
 
   fn finish(&mut self) -> Val {
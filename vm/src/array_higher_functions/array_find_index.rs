@@ -1,5 +1,6 @@
+use super::super::circuit_signal::try_is_truthy;
 use super::super::native_frame_function::NativeFrameFunction;
-use super::super::vs_value::{Val, ValTrait};
+use super::super::vs_value::Val;
 use super::array_mapping_frame::{ArrayMappingFrame, ArrayMappingState};
 
 pub static FIND_INDEX: NativeFrameFunction = NativeFrameFunction {
@@ -11,11 +12,13 @@ pub static FIND_INDEX: NativeFrameFunction = NativeFrameFunction {
 struct FindIndexState {}
 
 impl ArrayMappingState for FindIndexState {
-  fn process(&mut self, i: usize, _element: &Val, mapped: Val) -> Option<Val> {
-    match mapped.is_truthy() {
-      true => Some(Val::Number(i as f64)),
-      false => None,
-    }
+  fn process(&mut self, i: usize, _element: &Val, mapped: Val) -> Result<Option<Val>, Val> {
+    Ok(
+      match try_is_truthy(&mapped, "The result of the findIndex callback")? {
+        true => Some(Val::Number(i as f64)),
+        false => None,
+      },
+    )
   }
 
   fn finish(&mut self) -> Val {
@@ -15,7 +15,7 @@ struct FlatMapState {
 }
 
 impl ArrayMappingState for FlatMapState {
-  fn process(&mut self, _i: usize, _element: &Val, mapped: Val) -> Option<Val> {
+  fn process(&mut self, _i: usize, _element: &Val, mapped: Val) -> Result<Option<Val>, Val> {
     match mapped.as_array_data() {
       None => self.flat_map_results.push(mapped),
       Some(array_data) => {
@@ -25,7 +25,7 @@ impl ArrayMappingState for FlatMapState {
       }
     }
 
-    None
+    Ok(None)
   }
 
   fn finish(&mut self) -> Val {
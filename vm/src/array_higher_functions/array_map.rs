@@ -15,9 +15,9 @@ struct MapState {
 }
 
 impl ArrayMappingState for MapState {
-  fn process(&mut self, _i: usize, _element: &Val, mapped: Val) -> Option<Val> {
+  fn process(&mut self, _i: usize, _element: &Val, mapped: Val) -> Result<Option<Val>, Val> {
     self.map_results.push(mapped);
-    None
+    Ok(None)
   }
 
   fn finish(&mut self) -> Val {
@@ -3,13 +3,14 @@ use std::rc::Rc;
 
 use crate::builtins::type_error_builtin::ToTypeError;
 use crate::native_function::ThisWrapper;
+use crate::numeric_model::NumericModel;
 use crate::stack_frame::{CallResult, FrameStepOk, StackFrameTrait};
 use crate::stack_frame::{FrameStepResult, StackFrame};
 use crate::vs_array::VsArray;
 use crate::vs_value::{LoadFunctionResult, Val, ValTrait};
 
 pub trait ArrayMappingState {
-  fn process(&mut self, i: usize, element: &Val, mapped: Val) -> Option<Val>;
+  fn process(&mut self, i: usize, element: &Val, mapped: Val) -> Result<Option<Val>, Val>;
   fn finish(&mut self) -> Val;
   fn clone_to_array_mapping_state(&self) -> Box<dyn ArrayMappingState>;
 }
@@ -67,7 +68,7 @@ impl StackFrameTrait for ArrayMappingFrame {
     self.param_i += 1;
   }
 
-  fn step(&mut self) -> FrameStepResult {
+  fn step(&mut self, _numeric_model: &NumericModel) -> FrameStepResult {
     let array_data = match &self.this {
       None => return Err("Array fn called on non-array".to_type_error()),
       Some(ad) => ad,
@@ -102,7 +103,7 @@ impl StackFrameTrait for ArrayMappingFrame {
                   Val::Array(array_data.clone()),
                 ],
               )?,
-            ) {
+            )? {
               None => Ok(FrameStepOk::Continue),
               Some(val) => Ok(FrameStepOk::Pop(CallResult {
                 return_: val,
@@ -140,7 +141,7 @@ impl StackFrameTrait for ArrayMappingFrame {
     self.early_exit = self
       .state
       .process(array_i, element, call_result.return_)
-      .map(Ok);
+      .transpose();
   }
 
   fn get_call_result(&mut self) -> CallResult {
@@ -4,6 +4,7 @@ use std::rc::Rc;
 use crate::builtins::type_error_builtin::ToTypeError;
 use crate::native_frame_function::NativeFrameFunction;
 use crate::native_function::ThisWrapper;
+use crate::numeric_model::NumericModel;
 use crate::stack_frame::{CallResult, FrameStepOk, FrameStepResult, StackFrame, StackFrameTrait};
 use crate::vs_array::VsArray;
 use crate::vs_value::{LoadFunctionResult, Val, ValTrait};
@@ -50,7 +51,7 @@ impl StackFrameTrait for ReduceFrame {
     self.param_i += 1;
   }
 
-  fn step(&mut self) -> FrameStepResult {
+  fn step(&mut self, _numeric_model: &NumericModel) -> FrameStepResult {
     let array_data = match &self.this {
       None => return Err("reduce called on non-array".to_type_error()),
       Some(ad) => ad,
@@ -4,6 +4,7 @@ use std::rc::Rc;
 use crate::builtins::type_error_builtin::ToTypeError;
 use crate::native_frame_function::NativeFrameFunction;
 use crate::native_function::ThisWrapper;
+use crate::numeric_model::NumericModel;
 use crate::stack_frame::{CallResult, FrameStepOk, StackFrameTrait};
 use crate::stack_frame::{FrameStepResult, StackFrame};
 use crate::vs_array::VsArray;
@@ -219,7 +220,7 @@ impl StackFrameTrait for SortFrame {
     self.param_i += 1;
   }
 
-  fn step(&mut self) -> FrameStepResult {
+  fn step(&mut self, _numeric_model: &NumericModel) -> FrameStepResult {
     if !self.started {
       let array_data = match &mut self.this {
         None => return Err("array fn called on non-array".to_type_error()),
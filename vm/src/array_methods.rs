@@ -8,17 +8,23 @@ use crate::array_higher_functions::{
   array_some::SOME, array_sort::SORT,
 };
 use crate::builtins::internal_error_builtin::ToInternalError;
+use crate::circuit_signal::is_signal;
 use crate::helpers::{to_wrapping_index, to_wrapping_index_clamped};
 use crate::iteration::array_entries_iterator::ArrayEntriesIterator;
 use crate::iteration::array_iterator::ArrayIterator;
 use crate::native_function::{native_fn, NativeFunction};
 use crate::operations::op_triple_eq_impl;
+use crate::signal_index::op_sub_array_signal;
 use crate::todo_fn::TODO;
 use crate::vs_array::VsArray;
 use crate::vs_symbol::VsSymbol;
 use crate::vs_value::{ToDynamicVal, ToVal, Val, ValTrait, VsType};
 
 pub fn op_sub_array(array: &mut Rc<VsArray>, key: &Val) -> Result<Val, Val> {
+  if is_signal(key) {
+    return op_sub_array_signal(array, key);
+  }
+
   if let Some(index) = key.to_index() {
     return op_sub_array_index(array, index);
   }
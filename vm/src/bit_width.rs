@@ -0,0 +1,317 @@
+use serde_json::json;
+
+use crate::{
+  binary_op::BinaryOp,
+  circuit::{Circuit, Gate},
+  unary_op::UnaryOp,
+};
+
+/**
+ * Computes the number of bits each wire of the circuit needs, capped at `max_width`.
+ *
+ * This is a range analysis over unsigned values (matching `NumberOrBool`, where negative numbers
+ * wrap). Each wire gets an upper bound, starting from:
+ * - inputs declared with a width (`summon.uint(8)` has max 255, `summon.bool()` has max 1)
+ * - constants (their value)
+ *
+ * and then propagated through the gates, e.g. comparisons are 1 bit and the sum of two 8-bit
+ * values is 9 bits. Anything that might wrap (subtraction, negation, signed inputs, `number`
+ * inputs without a declared width, etc) is unbounded and gets `max_width`.
+ */
+pub fn infer_bit_widths(circuit: &Circuit, max_width: usize) -> Vec<usize> {
+  bounds_to_widths(&infer_bounds(circuit, max_width), max_width)
+}
+
+/**
+ * The upper bound of each wire, or `None` if the wire may need the full width.
+ */
+pub fn infer_bounds(circuit: &Circuit, max_width: usize) -> Vec<Option<u128>> {
+  let limit = width_max(max_width);
+  let signed = infer_signedness(circuit);
+  let mut bounds = vec![None::<u128>; circuit.size];
+
+  for input in circuit.inputs.values() {
+    bounds[input.wire_id] = match input.bit_width {
+      Some(bits) if !input.signed => Some(width_max(bits)),
+      _ => None,
+    };
+  }
+
+  for (wire_id, value) in &circuit.constants {
+    bounds[*wire_id] = if let Some(b) = value.as_bool() {
+      Some(b as u128)
+    } else {
+      value.as_u64().map(|x| x as u128)
+    };
+  }
+
+  for gate in &circuit.gates {
+    match gate {
+      Gate::Unary { op, input, output } => {
+        bounds[*output] = unary_bound(*op, bounds[*input]);
+      }
+      Gate::Binary {
+        op,
+        left,
+        right,
+        output,
+      } => {
+        bounds[*output] = match op {
+          // Signed division can be negative
+          BinaryOp::Div | BinaryOp::Mod if signed[*left] || signed[*right] => None,
+          _ => binary_bound(*op, bounds[*left], bounds[*right]),
+        };
+      }
+    }
+
+    let output = match gate {
+      Gate::Unary { output, .. } | Gate::Binary { output, .. } => *output,
+    };
+
+    if bounds[output].is_some_and(|bound| bound > limit) {
+      bounds[output] = None;
+    }
+  }
+
+  bounds
+}
+
+pub fn bounds_to_widths(bounds: &[Option<u128>], max_width: usize) -> Vec<usize> {
+  bounds
+    .iter()
+    .map(|bound| match bound {
+      Some(bound) => bits_needed(*bound).min(max_width),
+      None => max_width,
+    })
+    .collect()
+}
+
+fn unary_bound(op: UnaryOp, input: Option<u128>) -> Option<u128> {
+  match op {
+    UnaryOp::Plus => input,
+    UnaryOp::Not => Some(1),
+    UnaryOp::Minus | UnaryOp::BitNot => None,
+  }
+}
+
+fn binary_bound(op: BinaryOp, left: Option<u128>, right: Option<u128>) -> Option<u128> {
+  use BinaryOp::*;
+
+  match op {
+    LooseEq | LooseNe | Eq | Ne | And | Or | Less | LessEq | Greater | GreaterEq => Some(1),
+    Plus => left?.checked_add(right?),
+    Mul => left?.checked_mul(right?),
+    Minus => None,
+    Div => left,
+    Mod => match (left, right) {
+      (Some(left), Some(right)) => Some(left.min(right.saturating_sub(1))),
+      (None, Some(right)) => Some(right.saturating_sub(1)),
+      (left, None) => left,
+    },
+    Exp => {
+      let exponent = u32::try_from(right?).ok()?;
+      left?.checked_pow(exponent)
+    }
+    BitAnd => match (left, right) {
+      (Some(left), Some(right)) => Some(left.min(right)),
+      (Some(bound), None) | (None, Some(bound)) => Some(bound),
+      (None, None) => None,
+    },
+    BitOr | BitXor => Some(width_max(bits_needed(left?.max(right?)))),
+    LeftShift => {
+      let shift = u32::try_from(right?).ok()?;
+      let left = left?;
+
+      if shift >= 128 || bits_needed(left) + shift as usize > 128 {
+        return None;
+      }
+
+      Some(left << shift)
+    }
+    RightShift | RightShiftUnsigned => left,
+  }
+}
+
+fn bits_needed(bound: u128) -> usize {
+  (128 - bound.leading_zeros() as usize).max(1)
+}
+
+fn width_max(bits: usize) -> u128 {
+  if bits >= 128 {
+    u128::MAX
+  } else {
+    (1u128 << bits) - 1
+  }
+}
+
+/**
+ * A wire is signed if it comes from a signed input (`summon.int(bits)`) via arithmetic. This
+ * decides whether comparisons and division are signed and whether `>>` sign-extends.
+ */
+pub fn infer_signedness(circuit: &Circuit) -> Vec<bool> {
+  let mut signed = vec![false; circuit.size];
+
+  for input in circuit.inputs.values() {
+    signed[input.wire_id] = input.signed;
+  }
+
+  for gate in &circuit.gates {
+    match gate {
+      Gate::Unary { op, input, output } => {
+        signed[*output] = *op != UnaryOp::Not && signed[*input];
+      }
+      Gate::Binary {
+        op,
+        left,
+        right,
+        output,
+      } => {
+        use BinaryOp::*;
+
+        signed[*output] = match op {
+          LooseEq | LooseNe | Eq | Ne | And | Or | Less | LessEq | Greater | GreaterEq => false,
+          LeftShift | RightShift | RightShiftUnsigned => signed[*left],
+          Plus | Minus | Mul | Div | Mod | Exp | BitAnd | BitOr | BitXor => {
+            signed[*left] || signed[*right]
+          }
+        };
+      }
+    }
+  }
+
+  signed
+}
+
+/**
+ * A wire is a bool if it's a bool input or constant, or the result of a comparison, `!`, `&&` or
+ * `||` (which always give bools, see `NumberOrBool`). Other wires are numbers.
+ */
+pub fn infer_bools(circuit: &Circuit) -> Vec<bool> {
+  let mut bools = vec![false; circuit.size];
+
+  for input in circuit.inputs.values() {
+    bools[input.wire_id] = input.type_json == json!("bool");
+  }
+
+  for (wire, value) in &circuit.constants {
+    bools[*wire] = value.is_boolean();
+  }
+
+  for gate in &circuit.gates {
+    use BinaryOp::*;
+
+    bools[gate.output()] = match *gate {
+      Gate::Unary { op, input, .. } => match op {
+        UnaryOp::Plus => bools[input],
+        UnaryOp::Not => true,
+        UnaryOp::Minus | UnaryOp::BitNot => false,
+      },
+      Gate::Binary { op, .. } => matches!(
+        op,
+        LooseEq | LooseNe | Eq | Ne | And | Or | Less | LessEq | Greater | GreaterEq
+      ),
+    };
+  }
+
+  bools
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+  use crate::circuit::test_circuit;
+
+  /**
+   * A circuit with 8-bit inputs `a` (wire 0) and `b` (wire 1), `constants` on the following wires
+   * and then a wire for each gate, given as `(op, left, right)`.
+   */
+  fn circuit(signed: bool, constants: &[u64], gates: &[(BinaryOp, usize, usize)]) -> Circuit {
+    let input = json!({ "type": if signed { "int" } else { "uint" }, "bits": 8 });
+    let constants = constants.iter().map(|x| json!(x)).collect::<Vec<_>>();
+    let first_gate_wire = 2 + constants.len();
+
+    let gates = gates
+      .iter()
+      .enumerate()
+      .map(|(i, (op, left, right))| Gate::Binary {
+        op: *op,
+        left: *left,
+        right: *right,
+        output: first_gate_wire + i,
+      })
+      .collect::<Vec<_>>();
+
+    test_circuit(&[input.clone(), input], &constants, &gates, &[])
+  }
+
+  #[test]
+  fn binary_bound_grows_with_add_mul_and_shifts() {
+    use BinaryOp::*;
+
+    assert_eq!(binary_bound(Plus, Some(255), Some(255)), Some(510));
+    assert_eq!(binary_bound(Mul, Some(255), Some(255)), Some(65025));
+    assert_eq!(binary_bound(LeftShift, Some(255), Some(3)), Some(2040));
+    assert_eq!(binary_bound(RightShift, Some(255), Some(3)), Some(255));
+    assert_eq!(binary_bound(RightShiftUnsigned, Some(255), None), Some(255));
+
+    assert_eq!(binary_bound(Plus, Some(255), None), None);
+    assert_eq!(binary_bound(LeftShift, Some(255), None), None);
+    assert_eq!(binary_bound(LeftShift, Some(1), Some(128)), None);
+    assert_eq!(binary_bound(Minus, Some(255), Some(1)), None);
+  }
+
+  #[test]
+  fn binary_bound_comparisons_are_one_bit() {
+    use BinaryOp::*;
+
+    for op in [LooseEq, LooseNe, Eq, Ne, Less, LessEq, Greater, GreaterEq] {
+      assert_eq!(binary_bound(op, None, None), Some(1), "{:?}", op);
+    }
+  }
+
+  #[test]
+  fn infer_bit_widths_of_gates() {
+    use BinaryOp::*;
+
+    let circuit = circuit(
+      false,
+      &[2, 1],
+      &[
+        (Plus, 0, 1),       // 4: 9 bits
+        (Mul, 0, 1),        // 5: 16 bits
+        (Less, 0, 1),       // 6: 1 bit
+        (LeftShift, 0, 2),  // 7: 10 bits
+        (RightShift, 0, 3), // 8: 8 bits
+        (Minus, 0, 1),      // 9: may wrap
+        (Mul, 5, 5),        // 10: 32 bits
+      ],
+    );
+
+    assert_eq!(
+      infer_bit_widths(&circuit, 64),
+      vec![8, 8, 2, 1, 9, 16, 1, 10, 8, 64, 32],
+    );
+
+    // The 32 bit product is over the limit, so it's unbounded
+    let bounds = infer_bounds(&circuit, 16);
+    assert_eq!(bounds[5], Some(65025));
+    assert_eq!(bounds[10], None);
+
+    assert_eq!(
+      infer_bit_widths(&circuit, 16),
+      vec![8, 8, 2, 1, 9, 16, 1, 10, 8, 16, 16],
+    );
+  }
+
+  #[test]
+  fn infer_bit_widths_of_signed_inputs() {
+    use BinaryOp::*;
+
+    let circuit = circuit(true, &[], &[(Plus, 0, 1), (Less, 0, 1)]);
+
+    assert_eq!(infer_bounds(&circuit, 32), vec![None, None, None, Some(1)]);
+    assert_eq!(infer_bit_widths(&circuit, 32), vec![32, 32, 32, 1]);
+  }
+}
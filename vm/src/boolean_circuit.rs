@@ -0,0 +1,687 @@
+use std::{cmp::max, collections::BTreeMap};
+
+use bristol_circuit::{BristolCircuit, CircuitInfo, ConstantInfo, Gate as BristolGate, IOInfo};
+use serde_json::json;
+
+use crate::{
+  binary_op::BinaryOp,
+  bit_width::{infer_bit_widths, infer_bools, infer_signedness},
+  circuit::{Circuit, Gate},
+  summon_type::MAX_BITS,
+  unary_op::UnaryOp,
+};
+
+/**
+ * Lowers a circuit to a boolean circuit made of `AND`, `XOR` and `INV` gates.
+ *
+ * Each wire gets the width inferred by `infer_bit_widths` (at most `width`), so a flag costs one
+ * wire while a full-width number costs `width` wires. Values are little-endian: bit `i` of an
+ * input/output is at `address + i`.
+ *
+ * The wires are laid out in the usual bristol order: inputs first (in the order they were
+ * created), then constants (`constant_false`/`constant_true`, only when needed), then internal
+ * wires, and finally the outputs.
+ *
+ * Arithmetic is modulo `2^width`, matching `NumberOrBool` when `width` is 64. The exceptions are
+ * values derived from signed inputs (`summon.int(bits)`), which use signed comparison, signed
+ * division (truncating towards zero) and an arithmetic (sign-extending) `>>`. `FixedWidthNumber`
+ * evaluates circuits with the same semantics.
+ *
+ * Outputs have the type `bool` or `number` depending on the wire (see `infer_bools`). Constants
+ * must be non-negative integers that fit in 64 bits (or bools), otherwise this is an error.
+ */
+pub fn to_boolean_bristol(circuit: &Circuit, width: usize) -> Result<BristolCircuit, String> {
+  assert!(
+    (1..=MAX_BITS).contains(&width),
+    "Boolean width must be from 1 to {}",
+    MAX_BITS
+  );
+
+  let widths = infer_bit_widths(circuit, width);
+  let signed = infer_signedness(circuit);
+  let bools = infer_bools(circuit);
+
+  let mut builder = BooleanBuilder::default();
+  let mut values = vec![Vec::<Bit>::new(); circuit.size];
+
+  let mut inputs = circuit.inputs.iter().collect::<Vec<_>>();
+  inputs.sort_by_key(|(_, input)| input.wire_id);
+
+  let mut input_infos = Vec::<IOInfo>::new();
+
+  for (name, input) in inputs {
+    let address = builder.wire_count;
+
+    values[input.wire_id] = (0..widths[input.wire_id])
+      .map(|_| Bit::Wire(builder.new_wire()))
+      .collect();
+
+    input_infos.push(IOInfo {
+      name: name.clone(),
+      type_: input.type_json.clone(),
+      address,
+      width: widths[input.wire_id],
+    });
+  }
+
+  let input_wire_count = builder.wire_count;
+
+  for (wire_id, value) in &circuit.constants {
+    let x = match value.as_bool() {
+      Some(b) => b as u64,
+      None => value.as_u64().ok_or(format!(
+        "Unsupported constant in boolean circuit: {}",
+        value
+      ))?,
+    };
+
+    values[*wire_id] = (0..widths[*wire_id])
+      .map(|i| Bit::Const((x >> i) & 1 == 1))
+      .collect();
+  }
+
+  for gate in &circuit.gates {
+    match gate {
+      Gate::Unary { op, input, output } => {
+        values[*output] = resize(
+          &builder.lower_unary(*op, &values[*input], widths[*output]),
+          widths[*output],
+        );
+      }
+      Gate::Binary {
+        op,
+        left,
+        right,
+        output,
+      } => {
+        let left = Operand {
+          bits: &values[*left],
+          signed: signed[*left],
+        };
+
+        let right = Operand {
+          bits: &values[*right],
+          signed: signed[*right],
+        };
+
+        values[*output] = resize(
+          &builder.lower_binary(*op, left, right, widths[*output], width),
+          widths[*output],
+        );
+      }
+    }
+  }
+
+  // Outputs need to be distinct gate outputs so that they can be moved to the end. Anything else
+  // (inputs, constants, bits shared with another output) gets copied.
+  let mut claimed = vec![false; builder.wire_count];
+  let mut output_wires = Vec::<(String, usize, Vec<usize>)>::new();
+
+  for (name, wire_id) in &circuit.outputs {
+    let mut wires = Vec::<usize>::new();
+
+    for bit in &values[*wire_id] {
+      let wire = match *bit {
+        Bit::Wire(w) if w >= input_wire_count && !claimed[w] && !builder.is_constant(w) => w,
+        bit => builder.copy(bit),
+      };
+
+      if wire >= claimed.len() {
+        claimed.resize(wire + 1, false);
+      }
+
+      claimed[wire] = true;
+      wires.push(wire);
+    }
+
+    output_wires.push((name.clone(), *wire_id, wires));
+  }
+
+  claimed.resize(builder.wire_count, false);
+
+  let mut final_ids = vec![usize::MAX; builder.wire_count];
+  let mut next_id = 0;
+
+  for final_id in final_ids.iter_mut().take(input_wire_count) {
+    *final_id = next_id;
+    next_id += 1;
+  }
+
+  for (_, wire) in builder.constant_wires.iter().flatten() {
+    final_ids[*wire] = next_id;
+    next_id += 1;
+  }
+
+  for gate in &builder.gates {
+    for output in &gate.outputs {
+      if !claimed[*output] {
+        final_ids[*output] = next_id;
+        next_id += 1;
+      }
+    }
+  }
+
+  let mut output_infos = Vec::<IOInfo>::new();
+
+  for (name, wire_id, wires) in &output_wires {
+    let address = next_id;
+
+    for wire in wires {
+      final_ids[*wire] = next_id;
+      next_id += 1;
+    }
+
+    output_infos.push(IOInfo {
+      name: name.clone(),
+      type_: if bools[*wire_id] {
+        json!("bool")
+      } else {
+        json!("number")
+      },
+      address,
+      width: wires.len(),
+    });
+  }
+
+  let constants = builder
+    .constant_wires
+    .iter()
+    .flatten()
+    .map(|(value, wire)| ConstantInfo {
+      name: format!("constant_{}", value),
+      type_: json!("bool"),
+      value: json!(value),
+      address: final_ids[*wire],
+      width: 1,
+    })
+    .collect();
+
+  let gates = builder
+    .gates
+    .into_iter()
+    .map(|gate| BristolGate {
+      inputs: gate.inputs.iter().map(|w| final_ids[*w]).collect(),
+      outputs: gate.outputs.iter().map(|w| final_ids[*w]).collect(),
+      op: gate.op,
+    })
+    .collect();
+
+  Ok(BristolCircuit {
+    wire_count: next_id,
+    info: CircuitInfo {
+      constants,
+      inputs: input_infos,
+      outputs: output_infos,
+    },
+    gates,
+  })
+}
+
+/**
+ * Evaluates a boolean circuit (as produced by `to_boolean_bristol`) using little-endian values.
+ */
+pub fn eval_boolean_bristol(
+  circuit: &BristolCircuit,
+  inputs: &BTreeMap<String, usize>,
+) -> BTreeMap<String, usize> {
+  let mut wires = vec![false; circuit.wire_count];
+
+  for input in &circuit.info.inputs {
+    let value = inputs.get(&input.name).expect("Missing input");
+
+    for i in 0..input.width {
+      wires[input.address + i] = i < usize::BITS as usize && (value >> i) & 1 == 1;
+    }
+  }
+
+  for constant in &circuit.info.constants {
+    wires[constant.address] = constant.value.as_bool().expect("Expected bool constant");
+  }
+
+  for gate in &circuit.gates {
+    wires[gate.outputs[0]] = match gate.op.as_str() {
+      "AND" => wires[gate.inputs[0]] & wires[gate.inputs[1]],
+      "XOR" => wires[gate.inputs[0]] ^ wires[gate.inputs[1]],
+      "INV" => !wires[gate.inputs[0]],
+      op => panic!("Unsupported boolean gate: {}", op),
+    };
+  }
+
+  let mut res = BTreeMap::<String, usize>::new();
+
+  for output in &circuit.info.outputs {
+    let mut value = 0usize;
+
+    for i in 0..output.width.min(usize::BITS as usize) {
+      value |= (wires[output.address + i] as usize) << i;
+    }
+
+    res.insert(output.name.clone(), value);
+  }
+
+  res
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Bit {
+  Const(bool),
+  Wire(usize),
+}
+
+#[derive(Clone, Copy)]
+struct Operand<'a> {
+  bits: &'a [Bit],
+  signed: bool,
+}
+
+#[derive(Default)]
+struct BooleanBuilder {
+  wire_count: usize,
+  gates: Vec<BristolGate>,
+  constant_wires: [Option<(bool, usize)>; 2], // [false, true]
+}
+
+impl BooleanBuilder {
+  fn new_wire(&mut self) -> usize {
+    let wire = self.wire_count;
+    self.wire_count += 1;
+    wire
+  }
+
+  fn gate(&mut self, op: &str, inputs: Vec<usize>) -> Bit {
+    let output = self.new_wire();
+
+    self.gates.push(BristolGate {
+      inputs,
+      outputs: vec![output],
+      op: op.to_string(),
+    });
+
+    Bit::Wire(output)
+  }
+
+  fn constant_wire(&mut self, value: bool) -> usize {
+    if let Some((_, wire)) = self.constant_wires[value as usize] {
+      return wire;
+    }
+
+    let wire = self.new_wire();
+    self.constant_wires[value as usize] = Some((value, wire));
+
+    wire
+  }
+
+  fn is_constant(&self, wire: usize) -> bool {
+    self
+      .constant_wires
+      .iter()
+      .flatten()
+      .any(|(_, w)| *w == wire)
+  }
+
+  /** Creates a new gate output with the same value as `bit`. */
+  fn copy(&mut self, bit: Bit) -> usize {
+    let zero = self.constant_wire(false);
+
+    let copy = match bit {
+      Bit::Const(false) => self.gate("XOR", vec![zero, zero]),
+      Bit::Const(true) => self.gate("INV", vec![zero]),
+      Bit::Wire(w) => self.gate("XOR", vec![w, zero]),
+    };
+
+    match copy {
+      Bit::Wire(w) => w,
+      Bit::Const(_) => unreachable!(),
+    }
+  }
+
+  fn and(&mut self, a: Bit, b: Bit) -> Bit {
+    match (a, b) {
+      (Bit::Const(false), _) | (_, Bit::Const(false)) => Bit::Const(false),
+      (Bit::Const(true), x) | (x, Bit::Const(true)) => x,
+      (Bit::Wire(a), Bit::Wire(b)) if a == b => Bit::Wire(a),
+      (Bit::Wire(a), Bit::Wire(b)) => self.gate("AND", vec![a, b]),
+    }
+  }
+
+  fn xor(&mut self, a: Bit, b: Bit) -> Bit {
+    match (a, b) {
+      (Bit::Const(false), x) | (x, Bit::Const(false)) => x,
+      (Bit::Const(true), x) | (x, Bit::Const(true)) => self.inv(x),
+      (Bit::Wire(a), Bit::Wire(b)) if a == b => Bit::Const(false),
+      (Bit::Wire(a), Bit::Wire(b)) => self.gate("XOR", vec![a, b]),
+    }
+  }
+
+  fn inv(&mut self, a: Bit) -> Bit {
+    match a {
+      Bit::Const(x) => Bit::Const(!x),
+      Bit::Wire(a) => self.gate("INV", vec![a]),
+    }
+  }
+
+  fn or(&mut self, a: Bit, b: Bit) -> Bit {
+    let a_xor_b = self.xor(a, b);
+    let a_and_b = self.and(a, b);
+    self.xor(a_xor_b, a_and_b)
+  }
+
+  /** `cond ? a : b` */
+  fn mux(&mut self, cond: Bit, a: Bit, b: Bit) -> Bit {
+    match cond {
+      Bit::Const(true) => a,
+      Bit::Const(false) => b,
+      _ => {
+        let diff = self.xor(a, b);
+        let selected_diff = self.and(cond, diff);
+        self.xor(b, selected_diff)
+      }
+    }
+  }
+
+  fn mux_words(&mut self, cond: Bit, a: &[Bit], b: &[Bit]) -> Vec<Bit> {
+    a.iter()
+      .zip(b)
+      .map(|(a, b)| self.mux(cond, *a, *b))
+      .collect()
+  }
+
+  /** Whether any bit is set, using a balanced tree of ORs. */
+  fn any(&mut self, bits: &[Bit]) -> Bit {
+    match bits.len() {
+      0 => Bit::Const(false),
+      1 => bits[0],
+      len => {
+        let left = self.any(&bits[..len / 2]);
+        let right = self.any(&bits[len / 2..]);
+        self.or(left, right)
+      }
+    }
+  }
+
+  /**
+   * Ripple-carry adder using one AND per bit. Returns the sum (same length as the inputs) and the
+   * carry out.
+   */
+  fn add(&mut self, a: &[Bit], b: &[Bit], carry_in: Bit) -> (Vec<Bit>, Bit) {
+    let mut carry = carry_in;
+    let mut sum = Vec::<Bit>::with_capacity(a.len());
+
+    for (a, b) in a.iter().zip(b) {
+      let a_xor_b = self.xor(*a, *b);
+      sum.push(self.xor(a_xor_b, carry));
+
+      // carry' = majority(a, b, carry) = carry ^ ((a ^ carry) & (b ^ carry))
+      let a_xor_c = self.xor(*a, carry);
+      let b_xor_c = self.xor(*b, carry);
+      let flip = self.and(a_xor_c, b_xor_c);
+      carry = self.xor(carry, flip);
+    }
+
+    (sum, carry)
+  }
+
+  /** `a - b`, along with a flag that is set when there was no borrow (`a >= b`). */
+  fn sub(&mut self, a: &[Bit], b: &[Bit]) -> (Vec<Bit>, Bit) {
+    let not_b = b.iter().map(|b| self.inv(*b)).collect::<Vec<_>>();
+    self.add(a, &not_b, Bit::Const(true))
+  }
+
+  /**
+   * Unsigned `a < b`. This only computes the borrow chain of `a - b`, which needs one AND per bit
+   * and no sum bits.
+   */
+  fn less_unsigned(&mut self, a: &[Bit], b: &[Bit]) -> Bit {
+    let mut carry = Bit::Const(true);
+
+    for (a, b) in a.iter().zip(b) {
+      let not_b = self.inv(*b);
+      let a_xor_c = self.xor(*a, carry);
+      let b_xor_c = self.xor(not_b, carry);
+      let flip = self.and(a_xor_c, b_xor_c);
+      carry = self.xor(carry, flip);
+    }
+
+    self.inv(carry)
+  }
+
+  fn less(&mut self, a: Operand, b: Operand, width: usize) -> Bit {
+    if !a.signed && !b.signed {
+      let n = max(a.bits.len(), b.bits.len());
+      return self.less_unsigned(&resize(a.bits, n), &resize(b.bits, n));
+    }
+
+    // Signed comparison is unsigned comparison with the sign bits flipped
+    let mut a = resize(a.bits, width);
+    let mut b = resize(b.bits, width);
+    a[width - 1] = self.inv(a[width - 1]);
+    b[width - 1] = self.inv(b[width - 1]);
+
+    self.less_unsigned(&a, &b)
+  }
+
+  fn eq(&mut self, a: &[Bit], b: &[Bit]) -> Bit {
+    let n = max(a.len(), b.len());
+    let (a, b) = (resize(a, n), resize(b, n));
+
+    let diffs = a
+      .iter()
+      .zip(&b)
+      .map(|(a, b)| self.xor(*a, *b))
+      .collect::<Vec<_>>();
+
+    let any_diff = self.any(&diffs);
+    self.inv(any_diff)
+  }
+
+  /** Schoolbook multiplication modulo `2^len`. */
+  fn mul(&mut self, a: &[Bit], b: &[Bit], len: usize) -> Vec<Bit> {
+    let a = resize(a, len);
+    let mut acc = vec![Bit::Const(false); len];
+
+    for (i, b_bit) in b.iter().enumerate().take(len) {
+      let partial = a[..len - i]
+        .iter()
+        .map(|a_bit| self.and(*a_bit, *b_bit))
+        .collect::<Vec<_>>();
+
+      let (sum, _) = self.add(&acc[i..], &partial, Bit::Const(false));
+      acc[i..].copy_from_slice(&sum);
+    }
+
+    acc
+  }
+
+  /**
+   * Unsigned restoring division. Dividing by zero gives a quotient of all ones and a remainder
+   * equal to the dividend.
+   */
+  fn div_mod(&mut self, a: &[Bit], b: &[Bit]) -> (Vec<Bit>, Vec<Bit>) {
+    let n = max(a.len(), b.len());
+    let a = resize(a, n);
+    let b = resize(b, n + 1);
+
+    let mut quotient = vec![Bit::Const(false); n];
+    let mut rem = vec![Bit::Const(false); n + 1];
+
+    for i in (0..n).rev() {
+      rem.pop();
+      rem.insert(0, a[i]);
+
+      let (diff, no_borrow) = self.sub(&rem, &b);
+      quotient[i] = no_borrow;
+      rem = self.mux_words(no_borrow, &diff, &rem);
+    }
+
+    (quotient, resize(&rem, n))
+  }
+
+  /**
+   * Signed division, truncating towards zero like `i64`. The remainder takes the sign of the
+   * dividend. Both operands are treated as signed, like in `less`.
+   */
+  fn div_mod_signed(&mut self, a: &[Bit], b: &[Bit], width: usize) -> (Vec<Bit>, Vec<Bit>) {
+    let (a, b) = (resize(a, width), resize(b, width));
+    let (a_negative, b_negative) = (a[width - 1], b[width - 1]);
+
+    let a_abs = self.negate_if(a_negative, &a);
+    let b_abs = self.negate_if(b_negative, &b);
+
+    let (quotient, rem) = self.div_mod(&a_abs, &b_abs);
+
+    let signs_differ = self.xor(a_negative, b_negative);
+
+    (
+      self.negate_if(signs_differ, &quotient),
+      self.negate_if(a_negative, &rem),
+    )
+  }
+
+  fn negate_if(&mut self, cond: Bit, value: &[Bit]) -> Vec<Bit> {
+    let negated = self.sub(&vec![Bit::Const(false); value.len()], value).0;
+    self.mux_words(cond, &negated, value)
+  }
+
+  /** Square-and-multiply exponentiation modulo `2^len`, using (up to) 32 bits of exponent. */
+  fn exp(&mut self, base: &[Bit], exponent: &[Bit], len: usize) -> Vec<Bit> {
+    let base = resize(base, len);
+    let mut res = resize(&[Bit::Const(true)], len);
+
+    for e_bit in exponent.iter().take(32).rev() {
+      res = self.mul(&res, &res, len);
+      let multiplied = self.mul(&res, &base, len);
+      res = self.mux_words(*e_bit, &multiplied, &res);
+    }
+
+    res
+  }
+
+  /**
+   * Barrel shifter. Uses the low `ceil(log2(width))` bits of the amount, so (like
+   * `usize::wrapping_shl` at width 64) the amount is taken modulo the width when the width is a
+   * power of two.
+   */
+  fn shift(
+    &mut self,
+    value: &[Bit],
+    amount: &[Bit],
+    width: usize,
+    left: bool,
+    fill: Bit,
+  ) -> Vec<Bit> {
+    let stages = (usize::BITS - (width.max(1) - 1).leading_zeros()) as usize;
+    let mut res = value.to_vec();
+
+    for (stage, amount_bit) in amount.iter().enumerate().take(stages) {
+      let distance = 1 << stage;
+
+      let shifted = (0..res.len())
+        .map(|i| {
+          if left {
+            i.checked_sub(distance)
+              .map_or(Bit::Const(false), |j| res[j])
+          } else {
+            res.get(i + distance).copied().unwrap_or(fill)
+          }
+        })
+        .collect::<Vec<_>>();
+
+      res = self.mux_words(*amount_bit, &shifted, &res);
+    }
+
+    res
+  }
+
+  fn lower_unary(&mut self, op: UnaryOp, input: &[Bit], len: usize) -> Vec<Bit> {
+    match op {
+      UnaryOp::Plus => input.to_vec(),
+      UnaryOp::Minus => {
+        self
+          .sub(&vec![Bit::Const(false); len], &resize(input, len))
+          .0
+      }
+      UnaryOp::Not => {
+        let any = self.any(input);
+        vec![self.inv(any)]
+      }
+      UnaryOp::BitNot => resize(input, len).iter().map(|b| self.inv(*b)).collect(),
+    }
+  }
+
+  fn lower_binary(
+    &mut self,
+    op: BinaryOp,
+    left: Operand,
+    right: Operand,
+    len: usize,
+    width: usize,
+  ) -> Vec<Bit> {
+    use BinaryOp::*;
+
+    let (l, r) = (left.bits, right.bits);
+
+    match op {
+      Plus => {
+        self
+          .add(&resize(l, len), &resize(r, len), Bit::Const(false))
+          .0
+      }
+      Minus => self.sub(&resize(l, len), &resize(r, len)).0,
+      Mul => self.mul(l, r, len),
+      Div if left.signed || right.signed => self.div_mod_signed(l, r, width).0,
+      Mod if left.signed || right.signed => self.div_mod_signed(l, r, width).1,
+      Div => self.div_mod(l, r).0,
+      Mod => self.div_mod(l, r).1,
+      Exp => self.exp(l, r, len),
+      LooseEq | Eq => vec![self.eq(l, r)],
+      LooseNe | Ne => {
+        let eq = self.eq(l, r);
+        vec![self.inv(eq)]
+      }
+      And => {
+        let (l, r) = (self.any(l), self.any(r));
+        vec![self.and(l, r)]
+      }
+      Or => {
+        let (l, r) = (self.any(l), self.any(r));
+        vec![self.or(l, r)]
+      }
+      Less => vec![self.less(left, right, width)],
+      Greater => vec![self.less(right, left, width)],
+      LessEq => {
+        let greater = self.less(right, left, width);
+        vec![self.inv(greater)]
+      }
+      GreaterEq => {
+        let less = self.less(left, right, width);
+        vec![self.inv(less)]
+      }
+      BitAnd | BitOr | BitXor => {
+        let (l, r) = (resize(l, len), resize(r, len));
+
+        l.iter()
+          .zip(&r)
+          .map(|(a, b)| match op {
+            BitAnd => self.and(*a, *b),
+            BitOr => self.or(*a, *b),
+            _ => self.xor(*a, *b),
+          })
+          .collect()
+      }
+      LeftShift => self.shift(&resize(l, len), r, width, true, Bit::Const(false)),
+      RightShift if left.signed => {
+        let l = resize(l, width);
+        let sign = l[width - 1];
+        self.shift(&l, r, width, false, sign)
+      }
+      RightShift | RightShiftUnsigned => self.shift(l, r, width, false, Bit::Const(false)),
+    }
+  }
+}
+
+/** Zero-extends or truncates to `len` bits. */
+fn resize(bits: &[Bit], len: usize) -> Vec<Bit> {
+  let mut res = bits.iter().copied().take(len).collect::<Vec<_>>();
+  res.resize(len, Bit::Const(false));
+  res
+}
@@ -33,7 +33,50 @@ pub fn to_bristol_binary_op(binary_op: BinaryOp) -> String {
     BinaryOp::BitXor => "AXor",
     BinaryOp::LeftShift => "AShiftL",
     BinaryOp::RightShift => "AShiftR",
-    BinaryOp::RightShiftUnsigned => "AShiftR",
+    BinaryOp::RightShiftUnsigned => "AUShiftR",
   }
   .to_string()
 }
+
+/**
+ * The inverse of `to_bristol_unary_op`.
+ */
+pub fn from_bristol_unary_op(op: &str) -> Option<UnaryOp> {
+  Some(match op {
+    "AUnaryAdd" => UnaryOp::Plus,
+    "AUnarySub" => UnaryOp::Minus,
+    "ANot" => UnaryOp::Not,
+    "ABitNot" => UnaryOp::BitNot,
+    _ => return None,
+  })
+}
+
+/**
+ * The inverse of `to_bristol_binary_op`. Where several ops share a string, the strict version is
+ * used (e.g. `AEq` becomes `Eq`), which evaluates the same way.
+ */
+pub fn from_bristol_binary_op(op: &str) -> Option<BinaryOp> {
+  Some(match op {
+    "AAdd" => BinaryOp::Plus,
+    "ASub" => BinaryOp::Minus,
+    "AMul" => BinaryOp::Mul,
+    "ADiv" => BinaryOp::Div,
+    "AMod" => BinaryOp::Mod,
+    "AExp" => BinaryOp::Exp,
+    "AEq" => BinaryOp::Eq,
+    "ANeq" => BinaryOp::Ne,
+    "ABoolAnd" => BinaryOp::And,
+    "ABoolOr" => BinaryOp::Or,
+    "ALt" => BinaryOp::Less,
+    "ALEq" => BinaryOp::LessEq,
+    "AGt" => BinaryOp::Greater,
+    "AGEq" => BinaryOp::GreaterEq,
+    "ABitAnd" => BinaryOp::BitAnd,
+    "ABitOr" => BinaryOp::BitOr,
+    "AXor" => BinaryOp::BitXor,
+    "AShiftL" => BinaryOp::LeftShift,
+    "AShiftR" => BinaryOp::RightShift,
+    "AUShiftR" => BinaryOp::RightShiftUnsigned,
+    _ => return None,
+  })
+}
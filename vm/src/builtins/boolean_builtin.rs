@@ -1,7 +1,9 @@
 use std::{fmt, rc::Rc};
 
 use crate::{
+  circuit_signal::is_signal,
   native_function::ThisWrapper,
+  operations::op_not,
   vs_class::VsClass,
   vs_value::{LoadFunctionResult, Val},
   ValTrait,
@@ -22,10 +24,10 @@ impl BuiltinObject for BooleanBuiltin {
 
   fn bo_load_function() -> LoadFunctionResult {
     LoadFunctionResult::NativeFunction(|_: ThisWrapper, params: Vec<Val>| -> Result<Val, Val> {
-      Ok(if let Some(value) = params.first() {
-        Val::Bool(value.is_truthy())
-      } else {
-        Val::Bool(false)
+      Ok(match params.first() {
+        Some(value) if is_signal(value) => op_not(&op_not(value)?)?,
+        Some(value) => Val::Bool(value.is_truthy()?),
+        None => Val::Bool(false),
       })
     })
   }
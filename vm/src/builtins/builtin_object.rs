@@ -38,8 +38,8 @@ where
     false
   }
 
-  fn is_truthy(&self) -> bool {
-    true
+  fn is_truthy(&self) -> Result<bool, Val> {
+    Ok(true)
   }
 
   fn is_nullish(&self) -> bool {
@@ -3,7 +3,10 @@ use std::rc::Rc;
 
 use summon_common::to_u32;
 
+use crate::arithmetic_merge::arithmetic_merge;
+use crate::circuit_signal::is_signal;
 use crate::native_function::{native_fn, NativeFunction};
+use crate::operations::{op_greater, op_less, op_not, op_unary_minus};
 use crate::vs_class::VsClass;
 use crate::vs_value::{LoadFunctionResult, ToVal, Val, ValTrait};
 
@@ -37,6 +40,7 @@ impl BuiltinObject for MathBuiltin {
       "atanh" => ATANH.to_val(),
       "cbrt" => CBRT.to_val(),
       "ceil" => CEIL.to_val(),
+      "clamp" => CLAMP.to_val(),
       "clz32" => CLZ32.to_val(),
       "cos" => COS.to_val(),
       "cosh" => COSH.to_val(),
@@ -89,7 +93,75 @@ fn param_to_number(param: Option<&Val>) -> f64 {
   }
 }
 
+/**
+ * Evaluates `cond ? a : b`. When `cond` is a signal, this is done with an `arithmetic_merge` instead
+ * of branching.
+ */
+fn select(cond: &Val, a: &Val, b: &Val) -> Result<Val, Val> {
+  Ok(match cond {
+    Val::Bool(true) => a.clone(),
+    Val::Bool(false) => b.clone(),
+    _ => arithmetic_merge(cond, a, &op_not(cond)?, b)?,
+  })
+}
+
+fn signal_min(params: &[Val]) -> Result<Val, Val> {
+  let mut res = params[0].clone();
+
+  for param in &params[1..] {
+    res = select(&op_less(param, &res)?, param, &res)?;
+  }
+
+  Ok(res)
+}
+
+fn signal_max(params: &[Val]) -> Result<Val, Val> {
+  let mut res = params[0].clone();
+
+  for param in &params[1..] {
+    res = select(&op_greater(param, &res)?, param, &res)?;
+  }
+
+  Ok(res)
+}
+
+fn number_min(params: &[Val]) -> f64 {
+  let mut res = f64::INFINITY;
+
+  for param in params {
+    let x = param.to_number();
+
+    if x.is_nan() {
+      return f64::NAN;
+    }
+
+    res = res.min(x);
+  }
+
+  res
+}
+
+fn number_max(params: &[Val]) -> f64 {
+  let mut res = f64::NEG_INFINITY;
+
+  for param in params {
+    let x = param.to_number();
+
+    if x.is_nan() {
+      return f64::NAN;
+    }
+
+    res = res.max(x);
+  }
+
+  res
+}
+
 static ABS: NativeFunction = native_fn(|_this, params| {
+  if let Some(x) = params.first().filter(|x| is_signal(x)) {
+    return select(&op_less(x, &Val::Number(0.0))?, &op_unary_minus(x)?, x);
+  }
+
   let x = param_to_number(params.first());
   Ok(Val::Number(x.abs()))
 });
@@ -141,6 +213,24 @@ static CEIL: NativeFunction = native_fn(|_this, params| {
   Ok(Val::Number(x.ceil()))
 });
 
+/**
+ * Non-standard: `Math.clamp(x, min, max)` is `Math.min(Math.max(x, min), max)`.
+ */
+static CLAMP: NativeFunction = native_fn(|_this, params| {
+  let x = params.first().cloned().unwrap_or(Val::Undefined);
+  let min = params.get(1).cloned().unwrap_or(Val::Undefined);
+  let max = params.get(2).cloned().unwrap_or(Val::Undefined);
+
+  if [&x, &min, &max].iter().any(|p| is_signal(p)) {
+    return signal_min(&[signal_max(&[x, min])?, max]);
+  }
+
+  Ok(Val::Number(number_min(&[
+    Val::Number(number_max(&[x, min])),
+    max,
+  ])))
+});
+
 static CLZ32: NativeFunction = native_fn(|_this, params| {
   let x = param_to_number(params.first());
   Ok(Val::Number(to_u32(x).leading_zeros() as f64))
@@ -209,15 +299,19 @@ static LOG2: NativeFunction = native_fn(|_this, params| {
 });
 
 static MAX: NativeFunction = native_fn(|_this, params| {
-  let x = param_to_number(params.first());
-  let y = param_to_number(params.get(1));
-  Ok(Val::Number(x.max(y)))
+  if params.iter().any(is_signal) {
+    return signal_max(&params);
+  }
+
+  Ok(Val::Number(number_max(&params)))
 });
 
 static MIN: NativeFunction = native_fn(|_this, params| {
-  let x = param_to_number(params.first());
-  let y = param_to_number(params.get(1));
-  Ok(Val::Number(x.min(y)))
+  if params.iter().any(is_signal) {
+    return signal_min(&params);
+  }
+
+  Ok(Val::Number(number_min(&params)))
 });
 
 static POW: NativeFunction = native_fn(|_this, params| {
@@ -232,6 +326,19 @@ static ROUND: NativeFunction = native_fn(|_this, params| {
 });
 
 static SIGN: NativeFunction = native_fn(|_this, params| {
+  if let Some(x) = params.first().filter(|x| is_signal(x)) {
+    let positive = select(
+      &op_greater(x, &Val::Number(0.0))?,
+      &Val::Number(1.0),
+      &Val::Number(0.0),
+    )?;
+    return select(
+      &op_less(x, &Val::Number(0.0))?,
+      &Val::Number(-1.0),
+      &positive,
+    );
+  }
+
   let x = param_to_number(params.first());
   Ok(Val::Number(x.signum()))
 });
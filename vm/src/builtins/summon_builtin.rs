@@ -1,11 +1,13 @@
+use std::collections::BTreeMap;
 use std::fmt;
 use std::rc::Rc;
 
 use crate::circuit_signal::CircuitSignal;
 use crate::native_function::{native_fn, NativeFunction};
+use crate::summon_type::{SummonType, MAX_BITS};
 use crate::val_dynamic_downcast::val_dynamic_downcast;
 use crate::vs_class::VsClass;
-use crate::vs_value::{LoadFunctionResult, ToVal, Val};
+use crate::vs_value::{LoadFunctionResult, ToVal, Val, ValTrait};
 
 use super::builtin_object::BuiltinObject;
 use super::type_error_builtin::ToTypeError;
@@ -22,6 +24,11 @@ impl BuiltinObject for SummonBuiltin {
       "isSignal" => IS_SIGNAL.to_val(),
       "number" => NUMBER.to_val(),
       "bool" => BOOL.to_val(),
+      "uint" => UINT.to_val(),
+      "int" => INT.to_val(),
+      "array" => ARRAY.to_val(),
+      "tuple" => TUPLE.to_val(),
+      "object" => OBJECT.to_val(),
 
       _ => Val::Undefined,
     }
@@ -55,10 +62,7 @@ static NUMBER: NativeFunction = native_fn(|_this, params| {
     return Err("Unexpected arguments".to_type_error());
   }
 
-  Ok(Val::make_object(&[
-    ("about", "summon runtime type".to_val()),
-    ("json", "number".to_val()),
-  ]))
+  Ok(SummonType::Number.to_val())
 });
 
 static BOOL: NativeFunction = native_fn(|_this, params| {
@@ -66,8 +70,68 @@ static BOOL: NativeFunction = native_fn(|_this, params| {
     return Err("Unexpected arguments".to_type_error());
   }
 
-  Ok(Val::make_object(&[
-    ("about", "summon runtime type".to_val()),
-    ("json", "bool".to_val()),
-  ]))
+  Ok(SummonType::Bool.to_val())
+});
+
+fn param_to_bits(param: Option<&Val>) -> Result<usize, Val> {
+  match param.and_then(|bits| bits.to_index()) {
+    Some(bits) if (1..=MAX_BITS).contains(&bits) => Ok(bits),
+    _ => Err(format!("Expected `bits` to be an integer from 1 to {}", MAX_BITS).to_type_error()),
+  }
+}
+
+static UINT: NativeFunction =
+  native_fn(|_this, params| Ok(SummonType::UInt(param_to_bits(params.first())?).to_val()));
+
+static INT: NativeFunction =
+  native_fn(|_this, params| Ok(SummonType::Int(param_to_bits(params.first())?).to_val()));
+
+static ARRAY: NativeFunction = native_fn(|_this, params| {
+  let (Some(element), Some(length)) = (params.first(), params.get(1)) else {
+    return Err("Params (type, length) not provided".to_type_error());
+  };
+
+  let Some(element) = SummonType::from_val(element) else {
+    return Err("Expected `type` to be a summon runtime type".to_type_error());
+  };
+
+  let Some(length) = length.to_index() else {
+    return Err("Expected `length` to be a non-negative integer".to_type_error());
+  };
+
+  Ok(SummonType::Array(Box::new(element), length).to_val())
+});
+
+static TUPLE: NativeFunction = native_fn(|_this, params| {
+  let mut elements = Vec::<SummonType>::new();
+
+  for param in &params {
+    let Some(element) = SummonType::from_val(param) else {
+      return Err("Expected each param to be a summon runtime type".to_type_error());
+    };
+
+    elements.push(element);
+  }
+
+  Ok(SummonType::Tuple(elements).to_val())
+});
+
+static OBJECT: NativeFunction = native_fn(|_this, params| {
+  let Some(Val::Object(properties)) = params.first() else {
+    return Err("Expected an object mapping property names to types".to_type_error());
+  };
+
+  let mut res = BTreeMap::<String, SummonType>::new();
+
+  for (key, value) in &properties.string_map {
+    let Some(type_) = SummonType::from_val(value) else {
+      return Err(
+        format!("Expected property \"{}\" to be a summon runtime type", key).to_type_error(),
+      );
+    };
+
+    res.insert(key.clone(), type_);
+  }
+
+  Ok(SummonType::Object(res).to_val())
 });
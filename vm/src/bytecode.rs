@@ -1,11 +1,13 @@
 use std::{cell::RefCell, collections::HashMap, fmt, ops::Index, rc::Rc, slice::SliceIndex};
 
 use crate::bytecode_decoder::BytecodeDecoder;
+use crate::source_map::SourceMap;
 use crate::vs_value::Val;
 
 pub struct Bytecode {
   pub code: Vec<u8>,
   pub cache: RefCell<HashMap<usize, Val>>,
+  pub source_map: SourceMap,
 }
 
 impl<I: SliceIndex<[u8]>> Index<I> for Bytecode {
@@ -24,9 +26,14 @@ impl fmt::Debug for Bytecode {
 
 impl Bytecode {
   pub fn new(code: Vec<u8>) -> Bytecode {
+    Bytecode::with_source_map(code, SourceMap::default())
+  }
+
+  pub fn with_source_map(code: Vec<u8>, source_map: SourceMap) -> Bytecode {
     Bytecode {
       code,
       cache: RefCell::new(HashMap::new()),
+      source_map,
     }
   }
 }
@@ -5,8 +5,10 @@ use crate::cat_stack_frame::CatStackFrame;
 use crate::internal_error_builtin::ToInternalError;
 use crate::jsx_element::JsxElement;
 use crate::native_function::ThisWrapper;
+use crate::numeric_model::{model_binary_op, model_unary_op, NumericModel};
 use crate::operations::{op_delete, op_not};
 use crate::type_error_builtin::ToTypeError;
+use crate::unary_op::UnaryOp;
 use crate::vs_object::VsObject;
 use crate::vs_value::{ToDynamicVal, ToVal, VsType};
 use crate::{
@@ -14,8 +16,10 @@ use crate::{
 };
 use crate::{vs_value::Val, StackFrameTrait};
 
+use crate::binary_op::BinaryOp;
 use crate::bytecode_decoder::{BytecodeDecoder, BytecodeType};
 use crate::circuit_signal::CircuitSignal;
+use crate::source_map::StackTraceFrame;
 use crate::val_dynamic_downcast::val_dynamic_downcast;
 
 #[derive(Clone)]
@@ -45,6 +49,19 @@ pub struct CatchSetting {
 }
 
 impl BytecodeStackFrame {
+  /**
+   * The function and source location this frame is executing, if the bytecode has a source map.
+   */
+  pub fn stack_trace_frame(&self) -> Option<StackTraceFrame> {
+    // The decoder has moved past (at least some of) the current instruction, so we look up the
+    // previous byte to stay inside it
+    self
+      .decoder
+      .bytecode
+      .source_map
+      .stack_trace_frame(self.decoder.pos.saturating_sub(1))
+  }
+
   pub fn apply_unary_op(&mut self, op: fn(input: &Val) -> Result<Val, Val>) -> Result<(), Val> {
     let input = self.decoder.decode_val(&mut self.registers);
 
@@ -69,6 +86,49 @@ impl BytecodeStackFrame {
     Ok(())
   }
 
+  /**
+   * Like `apply_binary_op`, except that numbers known at compile time are computed with
+   * `numeric_model` (see `model_binary_op`).
+   */
+  pub fn apply_model_binary_op(
+    &mut self,
+    numeric_model: &NumericModel,
+    binary_op: BinaryOp,
+    op: fn(left: &Val, right: &Val) -> Result<Val, Val>,
+  ) -> Result<(), Val> {
+    let left = self.decoder.decode_val(&mut self.registers);
+    let right = self.decoder.decode_val(&mut self.registers);
+
+    if let Some(register_index) = self.decoder.decode_register_index() {
+      self.registers[register_index] =
+        match model_binary_op(numeric_model, binary_op, &left, &right) {
+          Some(res) => res?,
+          None => op(&left, &right)?,
+        };
+    }
+
+    Ok(())
+  }
+
+  /** Like `apply_model_binary_op`, for `-x` and `~x`. */
+  pub fn apply_model_unary_op(
+    &mut self,
+    numeric_model: &NumericModel,
+    unary_op: UnaryOp,
+    op: fn(input: &Val) -> Result<Val, Val>,
+  ) -> Result<(), Val> {
+    let input = self.decoder.decode_val(&mut self.registers);
+
+    if let Some(register_index) = self.decoder.decode_register_index() {
+      self.registers[register_index] = match model_unary_op(numeric_model, unary_op, &input) {
+        Some(res) => res?,
+        None => op(&input)?,
+      };
+    }
+
+    Ok(())
+  }
+
   pub fn transfer_parameters(&mut self, new_frame: &mut StackFrame) {
     let bytecode_type = self.decoder.peek_type();
 
@@ -200,7 +260,7 @@ impl StackFrameTrait for BytecodeStackFrame {
     }
   }
 
-  fn step(&mut self) -> FrameStepResult {
+  fn step(&mut self, numeric_model: &NumericModel) -> FrameStepResult {
     use InstructionByte::*;
 
     let instruction_byte = self.decoder.decode_instruction();
@@ -226,9 +286,14 @@ impl StackFrameTrait for BytecodeStackFrame {
         let val = &mut self.registers[register_index];
 
         match val {
-          Val::Number(n) => *n += 1.0,
+          Val::Number(n) if *numeric_model == NumericModel::Float64 => *n += 1.0,
           Val::BigInt(bi) => *bi += 1,
-          _ => *val = operations::op_plus(val, &1.0.to_val())?,
+          _ => {
+            *val = match model_binary_op(numeric_model, BinaryOp::Plus, val, &1.0.to_val()) {
+              Some(res) => res?,
+              None => operations::op_plus(val, &1.0.to_val())?,
+            }
+          }
         };
       }
 
@@ -237,18 +302,25 @@ impl StackFrameTrait for BytecodeStackFrame {
         let val = &mut self.registers[register_index];
 
         match val {
-          Val::Number(n) => *n -= 1.0,
+          Val::Number(n) if *numeric_model == NumericModel::Float64 => *n -= 1.0,
           Val::BigInt(bi) => *bi -= 1,
-          _ => *val = operations::op_minus(val, &1.0.to_val())?,
+          _ => {
+            *val = match model_binary_op(numeric_model, BinaryOp::Minus, val, &1.0.to_val()) {
+              Some(res) => res?,
+              None => operations::op_minus(val, &1.0.to_val())?,
+            }
+          }
         };
       }
 
-      OpPlus => self.apply_binary_op(operations::op_plus)?,
-      OpMinus => self.apply_binary_op(operations::op_minus)?,
-      OpMul => self.apply_binary_op(operations::op_mul)?,
-      OpDiv => self.apply_binary_op(operations::op_div)?,
-      OpMod => self.apply_binary_op(operations::op_mod)?,
-      OpExp => self.apply_binary_op(operations::op_exp)?,
+      OpPlus => self.apply_model_binary_op(numeric_model, BinaryOp::Plus, operations::op_plus)?,
+      OpMinus => {
+        self.apply_model_binary_op(numeric_model, BinaryOp::Minus, operations::op_minus)?
+      }
+      OpMul => self.apply_model_binary_op(numeric_model, BinaryOp::Mul, operations::op_mul)?,
+      OpDiv => self.apply_model_binary_op(numeric_model, BinaryOp::Div, operations::op_div)?,
+      OpMod => self.apply_model_binary_op(numeric_model, BinaryOp::Mod, operations::op_mod)?,
+      OpExp => self.apply_model_binary_op(numeric_model, BinaryOp::Exp, operations::op_exp)?,
       OpEq => self.apply_binary_op(operations::op_eq)?,
       OpNe => self.apply_binary_op(operations::op_ne)?,
       OpTripleEq => self.apply_binary_op(operations::op_triple_eq)?,
@@ -258,10 +330,18 @@ impl StackFrameTrait for BytecodeStackFrame {
 
       OpNot => self.apply_unary_op(operations::op_not)?,
 
-      OpLess => self.apply_binary_op(operations::op_less)?,
-      OpLessEq => self.apply_binary_op(operations::op_less_eq)?,
-      OpGreater => self.apply_binary_op(operations::op_greater)?,
-      OpGreaterEq => self.apply_binary_op(operations::op_greater_eq)?,
+      OpLess => self.apply_model_binary_op(numeric_model, BinaryOp::Less, operations::op_less)?,
+      OpLessEq => {
+        self.apply_model_binary_op(numeric_model, BinaryOp::LessEq, operations::op_less_eq)?
+      }
+      OpGreater => {
+        self.apply_model_binary_op(numeric_model, BinaryOp::Greater, operations::op_greater)?
+      }
+      OpGreaterEq => self.apply_model_binary_op(
+        numeric_model,
+        BinaryOp::GreaterEq,
+        operations::op_greater_eq,
+      )?,
       OpNullishCoalesce => self.apply_binary_op(operations::op_nullish_coalesce)?,
       OpOptionalChain => {
         let mut left = self.decoder.decode_val(&mut self.registers);
@@ -271,15 +351,35 @@ impl StackFrameTrait for BytecodeStackFrame {
           self.registers[register_index] = operations::op_optional_chain(&mut left, &right)?;
         }
       }
-      OpBitAnd => self.apply_binary_op(operations::op_bit_and)?,
-      OpBitOr => self.apply_binary_op(operations::op_bit_or)?,
+      OpBitAnd => {
+        self.apply_model_binary_op(numeric_model, BinaryOp::BitAnd, operations::op_bit_and)?
+      }
+      OpBitOr => {
+        self.apply_model_binary_op(numeric_model, BinaryOp::BitOr, operations::op_bit_or)?
+      }
 
-      OpBitNot => self.apply_unary_op(operations::op_bit_not)?,
+      OpBitNot => {
+        self.apply_model_unary_op(numeric_model, UnaryOp::BitNot, operations::op_bit_not)?
+      }
 
-      OpBitXor => self.apply_binary_op(operations::op_bit_xor)?,
-      OpLeftShift => self.apply_binary_op(operations::op_left_shift)?,
-      OpRightShift => self.apply_binary_op(operations::op_right_shift)?,
-      OpRightShiftUnsigned => self.apply_binary_op(operations::op_right_shift_unsigned)?,
+      OpBitXor => {
+        self.apply_model_binary_op(numeric_model, BinaryOp::BitXor, operations::op_bit_xor)?
+      }
+      OpLeftShift => self.apply_model_binary_op(
+        numeric_model,
+        BinaryOp::LeftShift,
+        operations::op_left_shift,
+      )?,
+      OpRightShift => self.apply_model_binary_op(
+        numeric_model,
+        BinaryOp::RightShift,
+        operations::op_right_shift,
+      )?,
+      OpRightShiftUnsigned => self.apply_model_binary_op(
+        numeric_model,
+        BinaryOp::RightShiftUnsigned,
+        operations::op_right_shift_unsigned,
+      )?,
 
       TypeOf => self.apply_unary_op(operations::op_typeof)?,
 
@@ -494,7 +594,7 @@ impl StackFrameTrait for BytecodeStackFrame {
           break 'b;
         }
 
-        if cond.is_truthy() {
+        if cond.is_truthy()? {
           self.decoder.pos = dst;
         }
       }
@@ -524,13 +624,15 @@ impl StackFrameTrait for BytecodeStackFrame {
           break 'b;
         }
 
-        if !cond.is_truthy() {
+        if !cond.is_truthy()? {
           self.decoder.pos = dst;
         }
       }
 
       UnaryPlus => self.apply_unary_op(operations::op_unary_plus)?,
-      UnaryMinus => self.apply_unary_op(operations::op_unary_minus)?,
+      UnaryMinus => {
+        self.apply_model_unary_op(numeric_model, UnaryOp::Minus, operations::op_unary_minus)?
+      }
 
       New => {
         // TODO: new Array
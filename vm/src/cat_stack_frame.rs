@@ -3,6 +3,7 @@ use std::{any::Any, mem::take, rc::Rc};
 use crate::{
   builtins::{internal_error_builtin::ToInternalError, type_error_builtin::ToTypeError},
   native_function::ThisWrapper,
+  numeric_model::NumericModel,
   operations::op_sub,
   stack_frame::{CallResult, FrameStepOk, FrameStepResult, StackFrame, StackFrameTrait},
   vs_symbol::VsSymbol,
@@ -84,7 +85,7 @@ impl CatStackFrame {
     res: &mut Vec<Val>,
     iter_result: Val,
   ) -> Result<(), Val> {
-    let done = iter_result.sub(&"done".to_val())?.is_truthy();
+    let done = iter_result.sub(&"done".to_val())?.is_truthy()?;
 
     if done {
       *state = CatFrameState::ReadNext;
@@ -105,7 +106,7 @@ impl StackFrameTrait for CatStackFrame {
     self.args.push(param);
   }
 
-  fn step(&mut self) -> FrameStepResult {
+  fn step(&mut self, _numeric_model: &NumericModel) -> FrameStepResult {
     if let Some(iter_result) = take(&mut self.iter_result) {
       Self::apply_iter_result(&mut self.state, &mut self.res, iter_result)?;
     }
@@ -1,26 +1,53 @@
 use std::{cmp::max, collections::BTreeMap, fmt};
 
-use crate::{binary_op::BinaryOp, unary_op::UnaryOp};
+use crate::{
+  binary_op::BinaryOp, field_element::parse_field_modulus, unary_op::UnaryOp, vs_value::Val,
+};
 use bristol_circuit::{BristolCircuit, CircuitInfo, ConstantInfo, Gate as BristolGate, IOInfo};
+use num_bigint::BigUint;
 use serde_json::json;
-use summon_common::InputDescriptor;
+use summon_common::{InputDescriptor, OutputDescriptor};
 
-use crate::bristol_op_strings::{to_bristol_binary_op, to_bristol_unary_op};
+use crate::bristol_op_strings::{
+  from_bristol_binary_op, from_bristol_unary_op, to_bristol_binary_op, to_bristol_unary_op,
+};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Circuit {
   pub size: usize,
   pub constants: BTreeMap<usize, serde_json::Value>, // wire_id -> value
   pub inputs: BTreeMap<String, CircuitInput>,
-  pub outputs: BTreeMap<String, usize>,
+  pub input_shapes: BTreeMap<String, serde_json::Value>, // composite inputs only
+  pub outputs: Vec<(String, usize)>, // in declared order, so r[2] comes before r[10]
+  pub output_shapes: BTreeMap<String, serde_json::Value>, // composite outputs only
   pub mpc_settings: MpcSettings,
   pub gates: Vec<Gate>,
+
+  /**
+   * When set, the circuit computes over the integers mod this prime rather than wrapping 64-bit
+   * integers, and constants are encoded as field elements. See `FieldElement`.
+   */
+  pub field_modulus: Option<BigUint>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CircuitInput {
   pub wire_id: usize,
   pub type_json: serde_json::Value, // TODO: rename to type_
+  pub bit_width: Option<usize>,
+  pub signed: bool,
+}
+
+impl CircuitInput {
+  /** Reads the bit width and signedness of the input from its type. */
+  pub fn from_type(wire_id: usize, type_json: serde_json::Value) -> Self {
+    CircuitInput {
+      wire_id,
+      bit_width: type_json["bits"].as_u64().map(|bits| bits as usize),
+      signed: type_json["type"] == "int",
+      type_json,
+    }
+  }
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -37,8 +64,8 @@ impl MpcSettings {
   pub fn from_io(
     parties: &[String],
     input_descriptors: &[InputDescriptor],
-    outputs: Vec<String>,
-  ) -> Self {
+    output_descriptors: &[OutputDescriptor],
+  ) -> Result<Self, String> {
     let mut participants = parties
       .iter()
       .map(|name| MpcParticipantSettings {
@@ -50,21 +77,35 @@ impl MpcSettings {
 
     for desc in input_descriptors {
       let Some(participant) = participants.iter_mut().find(|p| p.name == desc.from) else {
-        panic!("Participant {} not found", desc.from);
+        return Err(format!("Participant {} not found", desc.from));
       };
 
       participant.inputs.push(desc.name.clone());
     }
 
-    for participant in &mut participants {
-      participant.outputs = outputs.clone();
+    for desc in output_descriptors {
+      let Some(to) = &desc.to else {
+        for participant in &mut participants {
+          participant.outputs.push(desc.name.clone());
+        }
+
+        continue;
+      };
+
+      for recipient in to {
+        let Some(participant) = participants.iter_mut().find(|p| &p.name == recipient) else {
+          return Err(format!("Participant {} not found", recipient));
+        };
+
+        participant.outputs.push(desc.name.clone());
+      }
     }
 
-    Self(participants)
+    Ok(Self(participants))
   }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Gate {
   Unary {
     op: UnaryOp,
@@ -79,37 +120,119 @@ pub enum Gate {
   },
 }
 
-impl Circuit {
-  pub fn eval<N: CircuitNumber>(&self, inputs: &BTreeMap<String, N>) -> BTreeMap<String, N> {
-    let mut wire_values = vec![N::zero(); self.size];
-
-    for (
-      name,
-      CircuitInput {
-        wire_id,
-        type_json: _,
+impl Gate {
+  pub fn output(&self) -> usize {
+    match self {
+      Gate::Unary { output, .. } | Gate::Binary { output, .. } => *output,
+    }
+  }
+
+  /**
+   * The name of the gate's operation in bristol format, e.g. `AMul`.
+   */
+  pub fn bristol_op(&self) -> String {
+    match self {
+      Gate::Unary { op, .. } => to_bristol_unary_op(*op),
+      Gate::Binary { op, .. } => to_bristol_binary_op(*op),
+    }
+  }
+
+  pub fn for_each_input(&self, mut f: impl FnMut(usize)) {
+    match self {
+      Gate::Unary { input, .. } => f(*input),
+      Gate::Binary { left, right, .. } => {
+        f(*left);
+        f(*right);
+      }
+    }
+  }
+
+  /**
+   * The same gate, reading from different wires.
+   */
+  pub fn map_inputs(self, mut f: impl FnMut(usize) -> usize) -> Gate {
+    match self {
+      Gate::Unary { op, input, output } => Gate::Unary {
+        op,
+        input: f(input),
+        output,
+      },
+      Gate::Binary {
+        op,
+        left,
+        right,
+        output,
+      } => Gate::Binary {
+        op,
+        left: f(left),
+        right: f(right),
+        output,
+      },
+    }
+  }
+
+  /**
+   * Like `map_inputs`, also mapping the output.
+   */
+  pub fn map_wires(self, mut f: impl FnMut(usize) -> usize) -> Gate {
+    let output = f(self.output());
+
+    match self.map_inputs(f) {
+      Gate::Unary { op, input, .. } => Gate::Unary { op, input, output },
+      Gate::Binary {
+        op, left, right, ..
+      } => Gate::Binary {
+        op,
+        left,
+        right,
+        output,
       },
-    ) in &self.inputs
-    {
+    }
+  }
+}
+
+impl Circuit {
+  pub fn eval<N: CircuitNumber<Params = ()>>(
+    &self,
+    inputs: &BTreeMap<String, N>,
+  ) -> BTreeMap<String, N> {
+    self.eval_with(inputs, &())
+  }
+
+  /**
+   * Like `eval`, for number types that need parameters, e.g. `FieldElement` with the circuit's
+   * `field_modulus`.
+   */
+  pub fn eval_with<N: CircuitNumber>(
+    &self,
+    inputs: &BTreeMap<String, N>,
+    params: &N::Params,
+  ) -> BTreeMap<String, N> {
+    let mut wire_values = vec![N::zero(params); self.size];
+
+    for (name, CircuitInput { wire_id, .. }) in &self.inputs {
       let value = inputs.get(name).expect("Missing input");
       wire_values[*wire_id] = value.clone();
     }
 
     for (wire_id, value) in &self.constants {
-      wire_values[*wire_id] = N::from_json(value);
+      wire_values[*wire_id] = N::from_json(value, params);
     }
 
     for gate in &self.gates {
       match gate {
         Gate::Unary { op, input, output } => {
-          wire_values[*output] = N::unary_op(*op, &wire_values[*input])
+          wire_values[*output] = N::unary_op(*op, &wire_values[*input], params)
         }
         Gate::Binary {
           op,
           left,
           right,
           output,
-        } => wire_values[*output] = N::binary_op(*op, &wire_values[*left], &wire_values[*right]),
+        } => {
+          wire_values[*output] =
+            N::binary_op(*op, &wire_values[*left], &wire_values[*right], params)
+        }
       }
     }
 
@@ -122,6 +245,17 @@ impl Circuit {
     res
   }
 
+  /**
+   * The largest bit width declared by an input (via `summon.uint(bits)` etc), if any.
+   */
+  pub fn max_input_bit_width(&self) -> Option<usize> {
+    self
+      .inputs
+      .values()
+      .filter_map(|input| input.bit_width)
+      .max()
+  }
+
   pub fn depth(&self) -> usize {
     let mut wire_depths = vec![0usize; self.size];
 
@@ -146,6 +280,173 @@ impl Circuit {
     max_depth
   }
 
+  /**
+   * The multiplicative depth of each wire, i.e. the largest number of non-linear gates on a path
+   * from the inputs to that wire.
+   *
+   * Addition, subtraction, negation and multiplication by a constant (including `<<` by a constant)
+   * are linear. Every other gate (multiplying two signals, comparisons, bitwise operations, etc)
+   * adds a level.
+   */
+  pub fn mult_depths(&self) -> Vec<usize> {
+    let mut wire_depths = vec![0usize; self.size];
+
+    for gate in &self.gates {
+      let level = if self.is_linear(gate) { 0 } else { 1 };
+      let mut depth = 0;
+
+      gate.for_each_input(|input| depth = max(depth, wire_depths[input]));
+      wire_depths[gate.output()] = level + depth;
+    }
+
+    wire_depths
+  }
+
+  /**
+   * Whether a gate is linear, which makes it free in arithmetic MPC (see `mult_depths`).
+   */
+  pub fn is_linear(&self, gate: &Gate) -> bool {
+    match gate {
+      Gate::Unary { op, .. } => matches!(op, UnaryOp::Plus | UnaryOp::Minus),
+      Gate::Binary {
+        op, left, right, ..
+      } => match op {
+        BinaryOp::Plus | BinaryOp::Minus => true,
+        BinaryOp::Mul => self.constants.contains_key(left) || self.constants.contains_key(right),
+        BinaryOp::LeftShift => self.constants.contains_key(right),
+        _ => false,
+      },
+    }
+  }
+
+  /**
+   * Serializes circuit info (usually `self.to_bristol().info`, possibly after further processing
+   * like boolification), adding `inputShapes` and `outputShapes` when there are array/object
+   * inputs or outputs, so that front-ends can map values to and from the flattened ones.
+   */
+  pub fn info_json(&self, info: &CircuitInfo) -> serde_json::Value {
+    let mut res = serde_json::to_value(info).unwrap();
+
+    if !self.input_shapes.is_empty() {
+      res["inputShapes"] = json!(self.input_shapes);
+    }
+
+    if !self.output_shapes.is_empty() {
+      res["outputShapes"] = json!(self.output_shapes);
+    }
+
+    if let Some(field_modulus) = &self.field_modulus {
+      res["fieldModulus"] = json!(field_modulus.to_string());
+    }
+
+    res
+  }
+
+  /**
+   * Reads a circuit written by `summonc` (`circuit.txt` and `circuit_info.json`), so that it can be
+   * evaluated.
+   *
+   * Only arithmetic circuits are supported, i.e. not the output of `--boolify-width`. MPC settings
+   * aren't part of these files, so they are left empty.
+   */
+  pub fn from_bristol(bristol: &str, info: &serde_json::Value) -> Result<Circuit, String> {
+    let mut lines = bristol
+      .lines()
+      .map(str::trim)
+      .filter(|line| !line.is_empty());
+
+    let size = lines
+      .next()
+      .and_then(|line| line.split_whitespace().nth(1))
+      .and_then(|wire_count| wire_count.parse::<usize>().ok())
+      .ok_or("Missing wire count in header")?;
+
+    let mut gates = Vec::<Gate>::new();
+
+    for line in lines {
+      let parts = line.split_whitespace().collect::<Vec<_>>();
+
+      let Some(op) = parts.last() else {
+        continue;
+      };
+
+      if op.parse::<usize>().is_ok() {
+        // Input/output header lines only contain numbers
+        continue;
+      }
+
+      let wires = parts[..parts.len() - 1]
+        .iter()
+        .map(|part| part.parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("Invalid gate: {}", line))?;
+
+      if wires.len() > 2 && wires[2..].iter().any(|wire| *wire >= size) {
+        return Err(format!("Wire out of range: {}", line));
+      }
+
+      gates.push(match wires.as_slice() {
+        [1, 1, input, output] => Gate::Unary {
+          op: from_bristol_unary_op(op).ok_or(format!("Unsupported unary op: {}", op))?,
+          input: *input,
+          output: *output,
+        },
+        [2, 1, left, right, output] => Gate::Binary {
+          op: from_bristol_binary_op(op).ok_or(format!("Unsupported binary op: {}", op))?,
+          left: *left,
+          right: *right,
+          output: *output,
+        },
+        _ => return Err(format!("Invalid gate: {}", line)),
+      });
+    }
+
+    let mut constants = BTreeMap::<usize, serde_json::Value>::new();
+
+    for constant in json_array(info, "constants")? {
+      constants.insert(json_address(constant)?, constant["value"].clone());
+    }
+
+    let mut inputs = BTreeMap::<String, CircuitInput>::new();
+
+    for input in json_array(info, "inputs")? {
+      inputs.insert(
+        json_name(input)?,
+        CircuitInput::from_type(json_address(input)?, input["type"].clone()),
+      );
+    }
+
+    let mut outputs = Vec::<(String, usize)>::new();
+
+    for output in json_array(info, "outputs")? {
+      outputs.push((json_name(output)?, json_address(output)?));
+    }
+
+    let input_shapes = json_shapes(info, "inputShapes");
+    let output_shapes = json_shapes(info, "outputShapes");
+
+    let field_modulus = match info.get("fieldModulus") {
+      Some(modulus) => Some(parse_field_modulus(modulus.as_str().unwrap_or_default())?),
+      None => None,
+    };
+
+    Ok(Circuit {
+      size,
+      constants,
+      inputs,
+      input_shapes,
+      outputs,
+      output_shapes,
+      mpc_settings: MpcSettings(vec![]),
+      gates,
+      field_modulus,
+    })
+  }
+
+  /**
+   * Writes the arithmetic circuit, where every value is one wire (width 1). The declared width and
+   * signedness of an input are only recorded in its `type` (e.g. `{"type":"uint","bits":8}`).
+   */
   pub fn to_bristol(&self) -> BristolCircuit {
     let mut bristol_gates = Vec::<BristolGate>::new();
 
@@ -173,10 +474,13 @@ impl Circuit {
       .constants
       .iter()
       .map(|(id, value)| ConstantInfo {
-        name: format!("constant_{}", value),
+        name: match value.as_str() {
+          Some(big_value) => format!("constant_{}", big_value),
+          None => format!("constant_{}", value),
+        },
         type_: if value.is_boolean() {
           json!("bool")
-        } else if value.is_number() {
+        } else if value.is_number() || value.is_string() {
           json!("number")
         } else {
           panic!("Unsupported constant type")
@@ -190,17 +494,24 @@ impl Circuit {
     let mut inputs: Vec<IOInfo> = self
       .inputs
       .iter()
-      .map(|(name, CircuitInput { wire_id, type_json })| IOInfo {
-        name: name.clone(),
-        type_: type_json.clone(),
-        address: *wire_id,
-        width: 1,
-      })
+      .map(
+        |(
+          name,
+          CircuitInput {
+            wire_id, type_json, ..
+          },
+        )| IOInfo {
+          name: name.clone(),
+          type_: type_json.clone(),
+          address: *wire_id,
+          width: 1,
+        },
+      )
       .collect();
 
     inputs.sort_by_key(|io| io.address);
 
-    let mut outputs: Vec<IOInfo> = self
+    let outputs: Vec<IOInfo> = self
       .outputs
       .iter()
       .map(|(name, id)| IOInfo {
@@ -211,8 +522,6 @@ impl Circuit {
       })
       .collect();
 
-    outputs.sort_by_key(|io| io.address);
-
     BristolCircuit {
       wire_count: self.size,
       info: CircuitInfo {
@@ -225,11 +534,54 @@ impl Circuit {
   }
 }
 
+fn json_array<'a>(
+  info: &'a serde_json::Value,
+  key: &str,
+) -> Result<&'a Vec<serde_json::Value>, String> {
+  info[key]
+    .as_array()
+    .ok_or(format!("Missing {} in circuit info", key))
+}
+
+fn json_name(io: &serde_json::Value) -> Result<String, String> {
+  io["name"]
+    .as_str()
+    .map(str::to_string)
+    .ok_or(format!("Missing name: {}", io))
+}
+
+fn json_shapes(info: &serde_json::Value, key: &str) -> BTreeMap<String, serde_json::Value> {
+  match info.get(key) {
+    Some(serde_json::Value::Object(shapes)) => shapes
+      .iter()
+      .map(|(name, shape)| (name.clone(), shape.clone()))
+      .collect(),
+    _ => BTreeMap::new(),
+  }
+}
+
+fn json_address(io: &serde_json::Value) -> Result<usize, String> {
+  if io["width"] != 1 {
+    return Err(format!(
+      "Only arithmetic circuits (where every value has width 1) are supported: {}",
+      io
+    ));
+  }
+
+  io["address"]
+    .as_u64()
+    .map(|address| address as usize)
+    .ok_or(format!("Missing address: {}", io))
+}
+
 pub trait CircuitNumber: Clone {
-  fn zero() -> Self;
-  fn from_json(x: &serde_json::Value) -> Self;
-  fn unary_op(op: UnaryOp, input: &Self) -> Self;
-  fn binary_op(op: BinaryOp, left: &Self, right: &Self) -> Self;
+  /** Settings shared by every value, like the modulus for `FieldElement`. */
+  type Params;
+
+  fn zero(params: &Self::Params) -> Self;
+  fn from_json(x: &serde_json::Value, params: &Self::Params) -> Self;
+  fn unary_op(op: UnaryOp, input: &Self, params: &Self::Params) -> Self;
+  fn binary_op(op: BinaryOp, left: &Self, right: &Self, params: &Self::Params) -> Self;
 }
 
 #[derive(Clone, Debug)]
@@ -239,6 +591,47 @@ pub enum NumberOrBool {
 }
 
 impl NumberOrBool {
+  /**
+   * Reads an input value from JSON. Unlike `from_json` (for constants), this accepts negative
+   * numbers, which wrap like they do in the circuit.
+   */
+  pub fn from_input_json(x: &serde_json::Value) -> Option<Self> {
+    if let Some(x) = x.as_bool() {
+      return Some(NumberOrBool::Bool(x));
+    }
+
+    if let Some(x) = x.as_u64() {
+      return Some(NumberOrBool::Number(x as usize));
+    }
+
+    x.as_i64().map(|x| NumberOrBool::Number(x as usize))
+  }
+
+  /**
+   * Converts a concrete value (e.g. from `run_concrete` in the compiler) to the circuit's
+   * representation. Negative integers wrap, and non-integers aren't representable.
+   */
+  pub fn from_val(val: &Val) -> Option<Self> {
+    match val {
+      Val::Bool(x) => Some(NumberOrBool::Bool(*x)),
+      Val::Number(x) if x.fract() == 0.0 && x.abs() <= u64::MAX as f64 => {
+        Some(NumberOrBool::Number(if *x < 0.0 {
+          (*x as i64) as usize
+        } else {
+          *x as u64 as usize
+        }))
+      }
+      _ => None,
+    }
+  }
+
+  pub fn to_json(&self) -> serde_json::Value {
+    match self {
+      NumberOrBool::Number(x) => json!(x),
+      NumberOrBool::Bool(x) => json!(x),
+    }
+  }
+
   fn as_usize(&self) -> usize {
     match self {
       NumberOrBool::Number(x) => *x,
@@ -274,11 +667,13 @@ impl fmt::Display for NumberOrBool {
 }
 
 impl CircuitNumber for NumberOrBool {
-  fn zero() -> Self {
+  type Params = ();
+
+  fn zero(_: &()) -> Self {
     NumberOrBool::Number(0)
   }
 
-  fn from_json(x: &serde_json::Value) -> Self {
+  fn from_json(x: &serde_json::Value, _: &()) -> Self {
     if let Some(x) = x.as_u64() {
       return NumberOrBool::Number(x as usize);
     }
@@ -290,7 +685,7 @@ impl CircuitNumber for NumberOrBool {
     panic!("Couldn't convert to NumberOrBool: {}", x);
   }
 
-  fn unary_op(op: UnaryOp, input: &Self) -> Self {
+  fn unary_op(op: UnaryOp, input: &Self, _: &()) -> Self {
     match op {
       UnaryOp::Plus => input.clone(),
       UnaryOp::Minus => NumberOrBool::Number(0usize.wrapping_sub(input.as_usize())),
@@ -302,7 +697,7 @@ impl CircuitNumber for NumberOrBool {
     }
   }
 
-  fn binary_op(op: BinaryOp, left: &Self, right: &Self) -> Self {
+  fn binary_op(op: BinaryOp, left: &Self, right: &Self, _: &()) -> Self {
     match op {
       BinaryOp::Plus => NumberOrBool::Number(left.as_usize().wrapping_add(right.as_usize())),
       BinaryOp::Minus => NumberOrBool::Number(left.as_usize().wrapping_sub(right.as_usize())),
@@ -335,3 +730,43 @@ impl CircuitNumber for NumberOrBool {
     }
   }
 }
+
+/**
+ * A circuit for unit tests, with inputs `a`, `b`, etc of the given types (on wires
+ * `0..input_types.len()`), then `constants`, then `gates` (which give their own output wires).
+ * `outputs` are named `o0`, `o1`, etc.
+ */
+#[cfg(test)]
+pub(crate) fn test_circuit(
+  input_types: &[serde_json::Value],
+  constants: &[serde_json::Value],
+  gates: &[Gate],
+  outputs: &[usize],
+) -> Circuit {
+  let first_constant_wire = input_types.len();
+
+  Circuit {
+    size: first_constant_wire + constants.len() + gates.len(),
+    constants: (first_constant_wire..)
+      .zip(constants.iter().cloned())
+      .collect(),
+    inputs: input_types
+      .iter()
+      .enumerate()
+      .map(|(wire_id, type_json)| {
+        let name = ((b'a' + wire_id as u8) as char).to_string();
+        (name, CircuitInput::from_type(wire_id, type_json.clone()))
+      })
+      .collect(),
+    input_shapes: BTreeMap::new(),
+    outputs: outputs
+      .iter()
+      .enumerate()
+      .map(|(i, wire)| (format!("o{}", i), *wire))
+      .collect(),
+    output_shapes: BTreeMap::new(),
+    mpc_settings: MpcSettings(vec![]),
+    gates: gates.to_vec(),
+    field_modulus: None,
+  }
+}
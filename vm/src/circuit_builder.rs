@@ -1,53 +1,70 @@
 use std::{
-  collections::{BTreeMap, HashMap, HashSet},
-  mem::swap,
+  collections::{BinaryHeap, HashMap},
+  rc::Rc,
 };
 
-use crate::{vs_value::Val, ValTrait};
-use num_traits::ToPrimitive;
+use crate::{
+  internal_error_builtin::ToInternalError, type_error_builtin::ToTypeError, vs_value::Val, ValTrait,
+};
+use num_bigint::{BigInt, BigUint};
+use num_traits::{FromPrimitive, ToPrimitive};
 use serde_json::json;
 
 use crate::{
   circuit::Gate,
-  circuit_signal::{CircuitSignal, CircuitSignalData},
+  circuit_signal::{CircuitSignal, CircuitSignalData, SignalOperand},
+  field_element::FieldElement,
+  signal_arena::{SignalArena, SignalNode},
+  source_map::StackTraceFrame,
 };
 
 #[derive(Default)]
 pub struct CircuitBuilder {
   pub gates: Vec<Gate>,
   pub wire_count: usize,
-  pub wires_included: HashMap<usize, usize>, // CircuitSignal.id -> wire_id
-  pub signal_data: Vec<Option<Box<CircuitSignalData>>>, // wire_id -> CircuitSignalData
+  pub wires_included: Vec<Option<usize>>, // CircuitSignal.id -> wire_id
   pub constants: HashMap<serde_json::Value, usize>, // value -> wire_id
+  pub gate_origins: Vec<Option<Rc<StackTraceFrame>>>, // gate index -> SignalNode.origin
+
+  /** Encode constants as elements of this field (see `Circuit::field_modulus`). */
+  pub field_modulus: Option<BigUint>,
 }
 
 impl CircuitBuilder {
   pub fn include_inputs(&mut self, input_ids: &[usize]) {
     for (i, input_id) in input_ids.iter().enumerate() {
-      let prev = self.wires_included.insert(*input_id, i);
+      let prev = self.set_wire(*input_id, i);
       assert!(prev.is_none());
     }
 
     self.wire_count = input_ids.len();
   }
 
-  pub fn include_outputs(&mut self, output_vals: &Vec<(String, Val)>) -> BTreeMap<String, usize> {
+  pub fn include_outputs(
+    &mut self,
+    output_vals: &Vec<(String, Val)>,
+  ) -> Result<Vec<(String, usize)>, Val> {
+    // Include the outputs' operands first, so the output gates come last
     for (_, output) in output_vals {
-      for dep in get_dependencies(output) {
-        self.include_val(&dep);
+      if let Some(signal) = as_circuit_signal(output) {
+        let arena = signal.arena.borrow();
+
+        for id in signal_operand_ids(arena.node(signal.id).data) {
+          self.include_signal_id(&arena, id)?;
+        }
       }
     }
 
-    let mut outputs = BTreeMap::<String, usize>::new();
+    let mut outputs = Vec::<(String, usize)>::new();
 
     for (name, output) in output_vals {
-      outputs.insert(name.clone(), self.include_val(output));
+      outputs.push((name.clone(), self.include_val(output)?));
     }
 
-    outputs
+    Ok(outputs)
   }
 
-  pub fn include_val(&mut self, val: &Val) -> usize {
+  pub fn include_val(&mut self, val: &Val) -> Result<usize, Val> {
     if let Some(signal) = as_circuit_signal(val) {
       return self.include_signal(signal);
     }
@@ -55,202 +72,165 @@ impl CircuitBuilder {
     self.include_val_shallow(val)
   }
 
-  pub fn include_val_shallow(&mut self, val: &Val) -> usize {
-    match val {
-      Val::Bool(bool) => {
-        let value = if *bool { json!(true) } else { json!(false) };
-
-        if let Some(wire_id) = self.constants.get(&value) {
-          return *wire_id;
-        }
-
-        let wire_id = self.allocate_wire(None);
-        self.constants.insert(value, wire_id);
-
-        wire_id
-      }
+  pub fn include_val_shallow(&mut self, val: &Val) -> Result<usize, Val> {
+    let value = match val {
+      Val::Bool(bool) => json!(*bool),
       Val::Number(number) => {
         if *number != number.trunc() {
-          panic!("Cannot use non-integer constant");
+          return Err(format!("Cannot use non-integer constant ({})", number).to_type_error());
         }
 
-        let value = if *number < 0.0 {
-          usize::MAX - ((-number).to_usize().unwrap() - 1)
+        if let Some(field_modulus) = &self.field_modulus {
+          let Some(value) = BigInt::from_f64(*number) else {
+            return Err(format!("Constant out of range ({})", number).to_type_error());
+          };
+
+          FieldElement::from_bigint(&value, field_modulus).to_json()
         } else {
-          number.to_usize().unwrap()
-        };
+          let value = if *number < 0.0 {
+            (-number).to_usize().map(|x| usize::MAX - (x - 1))
+          } else {
+            number.to_usize()
+          };
 
-        let value = serde_json::Value::from(value);
+          let Some(value) = value else {
+            return Err(format!("Constant out of range ({})", number).to_type_error());
+          };
 
-        if let Some(wire_id) = self.constants.get(&value) {
-          return *wire_id;
+          serde_json::Value::from(value)
         }
-
-        let wire_id = self.allocate_wire(None);
-        self.constants.insert(value, wire_id);
-
-        wire_id
       }
       Val::Dynamic(dyn_val) => {
         if let Some(signal) = dyn_val.as_any().downcast_ref::<CircuitSignal>() {
-          if let Some(wire_id) = self.wires_included.get(&signal.id) {
-            return *wire_id;
+          if let Some(wire_id) = self.wire(signal.id) {
+            return Ok(wire_id);
           }
+
+          return Err("Signal should have been included earlier".to_internal_error());
         }
 
-        panic!("Can't include unrecognized type ({}) 1", val.codify());
+        return Err(format!("Can't include unrecognized type ({})", val.codify()).to_type_error());
+      }
+      _ => {
+        return Err(format!("Can't include unrecognized type ({})", val.codify()).to_type_error())
       }
-      _ => panic!("Can't include unrecognized type ({}) 2", val.codify()),
-    }
-  }
-
-  pub fn include_signal_shallow(
-    &mut self,
-    signal: &CircuitSignal,
-    dependent_ids: Vec<usize>,
-  ) -> usize {
-    let wire_id = self.allocate_wire(Some(signal));
-
-    let gate = match &signal.data {
-      CircuitSignalData::Input { .. } => panic!("Input should have been included earlier"),
-      CircuitSignalData::UnaryOp(op, _) => Gate::Unary {
-        op: *op,
-        input: dependent_ids[0],
-        output: wire_id,
-      },
-      CircuitSignalData::BinaryOp(op, _, _) => Gate::Binary {
-        op: *op,
-        left: dependent_ids[0],
-        right: dependent_ids[1],
-        output: wire_id,
-      },
     };
 
-    self.gates.push(gate);
+    if let Some(wire_id) = self.constants.get(&value) {
+      return Ok(*wire_id);
+    }
 
-    self.wires_included.insert(signal.id, wire_id);
+    let wire_id = self.allocate_wire();
+    self.constants.insert(value, wire_id);
 
-    wire_id
+    Ok(wire_id)
   }
 
-  pub fn include_signal(&mut self, signal: &CircuitSignal) -> usize {
-    if let Some(wire_id) = self.wires_included.get(&signal.id) {
-      return *wire_id;
+  pub fn include_signal(&mut self, signal: &CircuitSignal) -> Result<usize, Val> {
+    self.include_signal_id(&signal.arena.borrow(), signal.id)
+  }
+
+  fn include_signal_id(&mut self, arena: &SignalArena, id: usize) -> Result<usize, Val> {
+    if let Some(wire_id) = self.wire(id) {
+      return Ok(wire_id);
     }
 
-    let mut signals_to_process = vec![signal.clone()];
-    let mut signals_ids_processed = HashSet::<usize>::new();
-    let mut signal_id_to_parent_signals = HashMap::<usize, Vec<CircuitSignal>>::new();
-    let mut signal_id_to_dep_ids = HashMap::<usize, HashSet<usize>>::new();
-    let mut id_to_leaf_signal = BTreeMap::<usize, CircuitSignal>::new();
+    // Operands have lower ids than the signals that use them, so taking the largest id first
+    // reaches each signal after everything that uses it, with any repeats of it taken in a row
+    let mut to_visit = BinaryHeap::from([id]);
+    let mut to_include = Vec::<usize>::new();
 
-    while let Some(signal) = signals_to_process.pop() {
-      if signals_ids_processed.contains(&signal.id) {
+    while let Some(id) = to_visit.pop() {
+      if to_include.last() == Some(&id) || self.wire(id).is_some() {
         continue;
       }
 
-      signals_ids_processed.insert(signal.id);
-
-      let mut dep_ids = HashSet::<usize>::new();
-
-      for dep in get_signal_dependencies(&signal) {
-        let Some(dep_signal) = as_circuit_signal(&dep) else {
-          continue;
-        };
-
-        if self.wires_included.contains_key(&dep_signal.id) {
-          continue;
-        }
+      to_include.push(id);
+      to_visit.extend(signal_operand_ids(arena.node(id).data));
+    }
 
-        dep_ids.insert(dep_signal.id);
+    for id in to_include.into_iter().rev() {
+      self.include_node(id, arena.node(id))?;
+    }
 
-        signal_id_to_parent_signals
-          .entry(dep_signal.id)
-          .or_default()
-          .push(signal.clone());
+    let Some(wire_id) = self.wire(id) else {
+      return Err("Failed to include signal".to_internal_error());
+    };
 
-        signals_to_process.push(dep_signal.clone());
-      }
+    Ok(wire_id)
+  }
 
-      if dep_ids.is_empty() {
-        id_to_leaf_signal.insert(signal.id, signal.clone());
-      } else {
-        signal_id_to_dep_ids.insert(signal.id, dep_ids);
+  fn include_node(&mut self, id: usize, node: &SignalNode) -> Result<usize, Val> {
+    let gate = match node.data {
+      CircuitSignalData::Input => {
+        return Err("Input should have been included earlier".to_internal_error())
       }
-    }
-
-    let mut next_id_to_leaf_signal = BTreeMap::<usize, CircuitSignal>::new();
-
-    loop {
-      for (_, leaf_signal) in id_to_leaf_signal.iter() {
-        let dependent_ids = get_signal_dependencies(leaf_signal)
-          .iter()
-          .map(|dep| self.include_val_shallow(dep))
-          .collect::<Vec<usize>>();
+      CircuitSignalData::UnaryOp(op, input) => Gate::Unary {
+        op,
+        input: self.include_operand(input)?,
+        output: self.allocate_wire(),
+      },
+      CircuitSignalData::BinaryOp(op, left, right) => Gate::Binary {
+        op,
+        left: self.include_operand(left)?,
+        right: self.include_operand(right)?,
+        output: self.allocate_wire(),
+      },
+    };
 
-        self.include_signal_shallow(leaf_signal, dependent_ids);
+    let wire_id = gate.output();
+    self.gates.push(gate);
+    self.gate_origins.push(node.origin.clone());
+    self.set_wire(id, wire_id);
 
-        for parent_signal in signal_id_to_parent_signals
-          .get(&leaf_signal.id)
-          .unwrap_or(&vec![])
-        {
-          let dep_ids = signal_id_to_dep_ids.get_mut(&parent_signal.id).unwrap();
-          dep_ids.remove(&leaf_signal.id);
+    Ok(wire_id)
+  }
 
-          if dep_ids.is_empty() {
-            next_id_to_leaf_signal.insert(parent_signal.id, parent_signal.clone());
-          }
-        }
-      }
+  fn include_operand(&mut self, operand: SignalOperand) -> Result<usize, Val> {
+    match operand {
+      SignalOperand::Signal(id, _) => match self.wire(id) {
+        Some(wire_id) => Ok(wire_id),
+        None => Err("Signal should have been included earlier".to_internal_error()),
+      },
+      SignalOperand::Number(bits) => self.include_val_shallow(&Val::Number(f64::from_bits(bits))),
+      SignalOperand::Bool(x) => self.include_val_shallow(&Val::Bool(x)),
+    }
+  }
 
-      if next_id_to_leaf_signal.is_empty() {
-        break;
-      }
+  fn wire(&self, signal_id: usize) -> Option<usize> {
+    self.wires_included.get(signal_id).copied().flatten()
+  }
 
-      id_to_leaf_signal.clear();
-      swap(&mut id_to_leaf_signal, &mut next_id_to_leaf_signal);
+  fn set_wire(&mut self, signal_id: usize, wire_id: usize) -> Option<usize> {
+    if self.wires_included.len() <= signal_id {
+      self.wires_included.resize(signal_id + 1, None);
     }
 
-    let Some(wire_id) = self.wires_included.get(&signal.id) else {
-      panic!("Failed to include signal");
-    };
-
-    *wire_id
+    self.wires_included[signal_id].replace(wire_id)
   }
 
-  fn allocate_wire(&mut self, signal: Option<&CircuitSignal>) -> usize {
+  fn allocate_wire(&mut self) -> usize {
     let wire_id = self.wire_count;
     self.wire_count += 1;
 
-    self
-      .signal_data
-      .push(signal.map(|s| Box::new(s.data.clone())));
-
     wire_id
   }
-
-  /**
-   * Drop the signal data
-   *
-   * This is important to avoid blowing the stack. Signals can form a very deep tree, so if they are
-   * dropped from the root then the stack grows to the size of the tree to drop all the data. By
-   * retaining the children before dropping the parents, the parents do not recursively drop their
-   * children, and the stack remains small.
-   */
-  pub fn drop_signal_data(&mut self) {
-    self.signal_data.reverse(); // switch from child,parent order to parent,child order
-    self.signal_data.clear(); // drops parents first
-  }
 }
 
-fn get_dependencies(val: &Val) -> Vec<Val> {
-  if let Val::Dynamic(val) = val {
-    if let Some(circuit_number) = val.as_any().downcast_ref::<CircuitSignal>() {
-      return get_signal_dependencies(circuit_number);
-    }
-  }
-
-  vec![]
+fn signal_operand_ids(data: CircuitSignalData) -> impl Iterator<Item = usize> {
+  let operands = match data {
+    CircuitSignalData::Input => [None, None],
+    CircuitSignalData::UnaryOp(_, input) => [Some(input), None],
+    CircuitSignalData::BinaryOp(_, left, right) => [Some(left), Some(right)],
+  };
+
+  operands
+    .into_iter()
+    .flatten()
+    .filter_map(|operand| match operand {
+      SignalOperand::Signal(id, _) => Some(id),
+      _ => None,
+    })
 }
 
 fn as_circuit_signal(val: &Val) -> Option<&CircuitSignal> {
@@ -262,15 +242,3 @@ fn as_circuit_signal(val: &Val) -> Option<&CircuitSignal> {
 
   None
 }
-
-fn get_signal_dependencies(signal: &CircuitSignal) -> Vec<Val> {
-  match &signal.data {
-    CircuitSignalData::Input { .. } => vec![],
-    CircuitSignalData::UnaryOp(_, input) => {
-      vec![input.clone()]
-    }
-    CircuitSignalData::BinaryOp(_, left, right) => {
-      vec![left.clone(), right.clone()]
-    }
-  }
-}
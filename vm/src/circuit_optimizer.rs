@@ -0,0 +1,997 @@
+use std::{
+  cmp::{max, Reverse},
+  collections::{BinaryHeap, HashMap},
+  fmt,
+  mem::take,
+};
+
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+use serde_json::json;
+
+use crate::{
+  binary_op::BinaryOp,
+  bit_width::{infer_bools, infer_signedness},
+  circuit::{Circuit, CircuitNumber, Gate, NumberOrBool},
+  field_element::FieldElement,
+  unary_op::UnaryOp,
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct OptimizeOptions {
+  /**
+   * Rebuild chains of associative operations (like `sum += x` in a loop) as balanced trees. See
+   * `rebalance_chains`.
+   */
+  pub minimize_depth: bool,
+}
+
+/**
+ * The gate count and depth (see `Circuit::depth`) before and after one pass of
+ * `optimize_circuit`.
+ */
+#[derive(Clone, Debug)]
+pub struct PassReport {
+  pub name: &'static str,
+  pub gates_before: usize,
+  pub gates_after: usize,
+  pub depth_before: usize,
+  pub depth_after: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct OptimizationReport {
+  pub passes: Vec<PassReport>,
+
+  /**
+   * For each gate of the optimized circuit, the index of the gate of the original circuit it came
+   * from (e.g. to keep `CircuitBuilder::gate_origins` aligned).
+   */
+  pub gate_sources: Vec<usize>,
+}
+
+impl fmt::Display for OptimizationReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for pass in &self.passes {
+      writeln!(
+        f,
+        "  {}: {} -> {} gates, depth {} -> {}",
+        pass.name, pass.gates_before, pass.gates_after, pass.depth_before, pass.depth_after
+      )?;
+    }
+
+    Ok(())
+  }
+}
+
+/**
+ * Optimizes the gates of a circuit, keeping its inputs and outputs. The passes are:
+ * - common subexpression elimination: gates with the same operation and operands (in either order
+ *   for commutative operations) are computed once (`SignalArena::add` already reuses signals, so
+ *   this mostly matters for circuits from elsewhere, e.g. `Circuit::from_bristol`)
+ * - algebraic simplification: `x - x` and `x ^ x` are zero, `x * 2^k` becomes `x << k`, `-(-x)` and
+ *   `~~x` are `x`, and adding zero or multiplying by one or zero
+ * - constant folding: gates whose operands are all constants become constants, computed the way
+ *   the circuit would (`NumberOrBool`, or `FieldElement` when there is a `field_modulus`), as long
+ *   as boolean circuits of every width agree (see `agrees_at_every_width`)
+ * - depth rebalancing (with `minimize_depth`): see `rebalance_chains`
+ * - dead wire removal: gates and constants that don't contribute to an output are dropped, and
+ *   the wires are renumbered (inputs, then constants, then gates)
+ *
+ * A rewrite never changes whether a wire is a bool or whether it is signed (see
+ * `infer_signedness`), so evaluating the optimized circuit gives the same outputs, including as a
+ * boolean circuit of any width that holds the inputs.
+ */
+pub fn optimize_circuit(circuit: &mut Circuit, options: &OptimizeOptions) -> OptimizationReport {
+  let mut passes: Vec<(&'static str, Pass)> = vec![
+    (
+      "common subexpression elimination",
+      eliminate_common_subexpressions,
+    ),
+    ("algebraic simplification", simplify),
+    ("constant folding", fold_constants),
+  ];
+
+  if options.minimize_depth {
+    passes.push(("depth rebalancing", rebalance_chains));
+  }
+
+  passes.push(("dead wire removal", remove_dead_wires));
+
+  let mut report = OptimizationReport {
+    passes: vec![],
+    gate_sources: (0..circuit.gates.len()).collect(),
+  };
+
+  for (name, pass) in passes {
+    let gates_before = circuit.gates.len();
+    let depth_before = circuit.depth();
+    let sources = pass(circuit);
+
+    report.gate_sources = sources.iter().map(|i| report.gate_sources[*i]).collect();
+
+    report.passes.push(PassReport {
+      name,
+      gates_before,
+      gates_after: circuit.gates.len(),
+      depth_before,
+      depth_after: circuit.depth(),
+    });
+  }
+
+  report
+}
+
+/**
+ * Rewrites the circuit, returning the index (before the pass) of each remaining gate.
+ */
+type Pass = fn(&mut Circuit) -> Vec<usize>;
+
+fn eliminate_common_subexpressions(circuit: &mut Circuit) -> Vec<usize> {
+  let mut seen = HashMap::<GateKey, usize>::new();
+
+  rewrite_gates(circuit, |_, gate| {
+    let key = GateKey::new(gate);
+
+    match seen.get(&key) {
+      Some(existing) => Some(Rewrite::Wire(*existing)),
+      None => {
+        seen.insert(key, gate.output());
+        None
+      }
+    }
+  })
+}
+
+fn simplify(circuit: &mut Circuit) -> Vec<usize> {
+  rewrite_gates(circuit, |ctx, gate| match *gate {
+    Gate::Unary { op, input, .. } => match (op, ctx.producer(input)) {
+      (
+        UnaryOp::Minus | UnaryOp::BitNot,
+        Some(Gate::Unary {
+          op: inner_op,
+          input: inner_input,
+          ..
+        }),
+      ) if *inner_op == op => Some(Rewrite::Wire(*inner_input)),
+      _ => None,
+    },
+    Gate::Binary {
+      op,
+      left,
+      right,
+      output,
+    } => {
+      let (l, r) = (ctx.constant_int(left), ctx.constant_int(right));
+      let is = |x: &Option<BigUint>, value: u32| x.as_ref() == Some(&BigUint::from(value));
+
+      match op {
+        BinaryOp::Minus | BinaryOp::BitXor if left == right => Some(Rewrite::Constant(json!(0))),
+        BinaryOp::Plus if is(&l, 0) => Some(Rewrite::Wire(right)),
+        BinaryOp::Plus | BinaryOp::Minus if is(&r, 0) => Some(Rewrite::Wire(left)),
+        BinaryOp::Mul if is(&l, 0) || is(&r, 0) => Some(Rewrite::Constant(json!(0))),
+        BinaryOp::Mul if is(&l, 1) => Some(Rewrite::Wire(right)),
+        BinaryOp::Mul if is(&r, 1) => Some(Rewrite::Wire(left)),
+        BinaryOp::Mul if ctx.circuit.field_modulus.is_none() => {
+          // Multiplying by a constant is free in arithmetic backends, so this is only for integer
+          // circuits. In a boolean circuit, `2^k` is zero when it doesn't fit but the shift amount
+          // wraps (see `FixedWidthNumber`), so `k` needs to be below every width it might have.
+          let (x, k) = match (power_of_two(&l), power_of_two(&r)) {
+            (_, Some(k)) => (left, k),
+            (Some(k), _) => (right, k),
+            _ => return None,
+          };
+
+          if k >= min_boolean_width(ctx.circuit) as u64 {
+            return None;
+          }
+
+          Some(Rewrite::Gate(Gate::Binary {
+            op: BinaryOp::LeftShift,
+            left: x,
+            right: ctx.constant_wire(json!(k)),
+            output,
+          }))
+        }
+        _ => None,
+      }
+    }
+  })
+}
+
+fn fold_constants(circuit: &mut Circuit) -> Vec<usize> {
+  rewrite_gates(circuit, |ctx, gate| {
+    let value = match &ctx.circuit.field_modulus {
+      None => {
+        let value = |wire| ctx.constant(wire).map(|x| NumberOrBool::from_json(x, &()));
+
+        if !agrees_at_every_width(ctx.circuit, gate) {
+          return None;
+        }
+
+        match *gate {
+          Gate::Unary { op, input, .. } => NumberOrBool::unary_op(op, &value(input)?, &()),
+          Gate::Binary {
+            op, left, right, ..
+          } => {
+            let (left, right) = (value(left)?, value(right)?);
+
+            let zero = matches!(right, NumberOrBool::Number(0) | NumberOrBool::Bool(false));
+
+            if matches!(op, BinaryOp::Div | BinaryOp::Mod) && zero {
+              return None;
+            }
+
+            NumberOrBool::binary_op(op, &left, &right, &())
+          }
+        }
+        .to_json()
+      }
+      Some(modulus) => {
+        let value = |wire| {
+          ctx
+            .constant(wire)
+            .map(|x| FieldElement::from_json(x, modulus))
+        };
+
+        let res = match *gate {
+          Gate::Unary { op, input, .. } => FieldElement::unary_op(op, &value(input)?, modulus),
+          Gate::Binary {
+            op, left, right, ..
+          } => {
+            let (left, right) = (value(left)?, value(right)?);
+
+            let invertible = right.0.modinv(modulus).is_some();
+
+            if (op == BinaryOp::Div && !invertible) || (op == BinaryOp::Mod && right.0.is_zero()) {
+              return None;
+            }
+
+            FieldElement::binary_op(op, &left, &right, modulus)
+          }
+        };
+
+        // Bools are 0 and 1 in the field, but keeping them as bools keeps the wire's kind
+        match ctx.kinds[gate.output()].bool {
+          true => json!(!res.0.is_zero()),
+          false => res.to_json(),
+        }
+      }
+    };
+
+    Some(Rewrite::Constant(value))
+  })
+}
+
+/**
+ * The narrowest width the circuit can be lowered to a boolean circuit at, since that needs to hold
+ * every input (see `Circuit::max_input_bit_width`).
+ */
+fn min_boolean_width(circuit: &Circuit) -> usize {
+  circuit.max_input_bit_width().unwrap_or(1)
+}
+
+/**
+ * Whether evaluating a gate on constants with 64-bit integers gives the same result as a boolean
+ * circuit of any width would (after truncating to that width).
+ *
+ * Arithmetic and bitwise operations always do. The rest (comparisons, division, shifts, etc) only
+ * do when their operands are unaffected by truncation, and shift amounts are below the width.
+ */
+fn agrees_at_every_width(circuit: &Circuit, gate: &Gate) -> bool {
+  use BinaryOp::*;
+
+  let width = min_boolean_width(circuit);
+
+  let bits = |wire: &usize| match circuit.constants.get(wire) {
+    Some(serde_json::Value::Bool(x)) => *x as u64,
+    Some(x) => x.as_u64().unwrap_or(u64::MAX),
+    None => u64::MAX,
+  };
+
+  let fits = |wire: &usize| width >= 64 || bits(wire) < 1 << width;
+
+  match gate {
+    Gate::Unary { op, input, .. } => *op != UnaryOp::Not || fits(input),
+    Gate::Binary {
+      op, left, right, ..
+    } => match op {
+      Plus | Minus | Mul | BitAnd | BitOr | BitXor => true,
+      LeftShift | RightShift | RightShiftUnsigned => fits(left) && bits(right) < width as u64,
+      _ => fits(left) && fits(right),
+    },
+  }
+}
+
+/**
+ * Rebuilds chains of the same associative and commutative operation (e.g. `((a + b) + c) + d`
+ * from `sum += x` in a loop) as balanced trees (`(a + b) + (c + d)`), which computes the same
+ * result with the same number of gates.
+ *
+ * A chain continues through gates whose output is only used by the next gate of the chain.
+ * Operands are combined shallowest first (by multiplicative depth, then depth), which gives the
+ * least depth possible when they are ready at different depths. Constant factors of a product are
+ * applied last, since multiplying by a constant is free (see `Circuit::is_linear`).
+ */
+fn rebalance_chains(circuit: &mut Circuit) -> Vec<usize> {
+  let field = circuit.field_modulus.is_some();
+  let mut mult_depths = circuit.mult_depths();
+  let gates = take(&mut circuit.gates);
+
+  let mut uses = vec![0usize; circuit.size];
+
+  for gate in &gates {
+    gate.for_each_input(|input| uses[input] += 1);
+  }
+
+  for (_, wire) in &circuit.outputs {
+    uses[*wire] += 1;
+  }
+
+  let mut producers = HashMap::<usize, usize>::new();
+  let mut absorbed = vec![false; gates.len()];
+
+  for (i, gate) in gates.iter().enumerate() {
+    if let Gate::Binary {
+      op, left, right, ..
+    } = gate
+    {
+      if is_associative(*op, field) {
+        for operand in [left, right] {
+          if let Some(j) = producers.get(operand) {
+            if matches!(gates[*j], Gate::Binary { op: inner_op, .. } if inner_op == *op)
+              && uses[*operand] == 1
+            {
+              absorbed[*j] = true;
+            }
+          }
+        }
+      }
+    }
+
+    producers.insert(gate.output(), i);
+  }
+
+  let mut depths = vec![0usize; circuit.size];
+
+  for gate in &gates {
+    gate.for_each_input(|input| depths[gate.output()] = max(depths[gate.output()], depths[input]));
+    depths[gate.output()] += 1;
+  }
+
+  let mut sources = vec![];
+
+  for (i, gate) in gates.iter().enumerate() {
+    if absorbed[i] {
+      continue;
+    }
+
+    let Gate::Binary { op, output, .. } = *gate else {
+      circuit.gates.push(*gate);
+      sources.push(i);
+      continue;
+    };
+
+    let mut chain = vec![];
+    let mut leaves = vec![];
+    let mut pending = vec![i];
+
+    while let Some(j) = pending.pop() {
+      chain.push(j);
+
+      let Gate::Binary { left, right, .. } = gates[j] else {
+        unreachable!("Chains only contain binary gates");
+      };
+
+      for operand in [left, right] {
+        match producers.get(&operand) {
+          Some(k) if absorbed[*k] => pending.push(*k),
+          _ => leaves.push(operand),
+        }
+      }
+    }
+
+    if chain.len() == 1 {
+      circuit.gates.push(*gate);
+      sources.push(i);
+      continue;
+    }
+
+    chain.sort();
+    sources.extend(&chain);
+
+    let (mut constant_factors, mut leaves): (Vec<_>, Vec<_>) = leaves
+      .into_iter()
+      .partition(|leaf| op == BinaryOp::Mul && circuit.constants.contains_key(leaf));
+
+    if leaves.is_empty() {
+      leaves = take(&mut constant_factors);
+    }
+
+    let mut queue = leaves
+      .into_iter()
+      .enumerate()
+      .map(|(order, wire)| Reverse((mult_depths[wire], depths[wire], order, wire)))
+      .collect::<BinaryHeap<_>>();
+
+    let mut order = queue.len();
+    let mut constant_factors = constant_factors.into_iter();
+
+    // Each step combines two operands, so there are as many new gates as in the chain
+    loop {
+      let Reverse((_, _, _, left)) = queue.pop().expect("Chain should have operands");
+
+      let right = match queue.pop() {
+        Some(Reverse((_, _, _, right))) => right,
+        None => match constant_factors.next() {
+          Some(constant) => constant,
+          None => break,
+        },
+      };
+
+      let is_last = queue.is_empty() && constant_factors.len() == 0;
+
+      let new_output = if is_last {
+        output
+      } else {
+        circuit.size += 1;
+        mult_depths.push(0);
+        depths.push(0);
+        circuit.size - 1
+      };
+
+      let new_gate = Gate::Binary {
+        op,
+        left,
+        right,
+        output: new_output,
+      };
+
+      let level = if circuit.is_linear(&new_gate) { 0 } else { 1 };
+      mult_depths[new_output] = level + max(mult_depths[left], mult_depths[right]);
+      depths[new_output] = 1 + max(depths[left], depths[right]);
+
+      circuit.gates.push(new_gate);
+
+      if is_last {
+        break;
+      }
+
+      queue.push(Reverse((
+        mult_depths[new_output],
+        depths[new_output],
+        order,
+        new_output,
+      )));
+
+      order += 1;
+    }
+  }
+
+  sources
+}
+
+/**
+ * Whether `(a op b) op c == a op (b op c)` and `a op b == b op a`. Bitwise operations aren't
+ * associative in a field, where `|` and `^` can exceed the modulus and get reduced.
+ */
+fn is_associative(op: BinaryOp, field: bool) -> bool {
+  match op {
+    BinaryOp::Plus | BinaryOp::Mul | BinaryOp::And | BinaryOp::Or => true,
+    BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor => !field,
+    _ => false,
+  }
+}
+
+fn remove_dead_wires(circuit: &mut Circuit) -> Vec<usize> {
+  let mut live = vec![false; circuit.size];
+
+  for (_, wire) in &circuit.outputs {
+    live[*wire] = true;
+  }
+
+  for gate in circuit.gates.iter().rev() {
+    if live[gate.output()] {
+      gate.for_each_input(|input| live[input] = true);
+    }
+  }
+
+  let mut new_ids = vec![usize::MAX; circuit.size];
+  let mut next_id = 0;
+
+  let mut input_wires = circuit
+    .inputs
+    .values()
+    .map(|input| input.wire_id)
+    .collect::<Vec<_>>();
+
+  input_wires.sort();
+
+  let constant_wires = circuit
+    .constants
+    .keys()
+    .copied()
+    .filter(|wire| live[*wire])
+    .collect::<Vec<_>>();
+
+  let gate_wires = circuit
+    .gates
+    .iter()
+    .map(Gate::output)
+    .filter(|wire| live[*wire])
+    .collect::<Vec<_>>();
+
+  for wire in input_wires.iter().chain(&constant_wires).chain(&gate_wires) {
+    new_ids[*wire] = next_id;
+    next_id += 1;
+  }
+
+  let mut sources = vec![];
+
+  for (i, gate) in take(&mut circuit.gates).into_iter().enumerate() {
+    if live[gate.output()] {
+      circuit.gates.push(gate.map_wires(|wire| new_ids[wire]));
+      sources.push(i);
+    }
+  }
+
+  circuit.constants = take(&mut circuit.constants)
+    .into_iter()
+    .filter(|(wire, _)| live[*wire])
+    .map(|(wire, value)| (new_ids[wire], value))
+    .collect();
+
+  for input in circuit.inputs.values_mut() {
+    input.wire_id = new_ids[input.wire_id];
+  }
+
+  for (_, wire) in &mut circuit.outputs {
+    *wire = new_ids[*wire];
+  }
+
+  circuit.size = next_id;
+
+  sources
+}
+
+enum Rewrite {
+  /** The gate's output is the same as this wire. */
+  Wire(usize),
+
+  /** The gate's output is always this constant. */
+  Constant(serde_json::Value),
+
+  /** Replace the gate with a different gate that has the same output. */
+  Gate(Gate),
+}
+
+/**
+ * What a rewrite needs to preserve about a wire.
+ */
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct WireKind {
+  bool: bool,
+  signed: bool,
+}
+
+impl WireKind {
+  fn constant(value: &serde_json::Value) -> Self {
+    WireKind {
+      bool: value.is_boolean(),
+      signed: false,
+    }
+  }
+}
+
+fn wire_kinds(circuit: &Circuit) -> Vec<WireKind> {
+  infer_bools(circuit)
+    .into_iter()
+    .zip(infer_signedness(circuit))
+    .map(|(bool, signed)| WireKind { bool, signed })
+    .collect()
+}
+
+struct RewriteContext<'a> {
+  circuit: &'a mut Circuit,
+  kinds: Vec<WireKind>,
+  replacements: Vec<usize>,
+  constant_wires: HashMap<serde_json::Value, usize>,
+  producers: HashMap<usize, usize>,
+}
+
+impl RewriteContext<'_> {
+  fn constant(&self, wire: usize) -> Option<&serde_json::Value> {
+    self.circuit.constants.get(&wire)
+  }
+
+  /** The value of a numeric (not bool) constant. */
+  fn constant_int(&self, wire: usize) -> Option<BigUint> {
+    match self.constant(wire)? {
+      serde_json::Value::Number(x) => x.as_u64().map(BigUint::from),
+      serde_json::Value::String(x) => x.parse().ok(),
+      _ => None,
+    }
+  }
+
+  /** The wire of a constant with this value, adding it if needed. */
+  fn constant_wire(&mut self, value: serde_json::Value) -> usize {
+    if let Some(wire) = self.constant_wires.get(&value) {
+      return *wire;
+    }
+
+    let wire = self.circuit.size;
+    self.circuit.size += 1;
+
+    self.kinds.push(WireKind::constant(&value));
+    self.replacements.push(wire);
+    self.constant_wires.insert(value.clone(), wire);
+    self.circuit.constants.insert(wire, value);
+
+    wire
+  }
+
+  /** The (already rewritten) gate that computes `wire`, if any. */
+  fn producer(&self, wire: usize) -> Option<&Gate> {
+    self.producers.get(&wire).map(|i| &self.circuit.gates[*i])
+  }
+}
+
+/**
+ * Walks the gates in order, replacing each gate's operands according to earlier rewrites, and
+ * then applying `rewrite` to it. Rewrites that would change the kind of the gate's output are
+ * ignored. Returns the original index of each remaining gate.
+ */
+fn rewrite_gates(
+  circuit: &mut Circuit,
+  mut rewrite: impl FnMut(&mut RewriteContext, &Gate) -> Option<Rewrite>,
+) -> Vec<usize> {
+  let mut constant_wires = HashMap::<serde_json::Value, usize>::new();
+
+  for (wire, value) in &circuit.constants {
+    constant_wires.entry(value.clone()).or_insert(*wire);
+  }
+
+  let kinds = wire_kinds(circuit);
+  let gates = take(&mut circuit.gates);
+  let mut sources = vec![];
+
+  let mut ctx = RewriteContext {
+    kinds,
+    replacements: (0..circuit.size).collect(),
+    constant_wires,
+    producers: HashMap::new(),
+    circuit,
+  };
+
+  for (i, gate) in gates.into_iter().enumerate() {
+    let gate = gate.map_inputs(|wire| ctx.replacements[wire]);
+    let output = gate.output();
+
+    let gate = match rewrite(&mut ctx, &gate) {
+      Some(Rewrite::Wire(wire)) if ctx.kinds[wire] == ctx.kinds[output] => {
+        ctx.replacements[output] = wire;
+        continue;
+      }
+      Some(Rewrite::Constant(value)) if WireKind::constant(&value) == ctx.kinds[output] => {
+        ctx.replacements[output] = ctx.constant_wire(value);
+        continue;
+      }
+      Some(Rewrite::Gate(new_gate)) => new_gate,
+      _ => gate,
+    };
+
+    ctx.producers.insert(output, ctx.circuit.gates.len());
+    ctx.circuit.gates.push(gate);
+    sources.push(i);
+  }
+
+  for (_, wire) in &mut ctx.circuit.outputs {
+    *wire = ctx.replacements[*wire];
+  }
+
+  sources
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum GateKey {
+  Unary(UnaryOp, usize),
+  Binary(BinaryOp, usize, usize),
+}
+
+impl GateKey {
+  fn new(gate: &Gate) -> Self {
+    use BinaryOp::*;
+
+    match *gate {
+      Gate::Unary { op, input, .. } => GateKey::Unary(op, input),
+      Gate::Binary {
+        op, left, right, ..
+      } => match op {
+        Plus | Mul | LooseEq | LooseNe | Eq | Ne | And | Or | BitAnd | BitOr | BitXor => {
+          GateKey::Binary(op, left.min(right), left.max(right))
+        }
+        _ => GateKey::Binary(op, left, right),
+      },
+    }
+  }
+}
+
+fn power_of_two(x: &Option<BigUint>) -> Option<u64> {
+  let x = x.as_ref()?;
+
+  match x.count_ones() == 1 {
+    true => (x.bits() - 1).to_u64(),
+    false => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::BTreeMap;
+
+  use super::*;
+  use crate::circuit::test_circuit;
+
+  /** A circuit with `inputs` inputs of `bits` bits each (or numbers). See `test_circuit`. */
+  fn circuit(
+    bits: Option<usize>,
+    inputs: usize,
+    constants: &[serde_json::Value],
+    gates: &[Gate],
+    outputs: &[usize],
+  ) -> Circuit {
+    let type_json = match bits {
+      Some(bits) => json!({ "type": "uint", "bits": bits }),
+      None => json!("number"),
+    };
+
+    test_circuit(&vec![type_json; inputs], constants, gates, outputs)
+  }
+
+  fn unary(op: UnaryOp, input: usize, output: usize) -> Gate {
+    Gate::Unary { op, input, output }
+  }
+
+  fn binary(op: BinaryOp, left: usize, right: usize, output: usize) -> Gate {
+    Gate::Binary {
+      op,
+      left,
+      right,
+      output,
+    }
+  }
+
+  /** The gate counts before and after the pass called `name`. */
+  fn gate_counts(report: &OptimizationReport, name: &str) -> (usize, usize) {
+    let pass = report
+      .passes
+      .iter()
+      .find(|pass| pass.name == name)
+      .expect("Pass should have run");
+
+    (pass.gates_before, pass.gates_after)
+  }
+
+  fn output_wires(circuit: &Circuit) -> Vec<usize> {
+    circuit.outputs.iter().map(|(_, wire)| *wire).collect()
+  }
+
+  #[test]
+  fn simplify_cancels_and_undoes_negation() {
+    use BinaryOp::*;
+
+    // a = 0, b = 1
+    let mut c = circuit(
+      Some(8),
+      2,
+      &[],
+      &[
+        binary(Minus, 0, 0, 2),      // a - a
+        binary(BitXor, 1, 1, 3),     // b ^ b
+        unary(UnaryOp::Minus, 1, 4), // -b
+        unary(UnaryOp::Minus, 4, 5), // -(-b)
+        binary(Plus, 2, 5, 6),       // (a - a) + -(-b)
+        binary(Mul, 0, 1, 7),        // a * b
+      ],
+      &[3, 6, 7],
+    );
+
+    let report = optimize_circuit(&mut c, &OptimizeOptions::default());
+
+    assert_eq!(gate_counts(&report, "algebraic simplification"), (6, 2));
+    assert_eq!(gate_counts(&report, "constant folding"), (2, 2));
+    assert_eq!(gate_counts(&report, "dead wire removal"), (2, 1));
+
+    // Only `a * b` is left, and `b ^ b` is the constant 0 (wire 2)
+    assert_eq!(c.gates, vec![binary(Mul, 0, 1, 3)]);
+    assert_eq!(c.constants, BTreeMap::from([(2, json!(0))]));
+    assert_eq!(output_wires(&c), vec![2, 1, 3]);
+    assert_eq!(report.gate_sources, vec![5]);
+  }
+
+  #[test]
+  fn simplify_keeps_the_kind_of_the_wire() {
+    // `a - a` with signed `a` is a signed wire, which the unsigned constant 0 isn't
+    let mut c = circuit(Some(8), 1, &[], &[binary(BinaryOp::Minus, 0, 0, 1)], &[1]);
+    c.inputs.values_mut().for_each(|input| input.signed = true);
+
+    let report = optimize_circuit(&mut c, &OptimizeOptions::default());
+
+    assert_eq!(gate_counts(&report, "algebraic simplification"), (1, 1));
+    assert_eq!(c.gates, vec![binary(BinaryOp::Minus, 0, 0, 1)]);
+  }
+
+  #[test]
+  fn simplify_multiplies_by_powers_of_two_with_shifts() {
+    use BinaryOp::*;
+
+    // a = 0, 8 = 1, 256 = 2
+    let gates = [binary(Mul, 0, 1, 3), binary(Mul, 2, 0, 4)];
+
+    let mut c = circuit(Some(8), 1, &[json!(8), json!(256)], &gates, &[3, 4]);
+    let report = optimize_circuit(&mut c, &OptimizeOptions::default());
+
+    assert_eq!(gate_counts(&report, "algebraic simplification"), (2, 2));
+
+    // `a * 8` is `a << 3` (3 is the new constant wire 2), but `a * 256` isn't `a << 8` since 8
+    // bits is the narrowest boolean circuit, where the shift amount wraps
+    assert_eq!(
+      c.gates,
+      vec![binary(LeftShift, 0, 2, 3), binary(Mul, 1, 0, 4)]
+    );
+    assert_eq!(
+      c.constants,
+      BTreeMap::from([(1, json!(256)), (2, json!(3))])
+    );
+
+    // Without input widths, the circuit could be 1 bit wide, so nothing is rewritten
+    let mut c = circuit(None, 1, &[json!(8), json!(256)], &gates, &[3, 4]);
+    optimize_circuit(&mut c, &OptimizeOptions::default());
+
+    assert_eq!(c.gates, vec![binary(Mul, 0, 1, 3), binary(Mul, 2, 0, 4)]);
+
+    // Multiplying by a constant is already free in a field
+    let mut c = circuit(Some(8), 1, &[json!(8), json!(256)], &gates, &[3, 4]);
+    c.field_modulus = Some(BigUint::from(101u32));
+    optimize_circuit(&mut c, &OptimizeOptions::default());
+
+    assert_eq!(c.gates, vec![binary(Mul, 0, 1, 3), binary(Mul, 2, 0, 4)]);
+  }
+
+  #[test]
+  fn fold_constants_only_when_every_width_agrees() {
+    use BinaryOp::*;
+
+    // 300 = 1, 5 = 2, 1 = 3, 2 = 4, 9 = 5
+    let mut c = circuit(
+      Some(8),
+      1,
+      &[json!(300), json!(5), json!(1), json!(2), json!(9)],
+      &[
+        binary(Less, 1, 2, 6),      // 300 doesn't fit in 8 bits
+        binary(Less, 3, 2, 7),      // true
+        binary(Plus, 3, 4, 8),      // 3 (arithmetic agrees at every width)
+        binary(LeftShift, 4, 5, 9), // 9 isn't below 8 bits
+      ],
+      &[6, 7, 8, 9],
+    );
+
+    let report = optimize_circuit(&mut c, &OptimizeOptions::default());
+
+    assert_eq!(gate_counts(&report, "constant folding"), (4, 2));
+    assert_eq!(gate_counts(&report, "dead wire removal"), (2, 2));
+
+    assert_eq!(
+      c.gates,
+      vec![binary(Less, 1, 2, 7), binary(LeftShift, 3, 4, 8)]
+    );
+
+    assert_eq!(
+      c.constants,
+      BTreeMap::from([
+        (1, json!(300)),
+        (2, json!(5)),
+        (3, json!(2)),
+        (4, json!(9)),
+        (5, json!(true)),
+        (6, json!(3)),
+      ]),
+    );
+
+    assert_eq!(output_wires(&c), vec![7, 5, 6, 8]);
+  }
+
+  #[test]
+  fn fold_constants_in_a_field() {
+    use BinaryOp::*;
+
+    // 3 = 1, 5 = 2, 0 = 3
+    let mut c = circuit(
+      None,
+      1,
+      &[json!(3), json!(5), json!(0)],
+      &[
+        binary(Minus, 1, 2, 4), // 99
+        binary(Div, 1, 2, 5),   // 3 * 5^-1 = 41
+        binary(Div, 1, 3, 6),   // not invertible
+        binary(Less, 1, 2, 7),  // true (even though the circuit might be 1 bit wide)
+      ],
+      &[4, 5, 6, 7],
+    );
+
+    c.field_modulus = Some(BigUint::from(101u32));
+
+    let report = optimize_circuit(&mut c, &OptimizeOptions::default());
+
+    assert_eq!(gate_counts(&report, "constant folding"), (4, 1));
+
+    assert_eq!(c.gates, vec![binary(Div, 1, 2, 6)]);
+
+    assert_eq!(
+      c.constants,
+      BTreeMap::from([
+        (1, json!(3)),
+        (2, json!(0)),
+        (3, json!(99)),
+        (4, json!(41)),
+        (5, json!(true)),
+      ]),
+    );
+
+    assert_eq!(output_wires(&c), vec![3, 4, 6, 5]);
+  }
+
+  #[test]
+  fn rebalance_applies_constant_factors_last() {
+    use BinaryOp::*;
+
+    // ((((a * 3) * b) * 5) * c) * d, where a-d = 0-3, 3 = 4, 5 = 5
+    let mut c = circuit(
+      Some(8),
+      4,
+      &[json!(3), json!(5)],
+      &[
+        binary(Mul, 0, 4, 6),
+        binary(Mul, 6, 1, 7),
+        binary(Mul, 7, 5, 8),
+        binary(Mul, 8, 2, 9),
+        binary(Mul, 9, 3, 10),
+      ],
+      &[10],
+    );
+
+    let max_mult_depth = |c: &Circuit| c.mult_depths().into_iter().max().unwrap();
+    assert_eq!(max_mult_depth(&c), 3);
+
+    let report = optimize_circuit(
+      &mut c,
+      &OptimizeOptions {
+        minimize_depth: true,
+      },
+    );
+
+    let pass = report
+      .passes
+      .iter()
+      .find(|pass| pass.name == "depth rebalancing")
+      .unwrap();
+
+    assert_eq!((pass.gates_before, pass.gates_after), (5, 5));
+    assert_eq!((pass.depth_before, pass.depth_after), (5, 4));
+
+    // (d * c) * (b * a) first, then the constants, which don't add multiplicative depth
+    assert_eq!(
+      c.gates,
+      vec![
+        binary(Mul, 3, 2, 6),
+        binary(Mul, 1, 0, 7),
+        binary(Mul, 6, 7, 8),
+        binary(Mul, 8, 5, 9),
+        binary(Mul, 9, 4, 10),
+      ],
+    );
+
+    assert_eq!(max_mult_depth(&c), 2);
+    assert_eq!(report.gate_sources, vec![0, 1, 2, 3, 4]);
+  }
+}
@@ -3,6 +3,7 @@ use std::{cell::RefCell, rc::Rc};
 use crate::{
   binary_op::BinaryOp,
   operations::op_or,
+  signal_arena::SignalArena,
   type_error_builtin::ToTypeError,
   unary_op::UnaryOp,
   vs_value::{ToDynamicVal, ToVal, Val},
@@ -10,39 +11,156 @@ use crate::{
 };
 use num_bigint::BigInt;
 
+use crate::val_dynamic_downcast::val_dynamic_downcast;
 use crate::vs_value::VsType;
-use crate::{id_generator::IdGenerator, val_dynamic_downcast::val_dynamic_downcast};
 
-#[derive(Clone)]
+/**
+ * How a signal is computed. This is what `SignalArena` stores for each signal, and (for operations)
+ * how it finds existing signals that compute the same thing.
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum CircuitSignalData {
   Input,
-  UnaryOp(UnaryOp, Val),
-  BinaryOp(BinaryOp, Val, Val),
+  UnaryOp(UnaryOp, SignalOperand),
+  BinaryOp(BinaryOp, SignalOperand, SignalOperand),
+}
+
+/**
+ * A signal (by id, with the type it was used as) or a constant.
+ */
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum SignalOperand {
+  Signal(usize, VsType),
+  Number(u64), // f64 bits
+  Bool(bool),
 }
 
+/**
+ * A signal in a `SignalArena`. The type belongs to the handle rather than the arena, since the same
+ * signal can be used as different types (e.g. bools that were merged arithmetically, and `+x`).
+ */
 #[derive(Clone)]
 pub struct CircuitSignal {
   pub type_: VsType,
-  pub data: CircuitSignalData,
   pub id: usize,
-  pub id_generator: Rc<RefCell<IdGenerator>>,
+  pub arena: Rc<RefCell<SignalArena>>,
 }
 
 impl CircuitSignal {
-  pub fn new(
-    id_generator: &Rc<RefCell<IdGenerator>>,
-    type_: Option<VsType>,
-    data: CircuitSignalData,
-  ) -> Self {
+  pub fn input(arena: &Rc<RefCell<SignalArena>>, type_: VsType) -> Self {
     CircuitSignal {
-      type_: type_.unwrap_or_else(|| typeof_(&data)),
-      data,
-      id: id_generator.borrow_mut().gen(),
-      id_generator: id_generator.clone(),
+      type_,
+      id: arena.borrow_mut().add(CircuitSignalData::Input),
+      arena: arena.clone(),
+    }
+  }
+
+  pub fn unary_op(arena: &Rc<RefCell<SignalArena>>, op: UnaryOp, input: &Val) -> Result<Self, Val> {
+    CircuitSignal::new(
+      arena,
+      CircuitSignalData::UnaryOp(op, SignalOperand::new(input)?),
+    )
+  }
+
+  /**
+   * Operands of commutative operations are sorted, so `a * b` and `b * a` are the same signal.
+   */
+  pub fn binary_op(
+    arena: &Rc<RefCell<SignalArena>>,
+    op: BinaryOp,
+    left: &Val,
+    right: &Val,
+  ) -> Result<Self, Val> {
+    let mut left = SignalOperand::new(left)?;
+    let mut right = SignalOperand::new(right)?;
+
+    if is_commutative(op) && right < left {
+      std::mem::swap(&mut left, &mut right);
+    }
+
+    CircuitSignal::new(arena, CircuitSignalData::BinaryOp(op, left, right))
+  }
+
+  fn new(arena: &Rc<RefCell<SignalArena>>, data: CircuitSignalData) -> Result<Self, Val> {
+    Ok(CircuitSignal {
+      type_: typeof_(&data)?,
+      id: arena.borrow_mut().add(data),
+      arena: arena.clone(),
+    })
+  }
+
+  pub fn data(&self) -> CircuitSignalData {
+    self.arena.borrow().node(self.id).data
+  }
+}
+
+impl SignalOperand {
+  fn new(val: &Val) -> Result<SignalOperand, Val> {
+    match val {
+      // -0 and 0 are the same constant in the circuit
+      Val::Number(x) if *x == 0.0 => Ok(SignalOperand::Number(0f64.to_bits())),
+      Val::Number(x) => Ok(SignalOperand::Number(x.to_bits())),
+      Val::Bool(x) => Ok(SignalOperand::Bool(*x)),
+      _ => match val_dynamic_downcast::<CircuitSignal>(val) {
+        Some(signal) => Ok(SignalOperand::Signal(signal.id, signal.type_)),
+        None => {
+          Err(format!("Can't use {} in an operation on signals", val.codify()).to_type_error())
+        }
+      },
+    }
+  }
+
+  pub fn to_val(self, arena: &Rc<RefCell<SignalArena>>) -> Val {
+    match self {
+      SignalOperand::Signal(id, type_) => CircuitSignal {
+        type_,
+        id,
+        arena: arena.clone(),
+      }
+      .to_dynamic_val(),
+      SignalOperand::Number(bits) => f64::from_bits(bits).to_val(),
+      SignalOperand::Bool(x) => x.to_val(),
+    }
+  }
+
+  fn type_(&self) -> VsType {
+    match self {
+      SignalOperand::Signal(_, type_) => *type_,
+      SignalOperand::Number(_) => VsType::Number,
+      SignalOperand::Bool(_) => VsType::Bool,
     }
   }
 }
 
+/**
+ * Whether swapping the operands gives the same result in the circuit. (`&&` and `||` produce bools
+ * in the circuit, so they are commutative there, unlike in JavaScript.)
+ */
+fn is_commutative(op: BinaryOp) -> bool {
+  use BinaryOp::*;
+
+  matches!(
+    op,
+    Plus | Mul | LooseEq | LooseNe | Eq | Ne | And | Or | BitAnd | BitOr | BitXor
+  )
+}
+
+pub fn is_signal(val: &Val) -> bool {
+  val_dynamic_downcast::<CircuitSignal>(val).is_some()
+}
+
+/**
+ * Like `val.is_truthy()`, with an error for signals that names the context. This is for contexts
+ * that need a concrete bool, such as the callback result of `Array.prototype.filter`.
+ */
+pub fn try_is_truthy(val: &Val, context: &str) -> Result<bool, Val> {
+  if is_signal(val) {
+    return Err(format!("{} can't depend on a signal", context).to_type_error());
+  }
+
+  val.is_truthy()
+}
+
 impl ValTrait for CircuitSignal {
   fn to_number(&self) -> f64 {
     f64::NAN
@@ -53,15 +171,19 @@ impl ValTrait for CircuitSignal {
   }
 
   fn to_index(&self) -> Option<usize> {
-    panic!("Not implemented: using CircuitSignal as index")
+    // Arrays handle signal indexes separately (see `signal_index`), everything else treats a
+    // signal as a non-index
+    None
   }
 
   fn is_primitive(&self) -> bool {
     false
   }
 
-  fn is_truthy(&self) -> bool {
-    panic!("Not implemented: truthiness of CircuitSignal")
+  fn is_truthy(&self) -> Result<bool, Val> {
+    // Conditional jumps and logical operators handle signals before asking for truthiness, so this
+    // is only reached where a concrete bool is required
+    Err("Truthiness of a signal isn't known at compile time".to_type_error())
   }
 
   fn is_nullish(&self) -> bool {
@@ -109,6 +231,17 @@ impl ValTrait for CircuitSignal {
       return None;
     }
 
+    if matches!(op, BinaryOp::And | BinaryOp::Or) && left.typeof_() != right.typeof_() {
+      return Some(Err(
+        format!(
+          "Can't mix {} and {} in && or || with signals",
+          left.typeof_(),
+          right.typeof_()
+        )
+        .to_type_error(),
+      ));
+    }
+
     match op {
       BinaryOp::Plus => {
         if let Val::Number(left) = left {
@@ -152,13 +285,11 @@ impl ValTrait for CircuitSignal {
         if let Some((y, a, b)) = common_and(left, right) {
           // (y && a) || (y && b) => y && (a || b)
 
-          return Some(op_or(a, b).map(|a_or_b| {
-            CircuitSignal::new(
-              &y.id_generator,
-              None,
-              CircuitSignalData::BinaryOp(BinaryOp::And, y.clone().to_dynamic_val(), a_or_b),
-            )
-            .to_dynamic_val()
+          let [y, a, b] = [y, a, b].map(|operand| operand.to_val(&self.arena));
+
+          return Some(op_or(&a, &b).and_then(|a_or_b| {
+            CircuitSignal::binary_op(&self.arena, BinaryOp::And, &y, &a_or_b)
+              .map(|signal| signal.to_dynamic_val())
           }));
         }
 
@@ -197,32 +328,26 @@ impl ValTrait for CircuitSignal {
       _ => {}
     }
 
-    Some(Ok(
-      CircuitSignal::new(
-        &self.id_generator,
-        None,
-        CircuitSignalData::BinaryOp(op, left.clone(), right.clone()),
-      )
-      .to_dynamic_val(),
-    ))
+    Some(
+      CircuitSignal::binary_op(&self.arena, op, left, right).map(|signal| signal.to_dynamic_val()),
+    )
   }
 
   fn override_unary_op(&self, op: UnaryOp, input: &Val) -> Option<Result<Val, Val>> {
-    if op == UnaryOp::Plus && val_dynamic_downcast::<CircuitSignal>(input).is_some() {
-      return Some(Ok(
-        CircuitSignal::new(&self.id_generator, Some(VsType::Number), self.data.clone())
+    if op == UnaryOp::Plus {
+      if let Some(input) = val_dynamic_downcast::<CircuitSignal>(input) {
+        // The same signal, used as a number
+        return Some(Ok(
+          CircuitSignal {
+            type_: VsType::Number,
+            ..input.clone()
+          }
           .to_dynamic_val(),
-      ));
+        ));
+      }
     }
 
-    Some(Ok(
-      CircuitSignal::new(
-        &self.id_generator,
-        None,
-        CircuitSignalData::UnaryOp(op, input.clone()),
-      )
-      .to_dynamic_val(),
-    ))
+    Some(CircuitSignal::unary_op(&self.arena, op, input).map(|signal| signal.to_dynamic_val()))
   }
 
   fn pretty_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -235,38 +360,39 @@ impl ValTrait for CircuitSignal {
 }
 
 fn related_by_negation(left: &Val, right: &Val) -> bool {
-  if let Some((left, right)) = both_circuit_signals(left, right) {
-    if let CircuitSignalData::UnaryOp(UnaryOp::Not, not_left) = &left.data {
-      if let Some(not_left) = val_dynamic_downcast::<CircuitSignal>(not_left) {
-        if not_left.id == right.id {
-          return true;
-        }
-      }
-    }
-
-    if let CircuitSignalData::UnaryOp(UnaryOp::Not, not_right) = &right.data {
-      if let Some(not_right) = val_dynamic_downcast::<CircuitSignal>(not_right) {
-        if not_right.id == left.id {
-          return true;
-        }
-      }
-    }
-  }
-
-  false
+  let Some((left, right)) = both_circuit_signals(left, right) else {
+    return false;
+  };
+
+  let negates = |a: &CircuitSignal, b: &CircuitSignal| {
+    matches!(
+      a.data(),
+      CircuitSignalData::UnaryOp(UnaryOp::Not, SignalOperand::Signal(id, _)) if id == b.id
+    )
+  };
+
+  negates(left, right) || negates(right, left)
 }
 
-fn common_and<'a>(left: &'a Val, right: &'a Val) -> Option<(&'a CircuitSignal, &'a Val, &'a Val)> {
-  if let Some((left, right)) = both_circuit_signals(left, right) {
-    if let (
-      CircuitSignalData::BinaryOp(BinaryOp::And, left_lhs, left_rhs),
-      CircuitSignalData::BinaryOp(BinaryOp::And, right_lhs, right_rhs),
-    ) = (&left.data, &right.data)
-    {
-      if let Some((left_lhs, right_lhs)) = both_circuit_signals(left_lhs, right_lhs) {
-        if left_lhs.id == right_lhs.id {
-          return Some((left_lhs, left_rhs, right_rhs));
-        }
+/**
+ * For `(y && a)` and `(y && b)`, finds `(y, a, b)`.
+ */
+fn common_and(left: &Val, right: &Val) -> Option<(SignalOperand, SignalOperand, SignalOperand)> {
+  let (left, right) = both_circuit_signals(left, right)?;
+
+  let (
+    CircuitSignalData::BinaryOp(BinaryOp::And, left_lhs, left_rhs),
+    CircuitSignalData::BinaryOp(BinaryOp::And, right_lhs, right_rhs),
+  ) = (left.data(), right.data())
+  else {
+    return None;
+  };
+
+  // The operands of `&&` are sorted, so `y` can be on either side
+  for (y, a) in [(left_lhs, left_rhs), (left_rhs, left_lhs)] {
+    for (right_y, b) in [(right_lhs, right_rhs), (right_rhs, right_lhs)] {
+      if y == right_y && matches!(y, SignalOperand::Signal(..)) {
+        return Some((y, a, b));
       }
     }
   }
@@ -294,9 +420,9 @@ impl std::fmt::Display for CircuitSignal {
   }
 }
 
-fn typeof_(data: &CircuitSignalData) -> VsType {
-  match data {
-    CircuitSignalData::Input { .. } => VsType::Number,
+fn typeof_(data: &CircuitSignalData) -> Result<VsType, Val> {
+  Ok(match data {
+    CircuitSignalData::Input => VsType::Number,
     CircuitSignalData::UnaryOp(op, _) => match op {
       UnaryOp::Plus => VsType::Number,
       UnaryOp::Minus => VsType::Number,
@@ -314,10 +440,18 @@ fn typeof_(data: &CircuitSignalData) -> VsType {
       BinaryOp::LooseNe => VsType::Bool,
       BinaryOp::Eq => VsType::Bool,
       BinaryOp::Ne => VsType::Bool,
-      BinaryOp::And | BinaryOp::Or => match (left.typeof_(), right.typeof_()) {
+      BinaryOp::And | BinaryOp::Or => match (left.type_(), right.type_()) {
         (VsType::Number, VsType::Number) => VsType::Number,
         (VsType::Bool, VsType::Bool) => VsType::Bool,
-        (left, right) => panic!("Incompatible types {} {}", left, right),
+        (left, right) => {
+          return Err(
+            format!(
+              "Operands of && and || signals must have the same type, got {} and {}",
+              left, right
+            )
+            .to_type_error(),
+          )
+        }
       },
       BinaryOp::Less => VsType::Bool,
       BinaryOp::LessEq => VsType::Bool,
@@ -330,5 +464,5 @@ fn typeof_(data: &CircuitSignalData) -> VsType {
       BinaryOp::RightShift => VsType::Number,
       BinaryOp::RightShiftUnsigned => VsType::Number,
     },
-  }
+  })
 }
@@ -1,19 +1,103 @@
-use std::{cmp::Ordering, collections::BinaryHeap, mem::take, rc::Rc};
+use std::{
+  cell::RefCell,
+  cmp::Ordering,
+  collections::{BinaryHeap, HashMap},
+  mem::take,
+  rc::Rc,
+};
 
 use crate::{
   arithmetic_merge::arithmetic_merge,
   bytecode_stack_frame::BytecodeStackFrame,
-  circuit_vm_branch::CircuitVMBranch,
+  circuit_vm_branch::{CircuitVMBranch, UncaughtException},
+  error_builtin::ToError,
   internal_error_builtin::ToInternalError,
+  numeric_model::NumericModel,
   operations::op_or,
+  signal_arena::SignalArena,
+  source_map::StackTraceFrame,
   vs_value::{ToVal, Val},
   CallResult, FirstStackFrame, LoadFunctionResult, StackFrameTrait, ValTrait,
 };
 
+/**
+ * Limits for catching programs that branch indefinitely (e.g. a loop whose condition depends on a
+ * signal). `None` means unlimited.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct CircuitVMLimits {
+  pub steps: Option<usize>,
+
+  /** The number of branches waiting to be merged (`alt_branches`). */
+  pub branches: Option<usize>,
+
+  /**
+   * The number of signals created, which requires `CircuitVM::signal_arena`. Repeating a
+   * computation reuses the existing signal, so it only counts once.
+   */
+  pub signals: Option<usize>,
+}
+
+/**
+ * Describes why `run` stopped when a limit was exceeded.
+ */
+#[derive(Clone, Debug)]
+pub struct LimitExceeded {
+  pub message: String,
+
+  /**
+   * The call stack of the conditional jump that forked the most (innermost frame first), or of the
+   * current position if nothing forked.
+   */
+  pub stack_trace: Vec<StackTraceFrame>,
+}
+
+#[derive(Clone)]
+struct ForkSite {
+  count: usize,
+  stack_trace: Vec<StackTraceFrame>,
+}
+
 #[derive(Default)]
 pub struct CircuitVM {
   pub branch: CircuitVMBranch,
   pub alt_branches: BinaryHeap<CircuitVMBranch>,
+
+  /**
+   * Opt-in: set this to `Some(false)` to turn exceptions that are thrown under signal-dependent
+   * branches (and not caught) into a flag instead of failing the whole run. The throwing branch
+   * is discarded and its flag is or'd into this value, so afterwards it is a signal that is true
+   * exactly when the program would have thrown.
+   *
+   * Exceptions that are thrown regardless of the inputs still fail the run.
+   */
+  pub error_flag: Option<Val>,
+
+  /**
+   * When `run` fails because of an uncaught exception, this is the call stack where it was thrown
+   * (innermost frame first). It is only populated if the bytecode has a source map.
+   */
+  pub exception_stack_trace: Vec<StackTraceFrame>,
+
+  pub limits: CircuitVMLimits,
+
+  /** How operations on numbers known at compile time are computed. */
+  pub numeric_model: NumericModel,
+
+  /** The arena that signals are created in, so that `limits.signals` can be enforced. */
+  pub signal_arena: Option<Rc<RefCell<SignalArena>>>,
+
+  /**
+   * Tag new signals with the source of the instruction that created them (see `SignalArena::origin`)
+   * so that gates can be attributed to functions and lines. This requires `signal_arena`.
+   */
+  pub track_signal_origins: bool,
+
+  /** When `run` fails because of `limits` (or its `step_limit`), this explains why. */
+  pub limit_exceeded: Option<LimitExceeded>,
+
+  /** The number of times each conditional jump (by bytecode position) has forked. */
+  fork_sites: HashMap<usize, ForkSite>,
 }
 
 impl CircuitVM {
@@ -42,30 +126,23 @@ impl CircuitVM {
       alt_branch: None,
     };
 
-    let res = match step_limit {
-      Some(step_limit) => 'b: {
-        let mut step_count = 0;
-
-        while step_count < step_limit {
-          self.step()?;
-          step_count += 1;
+    let step_limit = step_limit.or(self.limits.steps);
+    let mut step_count = 0;
 
-          if self.branch.stack.is_empty() {
-            assert!(self.alt_branches.is_empty());
-            break 'b self.branch.frame_mut().get_call_result();
-          }
+    while !self.branch.stack.is_empty() {
+      if let Some(step_limit) = step_limit {
+        if step_count >= step_limit {
+          return Err(self.exceed_limit(format!("Step limit ({}) reached", step_limit)));
         }
-
-        return Err("step limit reached".to_internal_error());
       }
-      None => {
-        while !self.branch.stack.is_empty() {
-          self.step()?;
-        }
 
-        self.branch.frame_mut().get_call_result()
-      }
-    };
+      self.step()?;
+      step_count += 1;
+
+      self.check_limits()?;
+    }
+
+    let res = self.branch.frame_mut().get_call_result();
 
     let CallResult {
       return_,
@@ -81,7 +158,24 @@ impl CircuitVM {
     self.assert_current_branch_best();
     assert!(self.branch.alt_branch.is_none());
 
-    self.branch.step()?;
+    let pos = self
+      .branch
+      .frame
+      .as_any()
+      .downcast_ref::<BytecodeStackFrame>()
+      .map(|frame| frame.decoder.pos);
+
+    if self.track_signal_origins {
+      self.update_origin();
+    }
+
+    if let Err(uncaught) = self.branch.step(&self.numeric_model) {
+      self.discard_throwing_branch(uncaught)?;
+    }
+
+    if let (Some(pos), Some(_)) = (pos, &self.branch.alt_branch) {
+      self.record_fork(pos);
+    }
 
     if let Some(alt_branch) = take(&mut self.branch.alt_branch) {
       self.alt_branches.push(*alt_branch);
@@ -116,13 +210,13 @@ impl CircuitVM {
                   &current_frame.call_result.return_,
                   &alt_branch.flag,
                   &alt_frame.call_result.return_,
-                ),
+                )?,
                 this: arithmetic_merge(
                   &self.branch.flag,
                   &current_frame.call_result.this,
                   &alt_branch.flag,
                   &alt_frame.call_result.this,
-                ),
+                )?,
               });
 
               let mut new_frame = Rc::new(Box::new(new_frame) as Box<dyn StackFrameTrait>);
@@ -168,7 +262,7 @@ impl CircuitVM {
                   &current_frame.registers[i],
                   &alt_branch.flag,
                   &alt_frame.registers[i],
-                ));
+                )?);
               }
 
               current_frame.registers = new_registers;
@@ -193,6 +287,107 @@ impl CircuitVM {
     Ok(())
   }
 
+  fn update_origin(&mut self) {
+    let Some(signal_arena) = &self.signal_arena else {
+      return;
+    };
+
+    let origin = self
+      .branch
+      .frame
+      .as_any()
+      .downcast_ref::<BytecodeStackFrame>()
+      .and_then(|frame| {
+        frame
+          .decoder
+          .bytecode
+          .source_map
+          .stack_trace_frame(frame.decoder.pos)
+      });
+
+    let mut signal_arena = signal_arena.borrow_mut();
+
+    // Most steps stay in the same statement, so avoid reallocating in that case
+    if signal_arena.origin.as_deref() != origin.as_ref() {
+      signal_arena.origin = origin.map(Rc::new);
+    }
+  }
+
+  fn record_fork(&mut self, pos: usize) {
+    let branch = &self.branch;
+
+    let site = self.fork_sites.entry(pos).or_insert_with(|| ForkSite {
+      count: 0,
+      stack_trace: branch.stack_trace_at(pos),
+    });
+
+    site.count += 1;
+  }
+
+  fn check_limits(&mut self) -> Result<(), Val> {
+    if let Some(branch_limit) = self.limits.branches {
+      if self.alt_branches.len() > branch_limit {
+        return Err(self.exceed_limit(format!("Branch limit ({}) reached", branch_limit)));
+      }
+    }
+
+    if let (Some(signal_limit), Some(signal_arena)) = (self.limits.signals, &self.signal_arena) {
+      if signal_arena.borrow().count() > signal_limit {
+        return Err(self.exceed_limit(format!("Signal limit ({}) reached", signal_limit)));
+      }
+    }
+
+    Ok(())
+  }
+
+  /**
+   * Records the limit that was exceeded, blaming the jump that forked the most, and returns the
+   * error for `run`.
+   */
+  fn exceed_limit(&mut self, message: String) -> Val {
+    let worst_site = self
+      .fork_sites
+      .iter()
+      .max_by_key(|(pos, site)| (site.count, std::cmp::Reverse(**pos)))
+      .map(|(_, site)| site.clone());
+
+    let limit_exceeded = match worst_site {
+      Some(site) => LimitExceeded {
+        message: format!(
+          "{}. This conditional jump forked {} times, which usually means a loop condition \
+            depends on a signal, so the loop never ends during compilation.",
+          message, site.count,
+        ),
+        stack_trace: site.stack_trace,
+      },
+      None => LimitExceeded {
+        message,
+        stack_trace: self.branch.stack_trace(),
+      },
+    };
+
+    let error = limit_exceeded.message.clone().to_error();
+    self.limit_exceeded = Some(limit_exceeded);
+
+    error
+  }
+
+  fn discard_throwing_branch(&mut self, uncaught: UncaughtException) -> Result<(), Val> {
+    let error_flag = match &self.error_flag {
+      // If there are no other branches, then the program throws for every input
+      Some(error_flag) if !self.alt_branches.is_empty() => error_flag,
+      _ => {
+        self.exception_stack_trace = uncaught.stack_trace;
+        return Err(uncaught.exception);
+      }
+    };
+
+    self.error_flag = Some(op_or(error_flag, &self.branch.flag)?);
+    self.branch = self.alt_branches.pop().unwrap();
+
+    Ok(())
+  }
+
   fn set_branch(&mut self, mut new_branch: CircuitVMBranch) {
     std::mem::swap(&mut self.branch, &mut new_branch);
     self.alt_branches.push(new_branch);
@@ -3,7 +3,9 @@ use std::mem::take;
 use std::rc::Rc;
 
 use crate::internal_error_builtin::ToInternalError;
+use crate::numeric_model::NumericModel;
 use crate::operations::op_and;
+use crate::source_map::StackTraceFrame;
 use crate::vs_value::{ToVal, Val};
 use crate::{FirstStackFrame, FrameStepOk, StackFrame};
 
@@ -18,6 +20,13 @@ pub struct CircuitVMBranch {
   pub alt_branch: Option<Box<CircuitVMBranch>>,
 }
 
+pub struct UncaughtException {
+  pub exception: Val,
+
+  /** Where the exception was thrown, innermost frame first. */
+  pub stack_trace: Vec<StackTraceFrame>,
+}
+
 impl Default for CircuitVMBranch {
   fn default() -> Self {
     CircuitVMBranch {
@@ -34,8 +43,8 @@ impl CircuitVMBranch {
     Rc::make_mut(&mut self.frame)
   }
 
-  pub fn step(&mut self) -> Result<(), Val> {
-    let step_ok = match self.frame_mut().step() {
+  pub fn step(&mut self, numeric_model: &NumericModel) -> Result<(), UncaughtException> {
+    let step_ok = match self.frame_mut().step(numeric_model) {
       Ok(step_ok) => step_ok,
       Err(e) => return self.handle_exception(e),
     };
@@ -88,7 +97,11 @@ impl CircuitVMBranch {
     std::mem::swap(&mut self.frame, &mut old_frame);
   }
 
-  pub fn handle_exception(&mut self, mut exception: Val) -> Result<(), Val> {
+  pub fn handle_exception(&mut self, mut exception: Val) -> Result<(), UncaughtException> {
+    // This needs to be captured before unwinding, even though it's only needed if the exception
+    // isn't caught
+    let stack_trace = self.stack_trace();
+
     while !self.stack.is_empty() {
       if self.frame.can_catch_exception(&exception) {
         self.frame_mut().catch_exception(&mut exception);
@@ -96,13 +109,49 @@ impl CircuitVMBranch {
       }
 
       if self.stack.is_empty() {
-        return Err(exception);
+        break;
       }
 
       self.pop();
     }
 
-    Err(exception)
+    Err(UncaughtException {
+      exception,
+      stack_trace,
+    })
+  }
+
+  pub fn stack_trace(&self) -> Vec<StackTraceFrame> {
+    std::iter::once(&self.frame)
+      .chain(self.stack.iter().rev())
+      .filter_map(|frame| {
+        frame
+          .as_any()
+          .downcast_ref::<BytecodeStackFrame>()?
+          .stack_trace_frame()
+      })
+      .collect()
+  }
+
+  /**
+   * Like `stack_trace`, but using `pos` for the current frame instead of its decoder position.
+   */
+  pub fn stack_trace_at(&self, pos: usize) -> Vec<StackTraceFrame> {
+    let current = self
+      .frame
+      .as_any()
+      .downcast_ref::<BytecodeStackFrame>()
+      .and_then(|frame| frame.decoder.bytecode.source_map.stack_trace_frame(pos));
+
+    current
+      .into_iter()
+      .chain(self.stack.iter().rev().filter_map(|frame| {
+        frame
+          .as_any()
+          .downcast_ref::<BytecodeStackFrame>()?
+          .stack_trace_frame()
+      }))
+      .collect()
   }
 }
 
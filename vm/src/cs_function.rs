@@ -111,8 +111,8 @@ impl ValTrait for CsFunction {
     false
   }
 
-  fn is_truthy(&self) -> bool {
-    true
+  fn is_truthy(&self) -> Result<bool, Val> {
+    Ok(true)
   }
 
   fn is_nullish(&self) -> bool {
@@ -0,0 +1,158 @@
+use std::fmt;
+
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, ToPrimitive, Zero};
+
+use crate::{binary_op::BinaryOp, circuit::CircuitNumber, unary_op::UnaryOp};
+
+/**
+ * An element of the integers mod p, for evaluating circuits the way arithmetic MPC and ZK backends
+ * do (see `Circuit::field_modulus`).
+ *
+ * Arithmetic (`+`, `-`, `*`, `**` and negation) wraps at the modulus, and division multiplies by
+ * the inverse. Everything else uses the representative in `[0, p)`, so for example `-1 < 3` is
+ * false, because `-1` is `p - 1`. Bools are `0` and `1`.
+ *
+ * Dividing by an element without an inverse (only `0` when p is prime) gives `0`, and `x % 0` is
+ * `x` (as in `FixedWidthNumber`). Backends may disagree here, so the optimizer doesn't fold these.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldElement(pub BigUint);
+
+impl FieldElement {
+  /**
+   * Reduces an integer mod p. Negative integers become `p - |x|`.
+   */
+  pub fn from_bigint(x: &BigInt, modulus: &BigUint) -> Self {
+    let modulus = BigInt::from(modulus.clone());
+    let reduced = ((x % &modulus) + &modulus) % &modulus;
+
+    FieldElement(
+      reduced
+        .to_biguint()
+        .expect("Reduced value should be non-negative"),
+    )
+  }
+
+  /**
+   * Reads a bool, an integer, or a decimal string (for integers that JSON numbers can't hold).
+   */
+  pub fn from_input_json(x: &serde_json::Value, modulus: &BigUint) -> Option<Self> {
+    if let Some(x) = x.as_bool() {
+      return Some(FieldElement::from_bool(x));
+    }
+
+    let x = match x {
+      serde_json::Value::Number(x) => x.to_string().parse::<BigInt>().ok()?,
+      serde_json::Value::String(x) => x.parse::<BigInt>().ok()?,
+      _ => return None,
+    };
+
+    Some(FieldElement::from_bigint(&x, modulus))
+  }
+
+  /**
+   * A JSON number when it fits in a `u64`, otherwise a decimal string.
+   */
+  pub fn to_json(&self) -> serde_json::Value {
+    match self.0.to_u64() {
+      Some(x) => x.into(),
+      None => self.0.to_string().into(),
+    }
+  }
+
+  fn from_bool(x: bool) -> Self {
+    FieldElement(if x { BigUint::one() } else { BigUint::zero() })
+  }
+
+  fn is_true(&self) -> bool {
+    !self.0.is_zero()
+  }
+
+  fn shift_amount(&self) -> usize {
+    self.0.to_usize().unwrap_or(usize::MAX)
+  }
+}
+
+impl fmt::Display for FieldElement {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+/**
+ * Parses a modulus given in decimal or as `0x`-prefixed hex.
+ *
+ * Division needs inverses, which only exist for every non-zero element when the modulus is prime,
+ * but that isn't checked here.
+ */
+pub fn parse_field_modulus(s: &str) -> Result<BigUint, String> {
+  let modulus = match s.strip_prefix("0x") {
+    Some(hex) => BigUint::parse_bytes(hex.as_bytes(), 16),
+    None => BigUint::parse_bytes(s.as_bytes(), 10),
+  };
+
+  match modulus {
+    Some(modulus) if modulus > BigUint::one() => Ok(modulus),
+    _ => Err(format!("Invalid field modulus: {}", s)),
+  }
+}
+
+impl CircuitNumber for FieldElement {
+  type Params = BigUint;
+
+  fn zero(_modulus: &BigUint) -> Self {
+    FieldElement(BigUint::zero())
+  }
+
+  fn from_json(x: &serde_json::Value, modulus: &BigUint) -> Self {
+    FieldElement::from_input_json(x, modulus)
+      .unwrap_or_else(|| panic!("Couldn't convert to FieldElement: {}", x))
+  }
+
+  fn unary_op(op: UnaryOp, input: &Self, modulus: &BigUint) -> Self {
+    match op {
+      UnaryOp::Plus => input.clone(),
+      UnaryOp::Minus => FieldElement((modulus - &input.0) % modulus),
+      UnaryOp::Not => FieldElement::from_bool(!input.is_true()),
+
+      // ~x == -x - 1, as in two's complement
+      UnaryOp::BitNot => FieldElement((modulus - &input.0 + modulus - 1u32) % modulus),
+    }
+  }
+
+  fn binary_op(op: BinaryOp, left: &Self, right: &Self, modulus: &BigUint) -> Self {
+    let (l, r) = (&left.0, &right.0);
+
+    match op {
+      BinaryOp::Plus => FieldElement((l + r) % modulus),
+      BinaryOp::Minus => FieldElement((l + modulus - r) % modulus),
+      BinaryOp::Mul => FieldElement((l * r) % modulus),
+      BinaryOp::Div => match r.modinv(modulus) {
+        Some(inverse) => FieldElement((l * inverse) % modulus),
+        None => FieldElement(BigUint::zero()),
+      },
+      BinaryOp::Mod if r.is_zero() => left.clone(),
+      BinaryOp::Mod => FieldElement(l % r),
+      BinaryOp::Exp => FieldElement(l.modpow(r, modulus)),
+      BinaryOp::LooseEq | BinaryOp::Eq => FieldElement::from_bool(l == r),
+      BinaryOp::LooseNe | BinaryOp::Ne => FieldElement::from_bool(l != r),
+      BinaryOp::And => FieldElement::from_bool(left.is_true() && right.is_true()),
+      BinaryOp::Or => FieldElement::from_bool(left.is_true() || right.is_true()),
+      BinaryOp::Less => FieldElement::from_bool(l < r),
+      BinaryOp::LessEq => FieldElement::from_bool(l <= r),
+      BinaryOp::Greater => FieldElement::from_bool(l > r),
+      BinaryOp::GreaterEq => FieldElement::from_bool(l >= r),
+      BinaryOp::BitAnd => FieldElement((l & r) % modulus),
+      BinaryOp::BitOr => FieldElement((l | r) % modulus),
+      BinaryOp::BitXor => FieldElement((l ^ r) % modulus),
+      BinaryOp::LeftShift => {
+        let shift = BigUint::from(2u32).modpow(r, modulus);
+        FieldElement((l * shift) % modulus)
+      }
+      BinaryOp::RightShift | BinaryOp::RightShiftUnsigned => {
+        FieldElement(l >> right.shift_amount())
+      }
+    }
+  }
+}
@@ -2,6 +2,7 @@ use std::any::Any;
 
 use crate::stack_frame::StackFrame;
 
+use super::numeric_model::NumericModel;
 use super::stack_frame::{CallResult, FrameStepResult, StackFrameTrait};
 use super::vs_value::Val;
 
@@ -36,7 +37,7 @@ impl StackFrameTrait for FirstStackFrame {
     panic!("Not appropriate for FirstStackFrame");
   }
 
-  fn step(&mut self) -> FrameStepResult {
+  fn step(&mut self, _numeric_model: &NumericModel) -> FrameStepResult {
     panic!("Not appropriate for FirstStackFrame");
   }
 
@@ -0,0 +1,218 @@
+use std::fmt;
+
+use crate::{binary_op::BinaryOp, circuit::CircuitNumber, unary_op::UnaryOp};
+
+/**
+ * A value in a circuit evaluated at a fixed bit width (e.g. 16 for `--boolify-width 16`), giving
+ * the same results as the boolean circuit from `to_boolean_bristol` at that width.
+ *
+ * Numbers wrap at `2^width`. Like the boolean circuit, values derived from signed inputs
+ * (`summon.int(bits)`) are signed (see `infer_signedness`):
+ * - comparisons use two's complement
+ * - `>>` sign-extends (`>>>` always fills with zeros)
+ * - division truncates towards zero, and the remainder takes the sign of the dividend
+ *
+ * Shift amounts use the low `ceil(log2(width))` bits, so they are taken modulo the width when it
+ * is a power of two. Dividing by zero gives a quotient of all ones and leaves the dividend as the
+ * remainder. (The boolean circuit may give fewer ones when it has narrowed the wire.)
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FixedWidthNumber {
+  Number { value: u64, signed: bool },
+  Bool(bool),
+}
+
+impl FixedWidthNumber {
+  /**
+   * Reads an input value. Negative numbers are encoded in two's complement, and `signed` should
+   * match the input's declaration.
+   */
+  pub fn from_input_json(x: &serde_json::Value, signed: bool, width: usize) -> Option<Self> {
+    if let Some(x) = x.as_bool() {
+      return Some(FixedWidthNumber::Bool(x));
+    }
+
+    let value = match (x.as_u64(), x.as_i64()) {
+      (Some(x), _) => x,
+      (_, Some(x)) => x as u64,
+      _ => return None,
+    };
+
+    Some(FixedWidthNumber::Number {
+      value: value & mask(width),
+      signed,
+    })
+  }
+
+  /**
+   * The raw bits of the value, as they appear in the boolean circuit.
+   */
+  pub fn bits(&self) -> u64 {
+    match self {
+      FixedWidthNumber::Number { value, .. } => *value,
+      FixedWidthNumber::Bool(x) => *x as u64,
+    }
+  }
+
+  /**
+   * Signed numbers are written as negative numbers when their sign bit is set.
+   */
+  pub fn to_json(&self, width: usize) -> serde_json::Value {
+    match self {
+      FixedWidthNumber::Number {
+        value,
+        signed: true,
+      } => sign_extend(*value, width).into(),
+      FixedWidthNumber::Number { value, .. } => (*value).into(),
+      FixedWidthNumber::Bool(x) => (*x).into(),
+    }
+  }
+
+  fn is_signed(&self) -> bool {
+    matches!(self, FixedWidthNumber::Number { signed: true, .. })
+  }
+}
+
+impl fmt::Display for FixedWidthNumber {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      FixedWidthNumber::Number { value, .. } => write!(f, "{}", value),
+      FixedWidthNumber::Bool(x) => write!(f, "{}", x),
+    }
+  }
+}
+
+impl CircuitNumber for FixedWidthNumber {
+  /** The width in bits, from 1 to 64. */
+  type Params = usize;
+
+  fn zero(_width: &usize) -> Self {
+    FixedWidthNumber::Number {
+      value: 0,
+      signed: false,
+    }
+  }
+
+  fn from_json(x: &serde_json::Value, width: &usize) -> Self {
+    // Constants are unsigned (negative constants are already in two's complement)
+    FixedWidthNumber::from_input_json(x, false, *width)
+      .unwrap_or_else(|| panic!("Couldn't convert to FixedWidthNumber: {}", x))
+  }
+
+  fn unary_op(op: UnaryOp, input: &Self, width: &usize) -> Self {
+    let (width, x) = (*width, input.bits());
+
+    let number = |value: u64| FixedWidthNumber::Number {
+      value: value & mask(width),
+      signed: input.is_signed(),
+    };
+
+    match op {
+      UnaryOp::Plus => input.clone(),
+      UnaryOp::Minus => number(x.wrapping_neg()),
+      UnaryOp::Not => FixedWidthNumber::Bool(x == 0),
+      UnaryOp::BitNot => number(!x),
+    }
+  }
+
+  fn binary_op(op: BinaryOp, left: &Self, right: &Self, width: &usize) -> Self {
+    use BinaryOp::*;
+
+    let (width, l, r) = (*width, left.bits(), right.bits());
+    let signed = left.is_signed() || right.is_signed();
+
+    let number = |value: u64, signed: bool| FixedWidthNumber::Number {
+      value: value & mask(width),
+      signed,
+    };
+
+    let compare = || {
+      if signed {
+        sign_extend(l, width).cmp(&sign_extend(r, width))
+      } else {
+        l.cmp(&r)
+      }
+    };
+
+    let shift_amount = r & mask(shift_stages(width));
+
+    match op {
+      Plus => number(l.wrapping_add(r), signed),
+      Minus => number(l.wrapping_sub(r), signed),
+      Mul => number(l.wrapping_mul(r), signed),
+      Div | Mod if signed => {
+        // Like comparisons, both operands are treated as signed
+        let l_negative = sign_extend(l, width) < 0;
+        let r_negative = sign_extend(r, width) < 0;
+
+        let l_abs = if l_negative { l.wrapping_neg() } else { l };
+        let r_abs = if r_negative { r.wrapping_neg() } else { r };
+
+        let (quotient, remainder) = div_mod_unsigned(l_abs & mask(width), r_abs & mask(width));
+
+        match op {
+          Div if l_negative != r_negative => number(quotient.wrapping_neg(), true),
+          Div => number(quotient, true),
+          _ if l_negative => number(remainder.wrapping_neg(), true),
+          _ => number(remainder, true),
+        }
+      }
+      Div => number(div_mod_unsigned(l, r).0, false),
+      Mod => number(div_mod_unsigned(l, r).1, false),
+      Exp => number(l.wrapping_pow(r as u32), signed),
+      LooseEq | Eq => FixedWidthNumber::Bool(l == r),
+      LooseNe | Ne => FixedWidthNumber::Bool(l != r),
+      And => FixedWidthNumber::Bool(l != 0 && r != 0),
+      Or => FixedWidthNumber::Bool(l != 0 || r != 0),
+      Less => FixedWidthNumber::Bool(compare().is_lt()),
+      LessEq => FixedWidthNumber::Bool(compare().is_le()),
+      Greater => FixedWidthNumber::Bool(compare().is_gt()),
+      GreaterEq => FixedWidthNumber::Bool(compare().is_ge()),
+      BitAnd => number(l & r, signed),
+      BitOr => number(l | r, signed),
+      BitXor => number(l ^ r, signed),
+      LeftShift => number(
+        l.checked_shl(shift_amount as u32).unwrap_or(0),
+        left.is_signed(),
+      ),
+      RightShift if left.is_signed() => {
+        number((sign_extend(l, width) >> shift_amount) as u64, true)
+      }
+      RightShift | RightShiftUnsigned => number(
+        l.checked_shr(shift_amount as u32).unwrap_or(0),
+        left.is_signed(),
+      ),
+    }
+  }
+}
+
+fn mask(width: usize) -> u64 {
+  if width >= 64 {
+    u64::MAX
+  } else {
+    (1u64 << width) - 1
+  }
+}
+
+fn sign_extend(value: u64, width: usize) -> i64 {
+  let unused_bits = 64 - width.min(64) as u32;
+  ((value << unused_bits) as i64) >> unused_bits
+}
+
+/**
+ * The number of low bits of a shift amount that the boolean circuit's barrel shifter uses.
+ */
+fn shift_stages(width: usize) -> usize {
+  (usize::BITS - (width.max(1) - 1).leading_zeros()) as usize
+}
+
+/**
+ * Unsigned division, where dividing by zero gives a quotient of all ones (to be masked by the
+ * caller) and leaves the dividend as the remainder, like restoring division does.
+ */
+fn div_mod_unsigned(l: u64, r: u64) -> (u64, u64) {
+  match r {
+    0 => (u64::MAX, l),
+    _ => (l / r, l % r),
+  }
+}
@@ -9,6 +9,7 @@ use crate::{
   internal_error_builtin::ToInternalError,
   native_frame_function::NativeFrameFunction,
   native_function::ThisWrapper,
+  numeric_model::NumericModel,
   type_error_builtin::ToTypeError,
   vs_array::VsArray,
   vs_class::VsClass,
@@ -49,8 +50,8 @@ impl ValTrait for Generator {
     false
   }
 
-  fn is_truthy(&self) -> bool {
-    true
+  fn is_truthy(&self) -> Result<bool, Val> {
+    Ok(true)
   }
 
   fn is_nullish(&self) -> bool {
@@ -163,8 +164,8 @@ impl StackFrameTrait for GeneratorFrame {
     panic!("TODO: results of yield expressions")
   }
 
-  fn step(&mut self) -> FrameStepResult {
-    let fsr = self.generator.frame.step();
+  fn step(&mut self, numeric_model: &NumericModel) -> FrameStepResult {
+    let fsr = self.generator.frame.step(numeric_model);
 
     match fsr {
       Err(_) => fsr, // TODO: Stack unwind internal stack first
@@ -276,13 +277,13 @@ impl StackFrameTrait for YieldStarFrame {
     panic!("Not appropriate for YieldStarFrame")
   }
 
-  fn step(&mut self) -> FrameStepResult {
+  fn step(&mut self, _numeric_model: &NumericModel) -> FrameStepResult {
     let iter_result = take(&mut self.iter_result);
 
     if let Some(iter_result) = iter_result {
       let value = iter_result.sub(&"value".to_val())?; // TODO: mutable subscript to avoid cloning
 
-      return match iter_result.sub(&"done".to_val())?.is_truthy() {
+      return match iter_result.sub(&"done".to_val())?.is_truthy()? {
         false => Ok(FrameStepOk::Yield(value)),
         true => Ok(FrameStepOk::Pop(CallResult {
           return_: value,
@@ -314,7 +315,7 @@ impl StackFrameTrait for YieldStarFrame {
             let iter_result = native_fn(ThisWrapper::new(false, iterator), vec![])?;
             let value = iter_result.sub(&"value".to_val())?;
 
-            match iter_result.sub(&"done".to_val())?.is_truthy() {
+            match iter_result.sub(&"done".to_val())?.is_truthy()? {
               false => Ok(FrameStepOk::Yield(value)),
               true => Ok(FrameStepOk::Pop(CallResult {
                 return_: value,
@@ -34,8 +34,8 @@ impl ValTrait for JsxElement {
     false
   }
 
-  fn is_truthy(&self) -> bool {
-    true
+  fn is_truthy(&self) -> Result<bool, Val> {
+    Ok(true)
   }
 
   fn is_nullish(&self) -> bool {
@@ -150,7 +150,7 @@ fn write_attributes(
 ) -> fmt::Result {
   for (key, val) in attrs {
     if key == "checked" {
-      if val.is_truthy() { write!(f, " checked")? }
+      if let Ok(true) = val.is_truthy() { write!(f, " checked")? }
 
       continue;
     }
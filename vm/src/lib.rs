@@ -3,6 +3,8 @@ mod array_higher_functions;
 mod array_methods;
 mod bigint_methods;
 pub mod binary_op;
+pub mod bit_width;
+pub mod boolean_circuit;
 mod bristol_op_strings;
 mod builtins;
 mod bytecode;
@@ -11,24 +13,31 @@ mod bytecode_stack_frame;
 pub mod cat_stack_frame;
 pub mod circuit;
 pub mod circuit_builder;
+pub mod circuit_optimizer;
 pub mod circuit_signal;
 pub mod circuit_vm;
 mod circuit_vm_branch;
 mod copy_counter;
 pub mod cs_function;
+pub mod field_element;
 mod first_stack_frame;
+pub mod fixed_width;
 mod generator;
 mod helpers;
-pub mod id_generator;
 mod iteration;
 pub mod jsx_element;
 mod make_generator_frame;
 pub mod native_frame_function;
 pub mod native_function;
 mod number_methods;
+pub mod numeric_model;
 pub mod operations;
+pub mod signal_arena;
+mod signal_index;
+pub mod source_map;
 mod stack_frame;
 mod string_methods;
+pub mod summon_type;
 mod todo_fn;
 pub mod unary_op;
 pub mod val_dynamic_downcast;
@@ -3,6 +3,7 @@ use std::mem::take;
 use crate::{
   bytecode_stack_frame::BytecodeStackFrame,
   generator::Generator,
+  numeric_model::NumericModel,
   vs_value::{ToDynamicVal, Val},
   CallResult, FrameStepOk, FrameStepResult, StackFrame, StackFrameTrait,
 };
@@ -35,7 +36,7 @@ impl StackFrameTrait for MakeGeneratorFrame {
     self.frame_mut().write_param(param);
   }
 
-  fn step(&mut self) -> FrameStepResult {
+  fn step(&mut self, _numeric_model: &NumericModel) -> FrameStepResult {
     Ok(FrameStepOk::Pop(CallResult {
       return_: Generator::new(Box::new(self.take_frame())).to_dynamic_val(),
       this: Val::Undefined,
@@ -55,8 +55,8 @@ impl ValTrait for NativeFunction {
   fn is_primitive(&self) -> bool {
     false
   }
-  fn is_truthy(&self) -> bool {
-    true
+  fn is_truthy(&self) -> Result<bool, Val> {
+    Ok(true)
   }
   fn is_nullish(&self) -> bool {
     false
@@ -0,0 +1,220 @@
+use num_bigint::{BigInt, BigUint};
+use num_traits::{FromPrimitive, ToPrimitive, Zero};
+
+use crate::{
+  binary_op::BinaryOp,
+  builtins::range_error_builtin::ToRangeError,
+  circuit::CircuitNumber,
+  field_element::FieldElement,
+  fixed_width::FixedWidthNumber,
+  summon_type::MAX_BITS,
+  unary_op::UnaryOp,
+  vs_value::{ToVal, Val},
+};
+
+/**
+ * How operations on numbers that are known at compile time are computed.
+ *
+ * By default these follow JavaScript (`f64`, with 32-bit bitwise operations), while the circuit
+ * computes with integers, so a value can change depending on whether it happens to be known at
+ * compile time. The other models compute the same way the circuit does:
+ * - `Wrapping { width: 64, signed: false }` matches `NumberOrBool` (the default evaluation)
+ * - `Wrapping` in general matches `FixedWidthNumber` (boolean circuits at that width)
+ * - `Field` matches `FieldElement`
+ *
+ * Values are still stored as `f64`, so results are kept in the range where they are exact by using
+ * the negative representative when needed (e.g. `2^64 - 1` is stored as `-1` in `uint64`, which
+ * is the same constant in the circuit). Results that can't be stored exactly are a range error.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum NumericModel {
+  #[default]
+  Float64,
+  Wrapping {
+    width: usize,
+    signed: bool,
+  },
+  Field(BigUint),
+}
+
+/**
+ * Parses `f64`, `uintN`, `intN` (e.g. `uint64`, `int32`) or `field`, which uses `field_modulus`.
+ */
+pub fn parse_numeric_model(
+  s: &str,
+  field_modulus: Option<&BigUint>,
+) -> Result<NumericModel, String> {
+  let wrapping = |bits: &str, signed: bool| match bits.parse::<usize>() {
+    Ok(width) if (1..=MAX_BITS).contains(&width) => Ok(NumericModel::Wrapping { width, signed }),
+    _ => Err(format!("Invalid numeric model: {}", s)),
+  };
+
+  match s {
+    "f64" => Ok(NumericModel::Float64),
+    "field" => match field_modulus {
+      Some(modulus) => Ok(NumericModel::Field(modulus.clone())),
+      None => Err("The field numeric model requires a field modulus".to_string()),
+    },
+    _ => match (s.strip_prefix("uint"), s.strip_prefix("int")) {
+      (Some(bits), _) => wrapping(bits, false),
+      (_, Some(bits)) => wrapping(bits, true),
+      _ => Err(format!("Invalid numeric model: {}", s)),
+    },
+  }
+}
+
+/**
+ * Computes `op` with `model`, if it isn't `Float64` and both operands are integers. Otherwise
+ * returns `None`, and the JavaScript semantics apply.
+ */
+pub fn model_binary_op(
+  model: &NumericModel,
+  op: BinaryOp,
+  left: &Val,
+  right: &Val,
+) -> Option<Result<Val, Val>> {
+  use BinaryOp::*;
+
+  if *model == NumericModel::Float64 {
+    return None;
+  }
+
+  if !matches!(
+    op,
+    Plus
+      | Minus
+      | Mul
+      | Div
+      | Mod
+      | Exp
+      | Less
+      | LessEq
+      | Greater
+      | GreaterEq
+      | BitAnd
+      | BitOr
+      | BitXor
+      | LeftShift
+      | RightShift
+      | RightShiftUnsigned
+  ) {
+    return None;
+  }
+
+  let (left, right) = (integer(left)?, integer(right)?);
+
+  match model {
+    NumericModel::Float64 => None,
+    NumericModel::Wrapping { width, signed } => {
+      let left = to_fixed_width(left, *signed, *width);
+      let right = to_fixed_width(right, *signed, *width);
+
+      if matches!(op, Div | Mod) && right.bits() == 0 {
+        return Some(Err("Division by zero".to_range_error()));
+      }
+
+      Some(from_fixed_width(
+        &FixedWidthNumber::binary_op(op, &left, &right, width),
+        *width,
+      ))
+    }
+    NumericModel::Field(modulus) => {
+      let left = FieldElement::from_bigint(&left, modulus);
+      let right = FieldElement::from_bigint(&right, modulus);
+
+      let invertible = || right.0.modinv(modulus).is_some();
+
+      if (op == Div && !invertible()) || (op == Mod && right.0.is_zero()) {
+        return Some(Err("Division by zero".to_range_error()));
+      }
+
+      let res = FieldElement::binary_op(op, &left, &right, modulus);
+
+      Some(match op {
+        Less | LessEq | Greater | GreaterEq => Ok((!res.0.is_zero()).to_val()),
+        _ => from_field_element(&res, modulus),
+      })
+    }
+  }
+}
+
+/**
+ * Like `model_binary_op`, for `-x` and `~x`.
+ */
+pub fn model_unary_op(model: &NumericModel, op: UnaryOp, input: &Val) -> Option<Result<Val, Val>> {
+  if *model == NumericModel::Float64 || !matches!(op, UnaryOp::Minus | UnaryOp::BitNot) {
+    return None;
+  }
+
+  let input = integer(input)?;
+
+  match model {
+    NumericModel::Float64 => None,
+    NumericModel::Wrapping { width, signed } => Some(from_fixed_width(
+      &FixedWidthNumber::unary_op(op, &to_fixed_width(input, *signed, *width), width),
+      *width,
+    )),
+    NumericModel::Field(modulus) => Some(from_field_element(
+      &FieldElement::unary_op(op, &FieldElement::from_bigint(&input, modulus), modulus),
+      modulus,
+    )),
+  }
+}
+
+fn integer(val: &Val) -> Option<BigInt> {
+  match val {
+    Val::Number(x) if x.fract() == 0.0 => BigInt::from_f64(*x),
+    _ => None,
+  }
+}
+
+fn to_fixed_width(x: BigInt, signed: bool, width: usize) -> FixedWidthNumber {
+  // The low 64 bits, in two's complement
+  let bits = (x & BigInt::from(u64::MAX))
+    .to_u64()
+    .expect("Should fit in 64 bits");
+
+  FixedWidthNumber::from_input_json(&bits.into(), signed, width).expect("Should be a number")
+}
+
+fn from_fixed_width(x: &FixedWidthNumber, width: usize) -> Result<Val, Val> {
+  match x {
+    FixedWidthNumber::Bool(x) => Ok(x.to_val()),
+    FixedWidthNumber::Number { value, signed } => {
+      // Signed numbers are negative when their sign bit is set
+      let value = match x.to_json(width).as_i64() {
+        Some(signed_value) if *signed => BigInt::from(signed_value),
+        _ => BigInt::from(*value),
+      };
+
+      let modulus = BigInt::from(1u8) << width;
+      exact_number(&value, &modulus)
+    }
+  }
+}
+
+fn from_field_element(x: &FieldElement, modulus: &BigUint) -> Result<Val, Val> {
+  exact_number(&BigInt::from(x.0.clone()), &BigInt::from(modulus.clone()))
+}
+
+/**
+ * The number `value`, or `value - modulus` (the same value in the model) if only that is exact as
+ * an `f64`.
+ */
+fn exact_number(value: &BigInt, modulus: &BigInt) -> Result<Val, Val> {
+  let max_exact = BigInt::from(1u64 << f64::MANTISSA_DIGITS);
+
+  for candidate in [value.clone(), value - modulus] {
+    if candidate.magnitude() <= max_exact.magnitude() {
+      return Ok(candidate.to_f64().expect("Should be exact").to_val());
+    }
+  }
+
+  Err(
+    format!(
+      "Compile-time result {} can't be stored exactly as a number",
+      value
+    )
+    .to_range_error(),
+  )
+}
@@ -15,9 +15,11 @@ use crate::binary_op::BinaryOp;
 use crate::builtins::internal_error_builtin::ToInternalError;
 use crate::builtins::range_error_builtin::ToRangeError;
 use crate::builtins::type_error_builtin::ToTypeError;
+use crate::circuit_signal::is_signal;
 use crate::native_function::native_fn;
 use crate::native_function::NativeFunction;
 use crate::number_methods::op_sub_number;
+use crate::signal_index::op_submov_array_signal;
 use crate::string_methods::op_sub_string;
 use crate::unary_op::UnaryOp;
 use crate::vs_value::ToVal;
@@ -257,7 +259,7 @@ pub fn op_eq_impl(left: &Val, right: &Val) -> Result<bool, Val> {
       (Some(left_hash), Some(right_hash)) => left_hash == right_hash,
     },
     _ => {
-      if left.is_truthy() != right.is_truthy() {
+      if left.is_truthy()? != right.is_truthy()? {
         return Ok(false);
       }
 
@@ -437,7 +439,7 @@ pub fn op_and(left: &Val, right: &Val) -> Result<Val, Val> {
     return res;
   }
 
-  let truthy = left.is_truthy();
+  let truthy = left.is_truthy()?;
 
   Ok((if truthy { right } else { left }).clone())
 }
@@ -447,7 +449,7 @@ pub fn op_or(left: &Val, right: &Val) -> Result<Val, Val> {
     return res;
   }
 
-  let truthy = left.is_truthy();
+  let truthy = left.is_truthy()?;
 
   Ok((if truthy { left } else { right }).clone())
 }
@@ -457,7 +459,7 @@ pub fn op_not(input: &Val) -> Result<Val, Val> {
     return res;
   }
 
-  Ok(Val::Bool(!input.is_truthy()))
+  Ok(Val::Bool(!input.is_truthy()?))
 }
 
 pub fn op_less(left: &Val, right: &Val) -> Result<Val, Val> {
@@ -743,6 +745,10 @@ pub fn op_submov(target: &mut Val, subscript: &Val, value: Val) -> Result<(), Va
     Val::Symbol(_) => Err("Cannot assign to subscript of symbol".to_type_error()),
     Val::String(_) => Err("Cannot assign to subscript of string".to_type_error()),
     Val::Array(array_data) => {
+      if is_signal(subscript) {
+        return op_submov_array_signal(Rc::make_mut(array_data), subscript, value);
+      }
+
       let subscript_index = match subscript.to_index() {
         // TODO: Internal errors
         None => return Err("TODO: non-uint array subscript assignment".to_type_error()),
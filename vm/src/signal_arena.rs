@@ -0,0 +1,124 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{circuit_signal::CircuitSignalData, source_map::StackTraceFrame};
+
+/**
+ * The signals of a compile session, indexed by `CircuitSignal::id`.
+ *
+ * Signals refer to their operands by id, so the signal graph is a flat list rather than a tree of
+ * `Rc`s. An operand is always created before the signals that use it, which means ids are in
+ * dependency order, and nothing needs to recurse to traverse (or drop) the graph.
+ *
+ * The arena only grows: a signal's node and its `interned` entry are kept until the arena is
+ * dropped, even if nothing uses the signal anymore (e.g. the values of a discarded branch), and
+ * each operation's data is stored twice (in its node and as its key in `interned`). That comes to
+ * about 150 bytes per signal, which `CircuitVMLimits::signals` bounds.
+ */
+#[derive(Default)]
+pub struct SignalArena {
+  nodes: Vec<SignalNode>,
+
+  /** The id of each operation's signal, so that repeating a computation reuses it. */
+  interned: HashMap<CircuitSignalData, usize>,
+
+  /**
+   * Where the instruction currently being run came from. New signals are tagged with this (see
+   * `CircuitVM::track_signal_origins`).
+   */
+  pub origin: Option<Rc<StackTraceFrame>>,
+}
+
+pub struct SignalNode {
+  pub data: CircuitSignalData,
+
+  /** The source of the instruction that created this signal, if it was tracked. */
+  pub origin: Option<Rc<StackTraceFrame>>,
+}
+
+impl SignalArena {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /**
+   * The id of the signal computed by `data`. This is a new signal (with the current origin) unless
+   * an identical operation was already added. Inputs are always new.
+   */
+  pub fn add(&mut self, data: CircuitSignalData) -> usize {
+    let is_input = matches!(data, CircuitSignalData::Input);
+
+    if !is_input {
+      if let Some(id) = self.interned.get(&data) {
+        return *id;
+      }
+    }
+
+    let id = self.nodes.len();
+
+    self.nodes.push(SignalNode {
+      data,
+      origin: self.origin.clone(),
+    });
+
+    if !is_input {
+      self.interned.insert(data, id);
+    }
+
+    id
+  }
+
+  pub fn node(&self, id: usize) -> &SignalNode {
+    &self.nodes[id]
+  }
+
+  /** The number of signals created so far. */
+  pub fn count(&self) -> usize {
+    self.nodes.len()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{cell::RefCell, rc::Rc};
+
+  use super::*;
+  use crate::{
+    circuit_builder::CircuitBuilder,
+    circuit_signal::CircuitSignal,
+    operations::op_plus,
+    vs_value::{ToDynamicVal, ToVal, VsType},
+  };
+
+  /**
+   * `x = x + 1` a million times makes a chain as deep as the loop, which has to be built into a
+   * circuit and dropped without recursing.
+   */
+  #[test]
+  fn deep_chain() {
+    const STEPS: usize = 1_000_000;
+
+    let arena = Rc::new(RefCell::new(SignalArena::new()));
+    let input = CircuitSignal::input(&arena, VsType::Number).to_dynamic_val();
+
+    let mut x = input.clone();
+
+    for _ in 0..STEPS {
+      x = op_plus(&x, &1.0.to_val()).unwrap();
+    }
+
+    assert_eq!(arena.borrow().count(), 1 + STEPS);
+
+    let mut builder = CircuitBuilder::default();
+    builder.include_inputs(&[0]);
+    let outputs = builder
+      .include_outputs(&vec![("x".to_string(), x)])
+      .unwrap();
+
+    assert_eq!(builder.gates.len(), STEPS);
+    assert_eq!(outputs, vec![("x".to_string(), builder.wire_count - 1)]);
+
+    drop(builder);
+    drop(input);
+    drop(arena);
+  }
+}
@@ -0,0 +1,248 @@
+use crate::{
+  arithmetic_merge::arithmetic_merge,
+  operations::{op_not, op_triple_eq},
+  type_error_builtin::ToTypeError,
+  vs_array::VsArray,
+  vs_object::VsObject,
+  vs_value::{ToVal, Val, VsType},
+  ValTrait,
+};
+
+/**
+ * Reads `array[index]` where `index` is a signal.
+ *
+ * Since the index isn't known at compile time, this becomes a multiplexer over every element:
+ *
+ * ```ts
+ * res = zero;
+ * res = index === 0 ? array[0] : res;
+ * res = index === 1 ? array[1] : res;
+ * // ...
+ * ```
+ *
+ * The elements are combined using `arithmetic_merge`, so they can be numbers, bools, or nested
+ * structures of these, as long as they all have the same shape.
+ *
+ * When the index is out of range (including negative and non-integer indexes), the result is the
+ * zero value for that shape: `0` for numbers, `false` for bools, and nested structures of these
+ * for arrays and objects. This stands in for the `undefined` that JavaScript would give, which
+ * can't be represented in the circuit. Reading from an empty array gives `undefined`.
+ *
+ * Elements with different shapes (e.g. `[1, true]`, or objects with different keys) are a type
+ * error, as are elements that aren't numbers or bools at all (e.g. strings).
+ */
+pub fn op_sub_array_signal(array: &VsArray, index: &Val) -> Result<Val, Val> {
+  if index.typeof_() != VsType::Number {
+    return Err("Array index signal must be a number".to_type_error());
+  }
+
+  let Some(first) = array.elements.first() else {
+    return Ok(Val::Undefined);
+  };
+
+  for element in &array.elements {
+    if let Err(difference) = check_same_shape(first, element) {
+      return Err(
+        format!(
+          "Can't index {} with a signal because it contains {}",
+          describe(&array.clone().to_val()),
+          difference,
+        )
+        .to_type_error(),
+      );
+    }
+  }
+
+  let mut res = zero_like(first);
+
+  for (i, element) in array.elements.iter().enumerate() {
+    let flag = op_triple_eq(index, &(i as f64).to_val())?;
+
+    res = match flag {
+      Val::Bool(true) => element.clone(),
+      Val::Bool(false) => res,
+      _ => arithmetic_merge(&flag, element, &op_not(&flag)?, &res)?,
+    };
+  }
+
+  Ok(res)
+}
+
+/**
+ * Writes `array[index] = value` where `index` is a signal.
+ *
+ * Every element becomes a conditional update:
+ *
+ * ```ts
+ * array[i] = index === i ? value : array[i];
+ * ```
+ *
+ * The value must have the same shape as the elements it might replace (see `arithmetic_merge`),
+ * otherwise this is a type error and the array is unchanged. When the index is out of range, the
+ * array is unchanged. Unlike writes with a regular index, the array is never extended.
+ */
+pub fn op_submov_array_signal(array: &mut VsArray, index: &Val, value: Val) -> Result<(), Val> {
+  if index.typeof_() != VsType::Number {
+    return Err("Array index signal must be a number".to_type_error());
+  }
+
+  for element in &array.elements {
+    if let Err(difference) = check_same_shape(element, &value) {
+      return Err(
+        format!(
+          "Can't assign {} to an element of {} with a signal index because it would merge {}",
+          describe(&value),
+          describe(&array.clone().to_val()),
+          difference,
+        )
+        .to_type_error(),
+      );
+    }
+  }
+
+  for (i, element) in array.elements.iter_mut().enumerate() {
+    let flag = op_triple_eq(index, &(i as f64).to_val())?;
+
+    match flag {
+      Val::Bool(true) => *element = value.clone(),
+      Val::Bool(false) => {}
+      _ => *element = arithmetic_merge(&flag, &value, &op_not(&flag)?, element)?,
+    };
+  }
+
+  Ok(())
+}
+
+/**
+ * Checks that `val` can be merged with `reference` (see `arithmetic_merge`): both numbers, both
+ * bools, or arrays/objects of these with the same shape. Otherwise, describes the first difference,
+ * e.g. `a number and a bool`.
+ */
+fn check_same_shape(reference: &Val, val: &Val) -> Result<(), String> {
+  match (reference, val) {
+    (Val::Array(reference), Val::Array(val)) => {
+      if reference.elements.len() != val.elements.len() {
+        return Err(format!(
+          "arrays of length {} and {}",
+          reference.elements.len(),
+          val.elements.len()
+        ));
+      }
+
+      for (reference, val) in reference.elements.iter().zip(val.elements.iter()) {
+        check_same_shape(reference, val)?;
+      }
+
+      Ok(())
+    }
+    (Val::Object(reference), Val::Object(val)) => {
+      if !reference.string_map.keys().eq(val.string_map.keys())
+        || !reference.symbol_map.keys().eq(val.symbol_map.keys())
+      {
+        return Err(format!(
+          "objects with different keys ({} and {})",
+          describe_keys(reference),
+          describe_keys(val)
+        ));
+      }
+
+      for (reference, val) in reference.string_map.values().zip(val.string_map.values()) {
+        check_same_shape(reference, val)?;
+      }
+
+      for (reference, val) in reference.symbol_map.values().zip(val.symbol_map.values()) {
+        check_same_shape(reference, val)?;
+      }
+
+      Ok(())
+    }
+    _ => {
+      if reference.typeof_() != val.typeof_() {
+        return Err(format!(
+          "{} and {}",
+          with_article(reference.typeof_()),
+          with_article(val.typeof_())
+        ));
+      }
+
+      match reference.typeof_() {
+        VsType::Number | VsType::Bool => Ok(()),
+        _ => Err(format!(
+          "{}, which isn't a number or bool",
+          reference.codify()
+        )),
+      }
+    }
+  }
+}
+
+fn with_article(type_: VsType) -> String {
+  match type_ {
+    VsType::Undefined | VsType::Null => type_.to_string(),
+    VsType::Array | VsType::Object => format!("an {}", type_),
+    _ => format!("a {}", type_),
+  }
+}
+
+fn describe_keys(obj: &VsObject) -> String {
+  format!(
+    "{{{}}}",
+    obj
+      .string_map
+      .keys()
+      .cloned()
+      .collect::<Vec<_>>()
+      .join(", ")
+  )
+}
+
+/**
+ * The value as it would appear in the source, or just its type (and length, for arrays) if that
+ * would be too long for an error message.
+ */
+fn describe(val: &Val) -> String {
+  let codified = val.codify();
+
+  if codified.len() <= 60 {
+    return codified;
+  }
+
+  match val {
+    Val::Array(array) => format!("an array of length {}", array.elements.len()),
+    _ => format!("a large {}", val.typeof_()),
+  }
+}
+
+/**
+ * Produces a value with the same shape as `val` where every number is `0` and every bool is
+ * `false`. Other values are replaced by `undefined`.
+ */
+pub fn zero_like(val: &Val) -> Val {
+  match val {
+    Val::Array(arr) => arr
+      .elements
+      .iter()
+      .map(zero_like)
+      .collect::<Vec<_>>()
+      .to_val(),
+    Val::Object(obj) => VsObject {
+      string_map: obj
+        .string_map
+        .iter()
+        .map(|(k, v)| (k.clone(), zero_like(v)))
+        .collect(),
+      symbol_map: obj
+        .symbol_map
+        .iter()
+        .map(|(k, v)| (k.clone(), zero_like(v)))
+        .collect(),
+      prototype: obj.prototype.clone(),
+    }
+    .to_val(),
+    _ => match val.typeof_() {
+      VsType::Number => 0.0.to_val(),
+      VsType::Bool => false.to_val(),
+      _ => Val::Undefined,
+    },
+  }
+}
@@ -0,0 +1,63 @@
+use std::{collections::BTreeMap, rc::Rc};
+
+/**
+ * A span of source code, as byte offsets into the file at `path`.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceLocation {
+  pub path: Rc<str>,
+  pub lo: u32,
+  pub hi: u32,
+}
+
+/**
+ * One frame of a Summon-level call stack.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StackTraceFrame {
+  pub function_name: Rc<str>,
+  pub location: Option<SourceLocation>,
+}
+
+/**
+ * Maps bytecode positions to the source code they were compiled from.
+ *
+ * The assembler records where each function starts and where the code for each statement starts.
+ * A position maps to the last of these at or before it, so positions that aren't covered by a
+ * statement (e.g. the start of a function before its first statement) have no location.
+ */
+#[derive(Default)]
+pub struct SourceMap {
+  function_names: BTreeMap<usize, Rc<str>>,
+  locations: BTreeMap<usize, Option<SourceLocation>>,
+}
+
+impl SourceMap {
+  pub fn is_empty(&self) -> bool {
+    self.function_names.is_empty()
+  }
+
+  pub fn start_function(&mut self, pos: usize, name: &str) {
+    self.function_names.insert(pos, name.into());
+    self.locations.insert(pos, None);
+  }
+
+  pub fn set_location(&mut self, pos: usize, location: SourceLocation) {
+    self.locations.insert(pos, Some(location));
+  }
+
+  pub fn location(&self, pos: usize) -> Option<&SourceLocation> {
+    self.locations.range(..=pos).next_back()?.1.as_ref()
+  }
+
+  pub fn function_name(&self, pos: usize) -> Option<&Rc<str>> {
+    Some(self.function_names.range(..=pos).next_back()?.1)
+  }
+
+  pub fn stack_trace_frame(&self, pos: usize) -> Option<StackTraceFrame> {
+    Some(StackTraceFrame {
+      function_name: self.function_name(pos)?.clone(),
+      location: self.location(pos).cloned(),
+    })
+  }
+}
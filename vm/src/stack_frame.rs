@@ -1,5 +1,6 @@
 use std::any::Any;
 
+use super::numeric_model::NumericModel;
 use super::vs_value::Val;
 
 pub type StackFrame = Box<dyn StackFrameTrait>;
@@ -23,7 +24,8 @@ pub type FrameStepResult = Result<FrameStepOk, Val>;
 pub trait StackFrameTrait {
   fn write_this(&mut self, const_: bool, this: Val) -> Result<(), Val>;
   fn write_param(&mut self, param: Val);
-  fn step(&mut self) -> FrameStepResult;
+  /** Runs one instruction. Numbers known at compile time are computed with `numeric_model`. */
+  fn step(&mut self, numeric_model: &NumericModel) -> FrameStepResult;
   fn apply_call_result(&mut self, call_result: CallResult);
   fn get_call_result(&mut self) -> CallResult;
   fn can_catch_exception(&self, exception: &Val) -> bool;
@@ -55,7 +57,7 @@ impl StackFrameTrait for VoidStackFrame {
 
   fn write_param(&mut self, _param: Val) {}
 
-  fn step(&mut self) -> FrameStepResult {
+  fn step(&mut self, _numeric_model: &NumericModel) -> FrameStepResult {
     Ok(FrameStepOk::Continue)
   }
 
@@ -0,0 +1,384 @@
+use std::collections::BTreeMap;
+
+use crate::{
+  vs_value::{stringify_string, ToVal, Val, VsType},
+  ValTrait,
+};
+
+/** The largest width of `summon.uint(bits)` and `summon.int(bits)`. */
+pub const MAX_BITS: usize = 64;
+
+/**
+ * The types modelled by summon runtime type values like `summon.number()`.
+ *
+ * At runtime these are plain objects of the form `{ about: 'summon runtime type', json }`, where
+ * `json` describes the type. Scalars are described by a string (`"number"`, `"bool"`) and composite
+ * types are described by an object with a `type` field, for example:
+ *
+ * ```jsonc
+ * { "type": "uint", "bits": 8 }
+ * { "type": "int", "bits": 32 }
+ * { "type": "array", "element": "number", "length": 3 }
+ * { "type": "tuple", "elements": ["number", "bool"] }
+ * { "type": "object", "properties": { "x": "number", "y": "number" } }
+ * ```
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum SummonType {
+  Number,
+  Bool,
+  UInt(usize), // bits
+  Int(usize),  // bits (two's complement)
+  Array(Box<SummonType>, usize),
+  Tuple(Vec<SummonType>),
+  Object(BTreeMap<String, SummonType>),
+}
+
+impl SummonType {
+  pub fn from_val(val: &Val) -> Option<SummonType> {
+    let Val::Object(obj) = val else {
+      return None;
+    };
+
+    match obj.string_map.get("about") {
+      Some(Val::String(about)) if about.as_ref() == "summon runtime type" => {}
+      _ => return None,
+    };
+
+    SummonType::from_json_val(obj.string_map.get("json")?)
+  }
+
+  fn from_json_val(json: &Val) -> Option<SummonType> {
+    match json {
+      Val::String(name) => match name.as_ref() {
+        "number" => Some(SummonType::Number),
+        "bool" => Some(SummonType::Bool),
+        _ => None,
+      },
+      Val::Object(obj) => {
+        let Some(Val::String(type_)) = obj.string_map.get("type") else {
+          return None;
+        };
+
+        match type_.as_ref() {
+          "uint" | "int" => {
+            let bits = obj.string_map.get("bits")?.to_index()?;
+
+            if !(1..=MAX_BITS).contains(&bits) {
+              return None;
+            }
+
+            Some(match type_.as_ref() {
+              "uint" => SummonType::UInt(bits),
+              _ => SummonType::Int(bits),
+            })
+          }
+          "array" => {
+            let element = SummonType::from_json_val(obj.string_map.get("element")?)?;
+            let length = obj.string_map.get("length")?.to_index()?;
+
+            Some(SummonType::Array(Box::new(element), length))
+          }
+          "tuple" => {
+            let Some(Val::Array(elements)) = obj.string_map.get("elements") else {
+              return None;
+            };
+
+            let elements = elements
+              .elements
+              .iter()
+              .map(SummonType::from_json_val)
+              .collect::<Option<Vec<_>>>()?;
+
+            Some(SummonType::Tuple(elements))
+          }
+          "object" => {
+            let Some(Val::Object(properties)) = obj.string_map.get("properties") else {
+              return None;
+            };
+
+            let mut res = BTreeMap::<String, SummonType>::new();
+
+            for (key, value) in &properties.string_map {
+              res.insert(key.clone(), SummonType::from_json_val(value)?);
+            }
+
+            Some(SummonType::Object(res))
+          }
+          _ => None,
+        }
+      }
+      _ => None,
+    }
+  }
+
+  pub fn to_json(&self) -> serde_json::Value {
+    match self {
+      SummonType::Number => "number".into(),
+      SummonType::Bool => "bool".into(),
+      SummonType::UInt(bits) => serde_json::json!({ "type": "uint", "bits": bits }),
+      SummonType::Int(bits) => serde_json::json!({ "type": "int", "bits": bits }),
+      SummonType::Array(element, length) => serde_json::json!({
+        "type": "array",
+        "element": element.to_json(),
+        "length": length,
+      }),
+      SummonType::Tuple(elements) => serde_json::json!({
+        "type": "tuple",
+        "elements": elements.iter().map(|e| e.to_json()).collect::<Vec<_>>(),
+      }),
+      SummonType::Object(properties) => serde_json::json!({
+        "type": "object",
+        "properties": properties
+          .iter()
+          .map(|(k, v)| (k.clone(), v.to_json()))
+          .collect::<serde_json::Map<_, _>>(),
+      }),
+    }
+  }
+
+  fn to_json_val(&self) -> Val {
+    match self {
+      SummonType::Number => "number".to_val(),
+      SummonType::Bool => "bool".to_val(),
+      SummonType::UInt(bits) => {
+        Val::make_object(&[("type", "uint".to_val()), ("bits", (*bits as f64).to_val())])
+      }
+      SummonType::Int(bits) => {
+        Val::make_object(&[("type", "int".to_val()), ("bits", (*bits as f64).to_val())])
+      }
+      SummonType::Array(element, length) => Val::make_object(&[
+        ("type", "array".to_val()),
+        ("element", element.to_json_val()),
+        ("length", (*length as f64).to_val()),
+      ]),
+      SummonType::Tuple(elements) => Val::make_object(&[
+        ("type", "tuple".to_val()),
+        (
+          "elements",
+          elements
+            .iter()
+            .map(|e| e.to_json_val())
+            .collect::<Vec<_>>()
+            .to_val(),
+        ),
+      ]),
+      SummonType::Object(properties) => Val::make_object(&[
+        ("type", "object".to_val()),
+        (
+          "properties",
+          Val::make_object(
+            &properties
+              .iter()
+              .map(|(k, v)| (k.as_str(), v.to_json_val()))
+              .collect::<Vec<_>>(),
+          ),
+        ),
+      ]),
+    }
+  }
+
+  pub fn is_scalar(&self) -> bool {
+    matches!(
+      self,
+      SummonType::Number | SummonType::Bool | SummonType::UInt(_) | SummonType::Int(_)
+    )
+  }
+
+  /**
+   * The number of bits needed to represent a scalar of this type, if known. `number` doesn't have
+   * a declared width, so it gets `None`.
+   */
+  pub fn bit_width(&self) -> Option<usize> {
+    match self {
+      SummonType::Bool => Some(1),
+      SummonType::UInt(bits) | SummonType::Int(bits) => Some(*bits),
+      _ => None,
+    }
+  }
+
+  pub fn is_signed(&self) -> bool {
+    matches!(self, SummonType::Int(_))
+  }
+
+  /**
+   * Lists the scalar leaves of this type in a deterministic order, each with a path describing its
+   * position relative to `path`, e.g. `x[0].y`.
+   */
+  pub fn leaves(&self, path: &str) -> Vec<(String, SummonType)> {
+    let mut res = Vec::new();
+    self.leaves_impl(path.to_string(), &mut res);
+    res
+  }
+
+  fn leaves_impl(&self, path: String, res: &mut Vec<(String, SummonType)>) {
+    match self {
+      SummonType::Number | SummonType::Bool | SummonType::UInt(_) | SummonType::Int(_) => {
+        res.push((path, self.clone()))
+      }
+      SummonType::Array(element, length) => {
+        for i in 0..*length {
+          element.leaves_impl(format!("{}[{}]", path, i), res);
+        }
+      }
+      SummonType::Tuple(elements) => {
+        for (i, element) in elements.iter().enumerate() {
+          element.leaves_impl(format!("{}[{}]", path, i), res);
+        }
+      }
+      SummonType::Object(properties) => {
+        for (key, value) in properties {
+          value.leaves_impl(path_with_key(&path, key), res);
+        }
+      }
+    }
+  }
+
+  /**
+   * Builds a value of this type from its leaves, which must be provided in the order given by
+   * `leaves`.
+   */
+  pub fn assemble(&self, leaves: &mut impl Iterator<Item = Val>) -> Val {
+    match self {
+      SummonType::Number | SummonType::Bool | SummonType::UInt(_) | SummonType::Int(_) => {
+        leaves.next().unwrap_or(Val::Undefined)
+      }
+      SummonType::Array(element, length) => (0..*length)
+        .map(|_| element.assemble(leaves))
+        .collect::<Vec<_>>()
+        .to_val(),
+      SummonType::Tuple(elements) => elements
+        .iter()
+        .map(|element| element.assemble(leaves))
+        .collect::<Vec<_>>()
+        .to_val(),
+      SummonType::Object(properties) => {
+        let values = properties
+          .iter()
+          .map(|(key, value)| (key.as_str(), value.assemble(leaves)))
+          .collect::<Vec<_>>();
+
+        Val::make_object(&values)
+      }
+    }
+  }
+
+  /**
+   * Infers the type of a value made of numbers, bools, arrays and objects (where numbers and bools
+   * may also be signals), pushing its leaves onto `leaves` in the order given by `leaves`.
+   *
+   * Arrays whose elements all have the same type are described as arrays, other arrays are
+   * described as tuples.
+   */
+  pub fn flatten(value: &Val, path: &str, leaves: &mut Vec<(String, Val)>) -> Option<SummonType> {
+    match value {
+      Val::Array(arr) => {
+        let mut element_types = Vec::<SummonType>::new();
+
+        for (i, element) in arr.elements.iter().enumerate() {
+          element_types.push(SummonType::flatten(
+            element,
+            &format!("{}[{}]", path, i),
+            leaves,
+          )?);
+        }
+
+        match element_types.first() {
+          Some(first) if element_types.iter().all(|t| t == first) => Some(SummonType::Array(
+            Box::new(first.clone()),
+            element_types.len(),
+          )),
+          _ => Some(SummonType::Tuple(element_types)),
+        }
+      }
+      Val::Object(obj) => {
+        let mut properties = BTreeMap::<String, SummonType>::new();
+
+        for (key, value) in &obj.string_map {
+          properties.insert(
+            key.clone(),
+            SummonType::flatten(value, &path_with_key(path, key), leaves)?,
+          );
+        }
+
+        Some(SummonType::Object(properties))
+      }
+      _ => {
+        let type_ = match value.typeof_() {
+          VsType::Number => SummonType::Number,
+          VsType::Bool => SummonType::Bool,
+          _ => return None,
+        };
+
+        leaves.push((path.to_string(), value.clone()));
+
+        Some(type_)
+      }
+    }
+  }
+
+  /**
+   * Checks whether a (non-signal) value matches this type.
+   */
+  pub fn check(&self, value: &Val) -> bool {
+    match (self, value) {
+      (SummonType::Number, Val::Number(_)) => true,
+      (SummonType::Bool, Val::Bool(_)) => true,
+      (SummonType::UInt(bits), Val::Number(x)) => {
+        x.fract() == 0.0 && *x >= 0.0 && *x < 2f64.powi(*bits as i32)
+      }
+      (SummonType::Int(bits), Val::Number(x)) => {
+        let half = 2f64.powi(*bits as i32 - 1);
+        x.fract() == 0.0 && *x >= -half && *x < half
+      }
+      (SummonType::Array(element, length), Val::Array(arr)) => {
+        arr.elements.len() == *length && arr.elements.iter().all(|e| element.check(e))
+      }
+      (SummonType::Tuple(elements), Val::Array(arr)) => {
+        arr.elements.len() == elements.len()
+          && elements
+            .iter()
+            .zip(arr.elements.iter())
+            .all(|(t, e)| t.check(e))
+      }
+      (SummonType::Object(properties), Val::Object(obj)) => {
+        obj.string_map.len() == properties.len()
+          && properties
+            .iter()
+            .all(|(key, t)| match obj.string_map.get(key) {
+              Some(v) => t.check(v),
+              None => false,
+            })
+      }
+      _ => false,
+    }
+  }
+}
+
+impl ToVal for SummonType {
+  fn to_val(self) -> Val {
+    Val::make_object(&[
+      ("about", "summon runtime type".to_val()),
+      ("json", self.to_json_val()),
+    ])
+  }
+}
+
+/**
+ * Appends a property to a path, e.g. `x.y` or `x["not an identifier"]`.
+ */
+pub fn path_with_key(path: &str, key: &str) -> String {
+  let is_ident = key
+    .chars()
+    .next()
+    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+    && key
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+
+  if is_ident {
+    format!("{}.{}", path, key)
+  } else {
+    format!("{}[{}]", path, stringify_string(key))
+  }
+}
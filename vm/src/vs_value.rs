@@ -41,7 +41,7 @@ pub enum Val {
   CopyCounter(Box<CopyCounter>),
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub enum VsType {
   Undefined,
   Null,
@@ -85,7 +85,7 @@ pub trait ValTrait: fmt::Display {
   fn to_number(&self) -> f64;
   fn to_index(&self) -> Option<usize>;
   fn is_primitive(&self) -> bool;
-  fn is_truthy(&self) -> bool;
+  fn is_truthy(&self) -> Result<bool, Val>;
   fn is_nullish(&self) -> bool;
 
   fn bind(&self, params: Vec<Val>) -> Option<Val>;
@@ -368,10 +368,10 @@ impl ValTrait for Val {
     }
   }
 
-  fn is_truthy(&self) -> bool {
+  fn is_truthy(&self) -> Result<bool, Val> {
     use Val::*;
 
-    match self {
+    Ok(match self {
       Void => false,
       Undefined => false,
       Null => false,
@@ -384,10 +384,10 @@ impl ValTrait for Val {
       Object(_) => true,
       Function(_) => true,
       Class(_) => true,
-      Static(val) => val.is_truthy(), // TODO: true?
-      Dynamic(val) => val.is_truthy(),
+      Static(val) => val.is_truthy()?, // TODO: true?
+      Dynamic(val) => val.is_truthy()?,
       CopyCounter(_) => true,
-    }
+    })
   }
 
   fn is_nullish(&self) -> bool {